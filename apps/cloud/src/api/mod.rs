@@ -16,9 +16,9 @@ use axum::{
 use chrono::{Duration, Utc};
 use cloud_database::{
     Claims, MakeToken, RefreshToken, UpdateWorkspace, User, UserQuery, UserToken,
-    WorkspaceSearchInput,
+    WorkspaceSearchInput, WorkspaceSearchResults,
 };
-use jwst::{error, BlobStorage, JwstError};
+use jwst::{error, BlobStorage, JwstError, SearchError};
 use jwst_logger::{instrument, info, tracing};
 use lib0::any::Any;
 use std::sync::Arc;
@@ -33,6 +33,7 @@ use utoipa::OpenApi;
         update_workspace,
         delete_workspace,
         search_workspace,
+        search_workspaces,
         query_user,
         make_token,
         get_doc,
@@ -98,6 +99,7 @@ pub fn make_rest_route(ctx: Arc<Context>) -> Router {
                 )
                 .route("/workspace/:id/doc", get(get_doc))
                 .route("/workspace/:id/search", post(search_workspace))
+                .route("/search", post(search_workspaces))
                 .route("/workspace/:id/blob", put(blobs::upload_blob_in_workspace))
                 .route("/permission/:id", delete(permissions::remove_user))
                 .layer(make_firebase_auth_layer(ctx.key.jwt_decode.clone())),
@@ -673,6 +675,10 @@ async fn get_workspace_doc(ctx: Arc<Context>, workspace_id: String) -> Response
     path = "/{workspace_id}/search",
     request_body(content = WorkspaceSearchInput, description = "Request body for search workspace",content_type = "application/json",example = json!({
         "query": "string",
+        "offset": 0,
+        "limit": 10,
+        "space": "blocks",
+        "flavor": "affine:paragraph",
     }
     )),
     params(
@@ -682,7 +688,11 @@ async fn get_workspace_doc(ctx: Arc<Context>, workspace_id: String) -> Response
         (status = 200, description = "Workspace's data", body = SearchResults,
         example=json!([{
          "block_id": "xxxx",
+         "space_id": "blocks",
+         "flavor": "affine:paragraph",
          "score": "f32",
+         "field": "text",
+         "snippet": "<b>string</b>",
         }]
         )),
         (status = 400, description = "Request parameter error."),
@@ -716,10 +726,90 @@ pub async fn search_workspace(
         }
     };
 
-    let search_results = match ctx.search_workspace(workspace_id, &payload.query).await {
+    let search_results = match ctx
+        .search_workspace(workspace_id, &payload.query, &payload.options)
+        .await
+    {
         Ok(results) => results,
         Err(err) => return err.to_string().into_response(),
     };
 
     Json(search_results).into_response()
 }
+
+/// search in all workspaces the user can read
+/// - Return blocks of all workspaces ranked by score
+#[utoipa::path(
+    post,
+    tag = "Workspace",
+    context_path = "/api",
+    path = "/search",
+    request_body(content = WorkspaceSearchInput, description = "Request body for search workspaces",content_type = "application/json",example = json!({
+        "query": "string",
+        "offset": 0,
+        "limit": 10,
+    }
+    )),
+    responses(
+        (status = 200, description = "Search results of all workspaces", body = WorkspaceSearchResults,
+        example=json!({
+          "items": [{
+            "workspace_id": "xxxx",
+            "block_id": "xxxx",
+            "space_id": "blocks",
+            "flavor": "affine:paragraph",
+            "score": "f32",
+            "field": "text",
+            "snippet": "<b>string</b>",
+          }]
+        }
+        )),
+        (status = 400, description = "Request parameter error."),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Server error, please try again later.")
+    )
+)]
+#[instrument(
+    skip(ctx, claims),
+    fields(
+        user_id = %claims.user.id,
+    )
+)]
+pub async fn search_workspaces(
+    Extension(ctx): Extension<Arc<Context>>,
+    Extension(claims): Extension<Arc<Claims>>,
+    Json(payload): Json<WorkspaceSearchInput>,
+) -> Response {
+    info!("search_workspaces enter");
+    let workspaces = match ctx.db.get_user_workspaces(claims.user.id.clone()).await {
+        Ok(workspaces) => workspaces,
+        Err(e) => {
+            error!("Failed to get workspaces: {:?}", e);
+            return ErrorStatus::InternalServerError.into_response();
+        }
+    };
+
+    // the user can read all workspaces they accepted
+    let workspace_ids = workspaces
+        .into_iter()
+        .map(|workspace| workspace.id)
+        .collect();
+
+    match ctx
+        .search_workspaces(workspace_ids, &payload.query, &payload.options)
+        .await
+    {
+        Ok(items) => Json(WorkspaceSearchResults { items }).into_response(),
+        Err(e) => {
+            error!("Failed to search workspaces: {:?}", e);
+            // the query, offset or limit is invalid
+            if e.downcast_ref::<SearchError>()
+                .map_or(false, SearchError::is_invalid_query)
+            {
+                ErrorStatus::BadRequest.into_response()
+            } else {
+                ErrorStatus::InternalServerError.into_response()
+            }
+        }
+    }
+}
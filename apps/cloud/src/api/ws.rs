@@ -6,7 +6,7 @@ use axum::{
     },
     response::Response,
 };
-use jwst_rpc::{handle_connector, socket_connector};
+use jwst_rpc::{handle_connector, socket_connector, SessionAccess};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -73,9 +73,13 @@ async fn ws_handler(
                 return;
             };
 
-            handle_connector(ctx.clone(), workspace.clone(), user_id, move || {
-                socket_connector(socket, &workspace)
-            })
+            handle_connector(
+                ctx.clone(),
+                workspace.clone(),
+                user_id,
+                SessionAccess::ReadWrite,
+                move || socket_connector(socket, &workspace),
+            )
             .await
         })
 }
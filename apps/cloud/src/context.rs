@@ -1,10 +1,10 @@
 use cloud_components::{FirebaseContext, KeyContext, MailContext};
-use cloud_database::CloudDatabase;
-use jwst::SearchResults;
-use jwst_logger::{error, warn};
-use jwst_rpc::{BroadcastChannels, BroadcastType, RpcContextImpl};
+use cloud_database::{CloudDatabase, WorkspaceSearchResult};
+use jwst::{JwstError, SearchError, SearchOptions, SearchResults};
+use jwst_logger::{error, info, warn};
+use jwst_rpc::{BroadcastBridge, BroadcastChannels, BroadcastType, NetworkBackend, RpcContextImpl};
 use jwst_storage::JwstStorage;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use tempfile::{tempdir, TempDir};
 use tokio::sync::{Mutex, RwLock};
 
@@ -18,6 +18,7 @@ pub struct Context {
     pub storage: JwstStorage,
     pub user_channel: UserChannel,
     pub channel: BroadcastChannels,
+    pub bridge: Option<BroadcastBridge>,
     _dir: Option<TempDir>,
 }
 
@@ -40,13 +41,31 @@ impl Context {
             (Some(dir), cloud, storage)
         };
 
+        // the search indexes are persisted in this directory, or stored in memory if not set
+        let search_index_root = dotenvy::var("SEARCH_INDEX_DIR").ok().map(|dir| {
+            info!("use search index directory: {}", dir);
+            PathBuf::from(dir)
+        });
+
+        let bridge = if let Ok(addr) = dotenvy::var("BROADCAST_BROKER") {
+            info!("share broadcast by broker: {}", addr);
+            // the secret of the broker, see `BROADCAST_BROKER_SECRET` of keck
+            let secret = dotenvy::var("BROADCAST_BROKER_SECRET").ok();
+            let backend = NetworkBackend::connect(&addr, secret)
+                .await
+                .expect("Cannot connect to broadcast broker");
+            Some(BroadcastBridge::new(Arc::new(backend)))
+        } else {
+            None
+        };
+
         Self {
             _dir,
             // =========== database ===========
             db: CloudDatabase::init_pool(&cloud)
                 .await
                 .expect("Cannot create cloud database"),
-            storage: JwstStorage::new(&storage)
+            storage: JwstStorage::new_with_search_index(&storage, search_index_root)
                 .await
                 .expect("Cannot create storage"),
             // =========== auth ===========
@@ -65,6 +84,7 @@ impl Context {
             ),
             // =========== sync channel ===========
             channel: RwLock::new(HashMap::new()),
+            bridge,
             user_channel: UserChannel::new(),
         }
     }
@@ -73,12 +93,13 @@ impl Context {
         &self,
         workspace_id: String,
         query_string: &str,
+        options: &SearchOptions,
     ) -> Result<SearchResults, Box<dyn std::error::Error>> {
         let workspace_id = workspace_id.to_string();
 
         match self.storage.get_workspace(workspace_id.clone()).await {
             Ok(workspace) => {
-                let search_results = workspace.search(query_string)?;
+                let search_results = workspace.search_with_options(query_string, options)?;
                 Ok(search_results)
             }
             Err(e) => {
@@ -88,6 +109,63 @@ impl Context {
         }
     }
 
+    /// Search in multiple workspaces, results of all workspaces are ranked by score
+    /// before pagination. Workspaces which no longer exist will be skipped.
+    ///
+    /// Every workspace has its own index, so the scores of different workspaces are
+    /// only roughly comparable. Only the first [SearchOptions::MAX_LIMIT] results
+    /// can be paginated, the larger `offset` and `limit` are rejected.
+    pub async fn search_workspaces(
+        &self,
+        workspace_ids: Vec<String>,
+        query_string: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<WorkspaceSearchResult>, Box<dyn std::error::Error>> {
+        // every workspace may contribute all results of the requested page
+        let limit = options.offset.saturating_add(options.result_limit());
+        if limit > SearchOptions::MAX_LIMIT {
+            return Err(Box::new(SearchError::InvalidQuery(format!(
+                "offset and limit exceed the first {} results",
+                SearchOptions::MAX_LIMIT
+            ))));
+        }
+        let workspace_options = SearchOptions {
+            offset: 0,
+            limit: Some(limit),
+            ..options.clone()
+        };
+
+        let mut results = vec![];
+        for workspace_id in workspace_ids {
+            let workspace = match self.storage.get_workspace(&workspace_id).await {
+                Ok(workspace) => workspace,
+                Err(JwstError::WorkspaceNotFound(_)) => {
+                    warn!("skip search in not exists workspace: {}", workspace_id);
+                    continue;
+                }
+                Err(e) => {
+                    error!("cannot get workspace: {}", e);
+                    return Err(Box::new(e));
+                }
+            };
+            let search_results = workspace.search_with_options(query_string, &workspace_options)?;
+            results.extend(search_results.items().iter().cloned().map(|result| {
+                WorkspaceSearchResult {
+                    workspace_id: workspace_id.clone(),
+                    result,
+                }
+            }));
+        }
+
+        results.sort_by(|a, b| b.result.score.total_cmp(&a.result.score));
+
+        Ok(results
+            .into_iter()
+            .skip(options.offset)
+            .take(options.result_limit())
+            .collect())
+    }
+
     // TODO: this should be moved to another module
     pub async fn close_websocket(&self, workspace: String, user: String) {
         let mut closed = vec![];
@@ -128,4 +206,8 @@ impl RpcContextImpl<'_> for Context {
     fn get_channel(&self) -> &BroadcastChannels {
         &self.channel
     }
+
+    fn get_bridge(&self) -> Option<&BroadcastBridge> {
+        self.bridge.as_ref()
+    }
 }
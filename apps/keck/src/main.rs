@@ -9,5 +9,11 @@ static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 async fn main() {
     init_logger();
     jwst::print_versions(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-    server::start_server().await;
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("check") => server::check_integrity(args.collect()).await,
+        Some("token") => server::sign_token(args.collect()),
+        _ => server::start_server().await,
+    }
 }
@@ -487,3 +487,326 @@ pub async fn remove_block_children(
         StatusCode::NOT_FOUND.into_response()
     }
 }
+
+/// Get rich text content of `Block`'s property
+/// - Return 200 and the text delta if the property is a rich text.
+/// - Return 404 Not Found if `Workspace`, `Block` or rich text property not exists.
+#[utoipa::path(
+    get,
+    tag = "Blocks",
+    context_path = "/api/block",
+    path = "/{workspace}/{block}/text/{key}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("block", description = "block id"),
+        ("key", description = "property name"),
+    ),
+    responses(
+        (status = 200, description = "Get text delta", body = [TextDelta]),
+        (status = 404, description = "Workspace, block or text not found"),
+    )
+)]
+pub async fn get_block_text(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String, String)>,
+) -> Response {
+    let (ws_id, block, key) = params;
+    info!("get_block_text: {}, {}, {}", ws_id, block, key);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        workspace.with_trx(|mut t| {
+            if let Some(delta) = t
+                .get_blocks()
+                .get(&t.trx, block)
+                .and_then(|block| block.text_delta(&t.trx, &key))
+            {
+                Json(delta).into_response()
+            } else {
+                StatusCode::NOT_FOUND.into_response()
+            }
+        })
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Edit rich text content of `Block`'s property
+///
+/// Unlike setting the property through `Block` api, edits are merged with concurrent changes of other clients.
+/// A plain string property will be converted into rich text on first edit.
+/// - Return 200 and the text delta if edit successful.
+/// - Return 400 Bad Request if the property isn't a text, or the edit is out of text range.
+/// - Return 404 Not Found if `Workspace` or `Block` not exists.
+#[utoipa::path(
+    post,
+    tag = "Blocks",
+    context_path = "/api/block",
+    path = "/{workspace}/{block}/text/{key}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("block", description = "block id"),
+        ("key", description = "property name"),
+    ),
+    request_body(
+        content = TextOperation,
+        description = "json",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Text edited", body = [TextDelta]),
+        (status = 400, description = "Not a text or edit out of range"),
+        (status = 404, description = "Workspace or block not found"),
+    )
+)]
+pub async fn edit_block_text(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String, String)>,
+    Json(payload): Json<TextOperation>,
+) -> Response {
+    let (ws_id, block, key) = params;
+    info!("edit_block_text: {}, {}, {}", ws_id, block, key);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        let result = workspace.with_trx(|mut t| -> Result<_, StatusCode> {
+            let block = t
+                .get_blocks()
+                .get(&t.trx, block)
+                .ok_or(StatusCode::NOT_FOUND)?;
+            // a no-op edit within the text succeeds without changes
+            let text_len = block.text_len(&t.trx, &key);
+            let in_range = |index: u32| {
+                if text_len.map_or(false, |len| index <= len) {
+                    Ok(())
+                } else {
+                    Err(StatusCode::BAD_REQUEST)
+                }
+            };
+
+            let changed = match payload {
+                TextOperation::Insert {
+                    index,
+                    text,
+                    attributes,
+                } => {
+                    let inserted = if let Some(attributes) = attributes {
+                        block.text_insert_with_format(&mut t.trx, &key, index, &text, attributes)
+                    } else {
+                        block.text_insert(&mut t.trx, &key, index, &text)
+                    };
+                    if !inserted {
+                        // the property is neither a rich text nor a plain string
+                        return Err(StatusCode::BAD_REQUEST);
+                    }
+                    true
+                }
+                TextOperation::Delete { index, length } => {
+                    in_range(index)?;
+                    block.text_delete(&mut t.trx, &key, index, length)
+                }
+                TextOperation::Format {
+                    index,
+                    length,
+                    attributes,
+                } => {
+                    in_range(index)?;
+                    block.text_format(&mut t.trx, &key, index, length, attributes)
+                }
+                TextOperation::RemoveFormat {
+                    index,
+                    length,
+                    attributes,
+                } => {
+                    in_range(index)?;
+                    block.text_remove_format(&mut t.trx, &key, index, length, attributes)
+                }
+            };
+
+            let delta = block.text_delta(&t.trx, &key);
+            let update = changed.then(|| t.trx.encode_update_v1());
+
+            Ok((delta, update))
+        });
+
+        match result {
+            Ok((delta, update)) => {
+                if let Some(update) = update {
+                    if let Err(e) = context.storage.docs().write_update(ws_id, &update).await {
+                        error!("db write error: {}", e.to_string());
+                    }
+                }
+                Json(delta.unwrap_or_default()).into_response()
+            }
+            Err(status) => status.into_response(),
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Move `Block` and its descendants to another parent or space
+/// - Return 200 and `Block`'s data if move successful.
+/// - Return 400 Bad Request if the new parent is the block itself or its descendant,
+///   or the block id already exists in the target space.
+/// - Return 404 Not Found if `Workspace`, `Block` or parent `Block` not exists.
+#[utoipa::path(
+    post,
+    tag = "Blocks",
+    context_path = "/api/block",
+    path = "/{workspace}/{block}/move",
+    params(
+        ("workspace", description = "workspace id"),
+        ("block", description = "block id"),
+    ),
+    request_body(
+        content = MoveBlock,
+        description = "json",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Block moved", body = Block),
+        (status = 400, description = "Failed to move block"),
+        (status = 404, description = "Workspace, block or parent not found"),
+    )
+)]
+pub async fn move_block(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+    Json(payload): Json<MoveBlock>,
+) -> Response {
+    let (ws_id, block) = params;
+    info!("move_block: {}, {}", ws_id, block);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        let result = workspace.with_trx(|mut t| -> Result<_, StatusCode> {
+            let space = t.get_blocks();
+            let block = space.get(&t.trx, &block).ok_or(StatusCode::NOT_FOUND)?;
+
+            let target = match &payload.space {
+                Some(space_id) => t.get_space(space_id),
+                None => t.get_blocks(),
+            };
+            let parent = match &payload.parent {
+                Some(parent) => Some(target.get(&t.trx, parent).ok_or(StatusCode::NOT_FOUND)?),
+                None => None,
+            };
+
+            let block = block
+                .move_into_space(&mut t.trx, &target, parent.as_ref(), payload.pos)
+                .ok_or(StatusCode::BAD_REQUEST)?;
+
+            Ok((block, t.trx.encode_update_v1()))
+        });
+
+        match result {
+            Ok((block, update)) => {
+                if let Err(e) = context.storage.docs().write_update(ws_id, &update).await {
+                    error!("db write error: {}", e.to_string());
+                }
+                Json(block).into_response()
+            }
+            Err(status) => status.into_response(),
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Duplicate `Block` and its descendants with new block ids
+/// - Return 200 and the duplicated `Block`'s data if duplicate successful.
+/// - Return 400 Bad Request if the parent doesn't belong to the target space.
+/// - Return 404 Not Found if `Workspace`, `Block` or parent `Block` not exists.
+#[utoipa::path(
+    post,
+    tag = "Blocks",
+    context_path = "/api/block",
+    path = "/{workspace}/{block}/duplicate",
+    params(
+        ("workspace", description = "workspace id"),
+        ("block", description = "block id"),
+    ),
+    request_body(
+        content = DuplicateBlock,
+        description = "json",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Block duplicated", body = Block),
+        (status = 400, description = "Failed to duplicate block"),
+        (status = 404, description = "Workspace, block or parent not found"),
+    )
+)]
+pub async fn duplicate_block(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+    Json(payload): Json<DuplicateBlock>,
+) -> Response {
+    let (ws_id, block) = params;
+    info!("duplicate_block: {}, {}", ws_id, block);
+    let Ok(workspace) = context.storage.get_workspace(&ws_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let target_id = payload.workspace.clone().unwrap_or_else(|| ws_id.clone());
+    let result = if target_id == ws_id {
+        workspace.with_trx(|mut t| -> Result<_, StatusCode> {
+            let block = t
+                .get_blocks()
+                .get(&t.trx, &block)
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            let target = match &payload.space {
+                Some(space_id) => t.get_space(space_id),
+                None => t.get_blocks(),
+            };
+            let parent = match &payload.parent {
+                Some(parent) => Some(target.get(&t.trx, parent).ok_or(StatusCode::NOT_FOUND)?),
+                None => None,
+            };
+
+            let block = block
+                .duplicate(&mut t.trx, &target, parent.as_ref(), payload.pos)
+                .ok_or(StatusCode::BAD_REQUEST)?;
+
+            Ok((block, t.trx.encode_update_v1()))
+        })
+    } else {
+        let Ok(target_workspace) = context.storage.get_workspace(&target_id).await else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        workspace.with_trx(|mut s| -> Result<_, StatusCode> {
+            let block = s
+                .get_blocks()
+                .get(&s.trx, &block)
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            target_workspace.with_trx(|mut t| -> Result<_, StatusCode> {
+                let target = match &payload.space {
+                    Some(space_id) => t.get_space(space_id),
+                    None => t.get_blocks(),
+                };
+                let parent = match &payload.parent {
+                    Some(parent) => Some(target.get(&t.trx, parent).ok_or(StatusCode::NOT_FOUND)?),
+                    None => None,
+                };
+
+                let block = block
+                    .clone_into(&s.trx, &mut t.trx, &target, parent.as_ref(), payload.pos)
+                    .ok_or(StatusCode::BAD_REQUEST)?;
+
+                Ok((block, t.trx.encode_update_v1()))
+            })
+        })
+    };
+
+    match result {
+        Ok((block, update)) => {
+            if let Err(e) = context
+                .storage
+                .docs()
+                .write_update(target_id, &update)
+                .await
+            {
+                error!("db write error: {}", e.to_string());
+            }
+            Json(block).into_response()
+        }
+        Err(status) => status.into_response(),
+    }
+}
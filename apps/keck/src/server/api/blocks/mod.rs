@@ -1,19 +1,26 @@
 mod block;
 mod schema;
+mod template;
 mod workspace;
 
 pub use block::{
-    delete_block, get_block, get_block_history, insert_block_children, remove_block_children,
-    set_block,
+    delete_block, duplicate_block, edit_block_text, get_block, get_block_history, get_block_text,
+    insert_block_children, move_block, remove_block_children, set_block,
+};
+pub use template::{
+    delete_template, get_template, get_templates, instantiate_template, save_template,
 };
 pub use workspace::{
-    delete_workspace, get_workspace, history_workspace, history_workspace_clients, set_workspace,
-    workspace_client,
+    check_workspace_integrity, delete_workspace, get_workspace, history_workspace,
+    history_workspace_clients, repair_workspace_integrity, set_workspace, workspace_client,
 };
 
 use super::*;
 use jwst_static::with_api_doc_v2;
-use schema::InsertChildren;
+use schema::{
+    DuplicateBlock, InsertChildren, InstantiateTemplate, LoadedWorkspace, MoveBlock, SaveTemplate,
+    StructuredSearch, TextOperation,
+};
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
@@ -27,6 +34,17 @@ use utoipa::OpenApi;
         workspace::history_workspace,
         workspace::get_workspace_block,
         workspace::workspace_search,
+        workspace::workspace_structured_search,
+        workspace::get_search_analyzer,
+        workspace::set_search_analyzer,
+        workspace::get_search_fields,
+        workspace::set_search_fields,
+        workspace::check_workspace_integrity,
+        workspace::repair_workspace_integrity,
+        workspace::get_page_references,
+        workspace::get_page_backlinks,
+        workspace::check_workspace_links,
+        workspace::get_loaded_workspaces,
         block::get_block,
         block::set_block_with_flavour,
         block::get_block_by_flavour,
@@ -36,18 +54,31 @@ use utoipa::OpenApi;
         block::delete_block,
         block::insert_block_children,
         block::remove_block_children,
+        block::get_block_text,
+        block::edit_block_text,
+        block::move_block,
+        block::duplicate_block,
+        template::get_templates,
+        template::get_template,
+        template::save_template,
+        template::delete_template,
+        template::instantiate_template,
     ),
     components(
         schemas(
-            schema::InsertChildren,
-            schema::Workspace, schema::Block, schema::BlockRawHistory,
-            jwst::BlockHistory, jwst::HistoryOperation, jwst::RawHistory,
-            jwst::SearchResults, jwst::SearchResult
+            schema::InsertChildren, schema::TextOperation, schema::MoveBlock, schema::DuplicateBlock,
+            schema::SaveTemplate, schema::InstantiateTemplate, schema::StructuredSearch,
+            schema::Workspace, schema::Block, schema::BlockRawHistory, schema::LoadedWorkspace,
+            jwst::BlockHistory, jwst::HistoryOperation, jwst::RawHistory, jwst::TextDelta, jwst::Template,
+            jwst::IntegrityReport, jwst::IntegrityIssue, jwst::PageLink,
+            jwst::SearchResults, jwst::SearchResult, jwst::SearchAnalyzer, jwst::SearchQuery,
+            jwst::SearchOptions, jwst::SearchRange, jwst::SearchFieldType
         )
     ),
     tags(
         (name = "Workspace", description = "Read and write remote workspace"),
-        (name = "Blocks", description = "Read and write remote blocks")
+        (name = "Blocks", description = "Read and write remote blocks"),
+        (name = "Templates", description = "Save and instantiate block templates")
     )
 )]
 struct ApiDoc;
@@ -72,7 +103,13 @@ fn block_apis(router: Router) -> Router {
             "/children",
             get(block::get_block_children).post(block::insert_block_children),
         )
-        .route("/children/:children", delete(block::remove_block_children));
+        .route("/children/:children", delete(block::remove_block_children))
+        .route("/move", post(block::move_block))
+        .route("/duplicate", post(block::duplicate_block))
+        .route(
+            "/text/:key",
+            get(block::get_block_text).post(block::edit_block_text),
+        );
 
     doc_apis(router)
         .nest("/block/:workspace/:block/", block_operation)
@@ -110,13 +147,56 @@ fn workspace_apis(router: Router) -> Router {
             "/block/:workspace/blocks",
             get(workspace::get_workspace_block),
         )
-        .route("/search/:workspace", get(workspace::workspace_search))
+        .route(
+            "/admin/:workspace/integrity",
+            get(workspace::check_workspace_integrity).post(workspace::repair_workspace_integrity),
+        )
+        .route(
+            "/admin/:workspace/links",
+            get(workspace::check_workspace_links),
+        )
+        .route("/admin/workspaces", get(workspace::get_loaded_workspaces))
+        .route(
+            "/references/:workspace/:page",
+            get(workspace::get_page_references),
+        )
+        .route(
+            "/references/:workspace/:page/backlinks",
+            get(workspace::get_page_backlinks),
+        )
+        .route(
+            "/search/:workspace",
+            get(workspace::workspace_search).post(workspace::workspace_structured_search),
+        )
         .route(
             "/search/:workspace/index",
             get(workspace::get_search_index).post(workspace::set_search_index),
         )
+        .route(
+            "/search/:workspace/analyzer",
+            get(workspace::get_search_analyzer).post(workspace::set_search_analyzer),
+        )
+        .route(
+            "/search/:workspace/fields",
+            get(workspace::get_search_fields).post(workspace::set_search_fields),
+        )
+}
+
+fn template_apis(router: Router) -> Router {
+    router
+        .route("/template/:workspace", get(template::get_templates))
+        .route(
+            "/template/:workspace/:name",
+            get(template::get_template)
+                .post(template::save_template)
+                .delete(template::delete_template),
+        )
+        .route(
+            "/template/:workspace/:name/instantiate",
+            post(template::instantiate_template),
+        )
 }
 
 pub fn blocks_apis(router: Router) -> Router {
-    workspace_apis(block_apis(router))
+    template_apis(workspace_apis(block_apis(router)))
 }
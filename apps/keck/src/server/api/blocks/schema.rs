@@ -1,6 +1,8 @@
 pub use std::collections::HashMap;
 
-use serde::Deserialize;
+use jwst::{SearchOptions, SearchQuery};
+use lib0::any::Any;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 #[derive(Default, Deserialize, PartialEq, Debug, ToSchema)]
@@ -39,3 +41,97 @@ pub enum InsertChildren {
     InsertAfter { id: String, after: String },
     InsertAt { id: String, pos: u32 },
 }
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"Insert": {"index": 0, "text": "hello", "attributes": {"bold": true}}}))]
+pub enum TextOperation {
+    Insert {
+        index: u32,
+        text: String,
+        #[serde(default)]
+        #[schema(value_type = Object)]
+        attributes: Option<HashMap<String, Any>>,
+    },
+    Delete {
+        index: u32,
+        length: u32,
+    },
+    Format {
+        index: u32,
+        length: u32,
+        #[schema(value_type = Object)]
+        attributes: HashMap<String, Any>,
+    },
+    RemoveFormat {
+        index: u32,
+        length: u32,
+        attributes: Vec<String>,
+    },
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"parent": "jwstRf4rMzua7E", "pos": 0}))]
+pub struct MoveBlock {
+    /// target space id, default to the space of the block
+    pub space: Option<String>,
+    /// new parent block id, the block will be detached if not set
+    pub parent: Option<String>,
+    /// position in the children of new parent, default to append
+    pub pos: Option<u32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"workspace": "workspace", "parent": "jwstRf4rMzua7E"}))]
+pub struct DuplicateBlock {
+    /// target workspace id, default to the workspace of the block
+    pub workspace: Option<String>,
+    /// target space id, default to the blocks space
+    pub space: Option<String>,
+    /// parent block id of the duplicated block, the block will be detached if not set
+    pub parent: Option<String>,
+    /// position in the children of parent, default to append
+    pub pos: Option<u32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"block": "jwstRf4rMzua7E"}))]
+pub struct SaveTemplate {
+    /// root block id of the template
+    pub block: String,
+    /// space id of the block, default to the blocks space
+    pub space: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"parent": "jwstRf4rMzua7E", "variables": {"date": "2023-03-01"}}))]
+pub struct InstantiateTemplate {
+    /// target space id, default to the blocks space
+    pub space: Option<String>,
+    /// parent block id of the created blocks, the blocks will be detached if not set
+    pub parent: Option<String>,
+    /// position in the children of parent, default to append
+    pub pos: Option<u32>,
+    /// values of the `{{name}}` placeholders
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"query": {"type": "fuzzy", "text": "helo wrld", "prefix": true}, "options": {"limit": 5}}))]
+pub struct StructuredSearch {
+    /// fuzzy, prefix, phrase, text or bool query
+    pub query: SearchQuery,
+    /// pagination and filters
+    #[serde(default)]
+    pub options: SearchOptions,
+}
+
+/// A workspace loaded in memory of the server.
+#[derive(Serialize, ToSchema)]
+pub struct LoadedWorkspace {
+    pub id: String,
+    /// Seconds since the workspace was last read or written.
+    pub idle: u64,
+    /// Size of the encoded workspace in bytes, an estimate of its memory usage.
+    pub size: usize,
+}
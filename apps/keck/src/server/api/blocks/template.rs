@@ -0,0 +1,233 @@
+use super::*;
+use axum::{extract::Path, response::Response};
+use jwst::{DocStorage, WorkspaceTransaction};
+
+/// Get all templates of `Workspace`
+/// - Return 200 Ok and the templates sorted by name.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    get,
+    tag = "Templates",
+    context_path = "/api/template",
+    path = "/{workspace}",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "Get templates", body = [Template]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn get_templates(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("get_templates: {}", ws_id);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.with_trx(|t| t.templates())).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Get a template of `Workspace` by name
+/// - Return 200 Ok and the template.
+/// - Return 404 Not Found if `Workspace` or template not exists.
+#[utoipa::path(
+    get,
+    tag = "Templates",
+    context_path = "/api/template",
+    path = "/{workspace}/{name}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("name", description = "template name"),
+    ),
+    responses(
+        (status = 200, description = "Get template", body = Template),
+        (status = 404, description = "Workspace or template not found")
+    )
+)]
+pub async fn get_template(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+) -> Response {
+    let (ws_id, name) = params;
+    info!("get_template: {}, {}", ws_id, name);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        if let Some(template) = workspace.with_trx(|t| t.get_template(&name)) {
+            Json(template).into_response()
+        } else {
+            StatusCode::NOT_FOUND.into_response()
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Save a `Block` and its descendants as a template
+/// - Return 200 Ok and the template, the template with the same name will be replaced.
+/// - Return 404 Not Found if `Workspace` or `Block` not exists.
+#[utoipa::path(
+    post,
+    tag = "Templates",
+    context_path = "/api/template",
+    path = "/{workspace}/{name}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("name", description = "template name"),
+    ),
+    request_body(
+        content = SaveTemplate,
+        description = "json",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Template saved", body = Template),
+        (status = 404, description = "Workspace or block not found")
+    )
+)]
+pub async fn save_template(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+    Json(payload): Json<SaveTemplate>,
+) -> Response {
+    let (ws_id, name) = params;
+    info!("save_template: {}, {}", ws_id, name);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        if let Some((template, update)) = workspace.with_trx(|mut t| {
+            let space = match &payload.space {
+                Some(space_id) => t.get_exists_space(space_id)?,
+                None => t.get_blocks(),
+            };
+            let block = space.get(&t.trx, &payload.block)?;
+            let template = t.save_template(&name, &block)?;
+
+            Some((template, t.trx.encode_update_v1()))
+        }) {
+            if let Err(e) = context.storage.docs().write_update(ws_id, &update).await {
+                error!("db write error: {}", e.to_string());
+            }
+            Json(template).into_response()
+        } else {
+            StatusCode::NOT_FOUND.into_response()
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Delete a template of `Workspace`
+/// - Return 204 No Content if delete successful.
+/// - Return 404 Not Found if `Workspace` or template not exists.
+#[utoipa::path(
+    delete,
+    tag = "Templates",
+    context_path = "/api/template",
+    path = "/{workspace}/{name}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("name", description = "template name"),
+    ),
+    responses(
+        (status = 204, description = "Template successfully deleted"),
+        (status = 404, description = "Workspace or template not found")
+    )
+)]
+pub async fn delete_template(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+) -> StatusCode {
+    let (ws_id, name) = params;
+    info!("delete_template: {}, {}", ws_id, name);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        if let Some(update) =
+            workspace.with_trx(|mut t| t.remove_template(&name).then(|| t.trx.encode_update_v1()))
+        {
+            if let Err(e) = context.storage.docs().write_update(ws_id, &update).await {
+                error!("db write error: {}", e.to_string());
+            }
+            return StatusCode::NO_CONTENT;
+        }
+    }
+    StatusCode::NOT_FOUND
+}
+
+/// Create blocks from a template
+///
+/// `{{name}}` placeholders in string properties and rich texts will be replaced by the variables.
+/// - Return 200 Ok and the root `Block` of the created blocks.
+/// - Return 400 Bad Request if the target space is reserved, e.g. `templates`.
+/// - Return 404 Not Found if `Workspace`, template or parent `Block` not exists.
+#[utoipa::path(
+    post,
+    tag = "Templates",
+    context_path = "/api/template",
+    path = "/{workspace}/{name}/instantiate",
+    params(
+        ("workspace", description = "workspace id"),
+        ("name", description = "template name"),
+    ),
+    request_body(
+        content = InstantiateTemplate,
+        description = "json",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Blocks created", body = Block),
+        (status = 400, description = "Reserved space or failed to create blocks"),
+        (status = 404, description = "Workspace, template or parent not found")
+    )
+)]
+pub async fn instantiate_template(
+    Extension(context): Extension<Arc<Context>>,
+    Path(params): Path<(String, String)>,
+    Json(payload): Json<InstantiateTemplate>,
+) -> Response {
+    let (ws_id, name) = params;
+    info!("instantiate_template: {}, {}", ws_id, name);
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        let result = workspace.with_trx(|mut t| -> Result<_, StatusCode> {
+            if t.get_template(&name).is_none() {
+                return Err(StatusCode::NOT_FOUND);
+            }
+
+            let space_id = payload.space.as_deref().unwrap_or("blocks");
+            if WorkspaceTransaction::is_reserved_space(space_id) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            // the parent must exist in the space, otherwise the space is created for the copy
+            let (space, parent) = match &payload.parent {
+                Some(parent) => {
+                    let space = t.get_exists_space(space_id).ok_or(StatusCode::NOT_FOUND)?;
+                    let parent = space.get(&t.trx, parent).ok_or(StatusCode::NOT_FOUND)?;
+                    (space, Some(parent))
+                }
+                None => (t.get_space(space_id), None),
+            };
+
+            let block = t
+                .instantiate_template(
+                    &name,
+                    &space,
+                    parent.as_ref(),
+                    payload.pos,
+                    &payload.variables,
+                )
+                .ok_or(StatusCode::BAD_REQUEST)?;
+
+            Ok((block, t.trx.encode_update_v1()))
+        });
+
+        match result {
+            Ok((block, update)) => {
+                if let Err(e) = context.storage.docs().write_update(ws_id, &update).await {
+                    error!("db write error: {}", e.to_string());
+                }
+                Json(block).into_response()
+            }
+            Err(status) => status.into_response(),
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
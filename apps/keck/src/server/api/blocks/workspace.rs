@@ -4,7 +4,11 @@ use axum::{
     http::header,
     response::Response,
 };
-use jwst::{parse_history, parse_history_client, DocStorage};
+use jwst::{
+    parse_history, parse_history_client, DocStorage, JwstError, SearchAnalyzer, SearchFieldType,
+    SearchOptions, SearchQuery,
+};
+use std::collections::BTreeMap;
 use utoipa::IntoParams;
 
 /// Get a exists `Workspace` by id
@@ -140,11 +144,39 @@ pub async fn workspace_client(
 pub struct BlockSearchQuery {
     /// Search by title and text.
     query: String,
+    /// Number of results to skip, at most 10000.
+    #[serde(default)]
+    offset: usize,
+    /// Max number of results, default to 10, at most 100.
+    limit: Option<usize>,
+    /// Only search blocks in the space.
+    space: Option<String>,
+    /// Only search blocks with the flavor.
+    flavor: Option<String>,
+    /// Only search blocks updated at or after the timestamp in milliseconds.
+    updated_after: Option<u64>,
+    /// Only search blocks updated before the timestamp in milliseconds.
+    updated_before: Option<u64>,
+}
+
+impl From<&BlockSearchQuery> for SearchOptions {
+    fn from(query: &BlockSearchQuery) -> Self {
+        Self {
+            offset: query.offset,
+            limit: query.limit,
+            space: query.space.clone(),
+            flavor: query.flavor.clone(),
+            updated_after: query.updated_after,
+            updated_before: query.updated_before,
+            ..Default::default()
+        }
+    }
 }
 
 /// Search workspace blocks of server
 ///
-/// This will return back a list of relevant blocks.
+/// This will return back a list of relevant blocks, with the matched field and a highlighted snippet.
+/// Results can be paginated by `offset` and `limit`, and filtered by space, flavor and updated time.
 #[utoipa::path(
     get,
     tag = "Workspace",
@@ -156,6 +188,8 @@ pub struct BlockSearchQuery {
     ),
     responses(
         (status = 200, description = "Search results", body = SearchResults),
+        (status = 400, description = "Invalid query"),
+        (status = 404, description = "Workspace not found"),
     )
 )]
 pub async fn workspace_search(
@@ -165,12 +199,59 @@ pub async fn workspace_search(
 ) -> Response {
     let query_text = &query.query;
     info!("workspace_search: {ws_id:?} query = {query_text:?}");
+    search_response(&context, ws_id, query_text.into(), &(&query.0).into()).await
+}
+
+/// Search workspace blocks of server by a structured query
+///
+/// Supports fuzzy queries with an edit distance for typo tolerance, prefix queries for
+/// search as you type, exact phrases and boolean combinations of them.
+#[utoipa::path(
+    post,
+    tag = "Workspace",
+    context_path = "/api/search",
+    path = "/{workspace}",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    request_body(
+        content = StructuredSearch,
+        description = "Structured query and search options",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Search results", body = SearchResults),
+        (status = 400, description = "Invalid query"),
+        (status = 404, description = "Workspace not found"),
+    )
+)]
+pub async fn workspace_structured_search(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+    Json(payload): Json<StructuredSearch>,
+) -> Response {
+    info!(
+        "workspace_structured_search: {ws_id:?} query = {:?}",
+        payload.query
+    );
+    search_response(&context, ws_id, payload.query, &payload.options).await
+}
+
+async fn search_response(
+    context: &Context,
+    ws_id: String,
+    query: SearchQuery,
+    options: &SearchOptions,
+) -> Response {
     if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
-        match workspace.search(query_text) {
+        match workspace.search_with_options(query, options) {
             Ok(list) => {
-                debug!("workspace_search: {ws_id:?} query = {query_text:?}; {list:#?}");
+                debug!("workspace_search: {ws_id:?}; {list:#?}");
                 Json(list).into_response()
             }
+            Err(err) if err.is_invalid_query() => {
+                (StatusCode::BAD_REQUEST, err.to_string()).into_response()
+            }
             Err(err) => {
                 error!("Internal server error calling workspace_search: {err:?}");
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -251,6 +332,164 @@ pub async fn set_search_index(
     }
 }
 
+/// Get the analyzer of the search index of `Workspace`
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/search",
+    path = "/{workspace}/analyzer",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "result", body = SearchAnalyzer),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn get_search_analyzer(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("get_search_analyzer: {ws_id:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.metadata().search_analyzer).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Set the analyzer of the search index of `Workspace`
+///
+/// Change the analyzer used to tokenize the search index fields,
+/// the blocks of the workspace will be re-indexed.
+/// - Return 400 Bad Request if the analyzer is unknown.
+#[utoipa::path(
+    post,
+    tag = "Workspace",
+    context_path = "/api/search",
+    path = "/{workspace}/analyzer",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    request_body(
+        content = SearchAnalyzer,
+        description = "gram, english, chinese or cjk_bigram",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "success"),
+        (status = 400, description = "Unknown analyzer"),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn set_search_analyzer(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+    Json(analyzer): Json<String>,
+) -> Response {
+    info!("set_search_analyzer: {ws_id:?} analyzer = {analyzer:?}");
+
+    let Some(analyzer) = SearchAnalyzer::from_name(&analyzer) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown analyzer {analyzer:?}"),
+        )
+            .into_response();
+    };
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        workspace.set_search_analyzer(analyzer);
+        StatusCode::OK.into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Get the typed fields of the search index of `Workspace`
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/search",
+    path = "/{workspace}/fields",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "result", body = BTreeMap<String, SearchFieldType>),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn get_search_fields(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("get_search_fields: {ws_id:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.metadata().search_fields).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Set the typed fields of the search index of `Workspace`
+///
+/// Change the typed block properties indexed besides the search index fields,
+/// they can be used by the `ranges` and `tags` filters of structured search.
+/// The blocks of the workspace will be re-indexed.
+#[utoipa::path(
+    post,
+    tag = "Workspace",
+    context_path = "/api/search",
+    path = "/{workspace}/fields",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    request_body(
+        content = BTreeMap<String, SearchFieldType>,
+        description = "field names with type tag, number or date",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "success"),
+        (status = 400, description = "Bad Request"),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn set_search_fields(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+    Json(fields): Json<BTreeMap<String, SearchFieldType>>,
+) -> Response {
+    info!("set_search_fields: {ws_id:?} fields = {fields:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        if workspace.set_search_fields(fields) {
+            StatusCode::OK.into_response()
+        } else {
+            StatusCode::BAD_REQUEST.into_response()
+        }
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
 /// Get `Block` in `Workspace`
 /// - Return 200 and `Block`'s ID.
 /// - Return 404 Not Found if `Workspace` or `Block` not exists.
@@ -390,6 +629,213 @@ pub async fn history_workspace(
     }
 }
 
+/// Check the block tree of `Workspace`
+///
+/// Report orphans, dangling children, duplicate children, mismatched parents and cycles of all spaces.
+/// - Return 200 Ok and the reports of spaces.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/admin",
+    path = "/{workspace}/integrity",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "Integrity reports", body = [IntegrityReport]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn check_workspace_integrity(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("check_workspace_integrity: {ws_id:?}");
+    integrity_response(&context, ws_id, false).await
+}
+
+/// Repair the block tree of `Workspace`
+///
+/// Dangling and duplicate children will be removed, cycles will be broken and parents will be fixed,
+/// no block will be deleted.
+/// - Return 200 Ok and the reports of issues which have been repaired.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    post,
+    tag = "Workspace",
+    context_path = "/api/admin",
+    path = "/{workspace}/integrity",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "Integrity reports", body = [IntegrityReport]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn repair_workspace_integrity(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("repair_workspace_integrity: {ws_id:?}");
+    integrity_response(&context, ws_id, true).await
+}
+
+async fn integrity_response(context: &Context, ws_id: String, repair: bool) -> Response {
+    match context.storage.check_integrity(&ws_id, repair).await {
+        Ok(reports) => Json(reports).into_response(),
+        Err(JwstError::WorkspaceNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to check integrity of workspace {ws_id:?}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Get the links from the blocks of a page
+///
+/// A page is a space of the workspace, a block links to a page by the `pageId` property
+/// or an inline `reference` attribute of rich text.
+/// - Return 200 Ok and the links of the page.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/references",
+    path = "/{workspace}/{page}",
+    params(
+        ("workspace", description = "workspace id"),
+        ("page", description = "page id"),
+    ),
+    responses(
+        (status = 200, description = "Links of the page", body = [PageLink]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn get_page_references(
+    Extension(context): Extension<Arc<Context>>,
+    Path((ws_id, page_id)): Path<(String, String)>,
+) -> Response {
+    info!("get_page_references: {ws_id:?} {page_id:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.page_references(&page_id)).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Get the links to a page
+///
+/// Return the links from any block to the page or the blocks of the page, i.e. "what links here".
+/// - Return 200 Ok and the backlinks of the page.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/references",
+    path = "/{workspace}/{page}/backlinks",
+    params(
+        ("workspace", description = "workspace id"),
+        ("page", description = "page id"),
+    ),
+    responses(
+        (status = 200, description = "Backlinks of the page", body = [PageLink]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn get_page_backlinks(
+    Extension(context): Extension<Arc<Context>>,
+    Path((ws_id, page_id)): Path<(String, String)>,
+) -> Response {
+    info!("get_page_backlinks: {ws_id:?} {page_id:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.page_backlinks(&page_id)).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Check the links of `Workspace`
+///
+/// Report the links whose target page or block does not exist.
+/// - Return 200 Ok and the broken links.
+/// - Return 404 Not Found if `Workspace` not exists.
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/admin",
+    path = "/{workspace}/links",
+    params(
+        ("workspace", description = "workspace id"),
+    ),
+    responses(
+        (status = 200, description = "Broken links", body = [PageLink]),
+        (status = 404, description = "Workspace not found")
+    )
+)]
+pub async fn check_workspace_links(
+    Extension(context): Extension<Arc<Context>>,
+    Path(ws_id): Path<String>,
+) -> Response {
+    info!("check_workspace_links: {ws_id:?}");
+
+    if let Ok(workspace) = context.storage.get_workspace(&ws_id).await {
+        Json(workspace.broken_links()).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Workspace({ws_id:?}) not found"),
+        )
+            .into_response()
+    }
+}
+
+/// Get the workspaces loaded in memory
+///
+/// Loaded workspaces are unloaded by `WORKSPACE_IDLE_TIMEOUT` and `WORKSPACE_MEMORY_BUDGET`.
+/// - Return 200 Ok and the loaded workspaces, least recently used first.
+#[utoipa::path(
+    get,
+    tag = "Workspace",
+    context_path = "/api/admin",
+    path = "/workspaces",
+    responses(
+        (status = 200, description = "Loaded workspaces", body = [LoadedWorkspace]),
+    )
+)]
+pub async fn get_loaded_workspaces(Extension(context): Extension<Arc<Context>>) -> Response {
+    info!("get_loaded_workspaces");
+
+    let workspaces = context
+        .storage
+        .loaded_workspaces()
+        .await
+        .into_iter()
+        .map(|workspace| LoadedWorkspace {
+            id: workspace.id,
+            idle: workspace.idle.as_secs(),
+            size: workspace.size,
+        })
+        .collect::<Vec<_>>();
+
+    Json(workspaces).into_response()
+}
+
 #[cfg(all(test, feature = "sqlite"))]
 mod test {
     use super::*;
@@ -3,6 +3,7 @@ mod blobs;
 #[cfg(feature = "api")]
 mod blocks;
 
+use super::sync::{AllowAll, CollaborationVerifier, PublicViewer, SignedTokenVerifier};
 use super::*;
 use axum::Router;
 #[cfg(feature = "api")]
@@ -10,11 +11,14 @@ use axum::{
     extract::{Json, Path},
     http::StatusCode,
     response::IntoResponse,
-    routing::{delete, get, head},
+    routing::{delete, get, head, post},
+};
+use jwst_rpc::{
+    BroadcastBridge, BroadcastBroker, BroadcastChannels, ConnectionLimits, NetworkBackend,
+    RpcContextImpl,
 };
-use jwst_rpc::{BroadcastChannels, RpcContextImpl};
 use jwst_storage::JwstStorage;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 use tokio::sync::RwLock;
 
 #[derive(Deserialize)]
@@ -36,28 +40,96 @@ pub struct PageData<T> {
     data: T,
 }
 
+fn collaboration_verifier() -> Arc<dyn CollaborationVerifier> {
+    if let Ok(secret) = dotenvy::var("KECK_COLLABORATION_SECRET") {
+        let verifier = SignedTokenVerifier::new(secret);
+        if dotenvy::var("KECK_COLLABORATION_PUBLIC").is_ok() {
+            info!("existing workspaces are read-only without signed tokens");
+            Arc::new(PublicViewer(verifier))
+        } else {
+            info!("collaboration requires signed tokens");
+            Arc::new(verifier)
+        }
+    } else {
+        warn!("!!! collaboration is not authenticated !!!");
+        warn!("!!! please set KECK_COLLABORATION_SECRET if keck is exposed outside localhost !!!");
+        Arc::new(AllowAll)
+    }
+}
+
 pub struct Context {
     pub channel: BroadcastChannels,
     pub storage: JwstStorage,
+    pub bridge: Option<BroadcastBridge>,
+    pub limits: ConnectionLimits,
+    pub verifier: Arc<dyn CollaborationVerifier>,
 }
 
 impl Context {
     pub async fn new(storage: Option<JwstStorage>) -> Self {
+        // the search indexes are persisted in this directory, or stored in memory if not set
+        let search_index_root = dotenvy::var("SEARCH_INDEX_DIR").ok().map(|dir| {
+            info!("use search index directory: {}", dir);
+            PathBuf::from(dir)
+        });
         let storage = if let Some(storage) = storage {
             info!("use external storage instance: {}", storage.database());
             Ok(storage)
         } else if let Ok(database_url) = dotenvy::var("DATABASE_URL") {
             info!("use external database: {}", database_url);
-            JwstStorage::new(&database_url).await
+            JwstStorage::new_with_search_index(&database_url, search_index_root).await
         } else {
             info!("use sqlite database: jwst.db");
-            JwstStorage::new_with_sqlite("jwst").await
+            JwstStorage::new_with_sqlite("jwst", search_index_root).await
         }
         .expect("Cannot create database");
 
+        // the updates published to the broker are applied to the workspaces of every node,
+        // so the broker and its nodes share a secret, which is required unless the broker
+        // only listens on a loopback address
+        let broker_secret = dotenvy::var("BROADCAST_BROKER_SECRET").ok();
+
+        // a node can host the broker for the other nodes
+        if let Ok(addr) = dotenvy::var("BROADCAST_BROKER_LISTEN") {
+            let broker = BroadcastBroker::bind(&addr, broker_secret.clone())
+                .await
+                .expect("Cannot bind broadcast broker");
+            info!("broadcast broker listening on: {}", addr);
+            tokio::spawn(async move {
+                if let Err(e) = broker.serve().await {
+                    error!("broadcast broker stopped: {}", e);
+                }
+            });
+        }
+
+        let bridge = if let Ok(addr) = dotenvy::var("BROADCAST_BROKER") {
+            info!("share broadcast by broker: {}", addr);
+            let backend = NetworkBackend::connect(&addr, broker_secret)
+                .await
+                .expect("Cannot connect to broadcast broker");
+            Some(BroadcastBridge::new(Arc::new(backend)))
+        } else {
+            None
+        };
+
+        fn env<T: std::str::FromStr>(key: &str) -> Option<T> {
+            dotenvy::var(key).ok().and_then(|s| s.parse().ok())
+        }
+        let limits = ConnectionLimits {
+            max_messages_per_second: env("SYNC_MAX_MESSAGES_PER_SECOND"),
+            max_message_size: env("SYNC_MAX_MESSAGE_SIZE"),
+            max_total_bytes: env("SYNC_MAX_TOTAL_BYTES"),
+            max_workspaces: env("SYNC_MAX_WORKSPACES"),
+        };
+
+        let verifier = collaboration_verifier();
+
         Context {
             channel: RwLock::new(HashMap::new()),
             storage,
+            bridge,
+            limits,
+            verifier,
         }
     }
 }
@@ -70,6 +142,14 @@ impl RpcContextImpl<'_> for Context {
     fn get_channel(&self) -> &BroadcastChannels {
         &self.channel
     }
+
+    fn get_bridge(&self) -> Option<&BroadcastBridge> {
+        self.bridge.as_ref()
+    }
+
+    fn get_limits(&self) -> ConnectionLimits {
+        self.limits.clone()
+    }
 }
 
 pub fn api_handler(router: Router) -> Router {
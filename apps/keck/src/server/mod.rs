@@ -4,11 +4,18 @@ mod sync;
 mod utils;
 
 use axum::{http::Method, response::Redirect, Extension, Router, Server};
-use std::{net::SocketAddr, sync::Arc};
+use jwst_rpc::{RpcContextImpl, SessionAccess};
+use jwst_storage::EvictionPolicy;
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use tokio::signal;
 use tower_http::cors::{Any, CorsLayer};
 
 use api::Context;
+use sync::SignedTokenVerifier;
 use utils::*;
 
 async fn shutdown_signal() {
@@ -37,6 +44,31 @@ async fn shutdown_signal() {
     info!("Shutdown signal received, starting graceful shutdown");
 }
 
+/// Unload the idle workspaces periodically, configured by `WORKSPACE_IDLE_TIMEOUT` in seconds
+/// and `WORKSPACE_MEMORY_BUDGET` in bytes, nothing is unloaded if neither is set.
+fn start_eviction(context: Arc<Context>) {
+    let policy = EvictionPolicy {
+        idle_timeout: dotenvy::var("WORKSPACE_IDLE_TIMEOUT")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs),
+        memory_budget: dotenvy::var("WORKSPACE_MEMORY_BUDGET")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok()),
+    };
+    if policy.is_empty() {
+        return;
+    }
+
+    info!("evict workspaces by policy: {:?}", policy);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            context.evict_workspaces(&policy).await;
+        }
+    });
+}
+
 pub async fn start_server() {
     let origins = [
         "http://localhost:4200".parse().unwrap(),
@@ -60,6 +92,7 @@ pub async fn start_server() {
         .allow_headers(Any);
 
     let context = Arc::new(Context::new(None).await);
+    start_eviction(context.clone());
 
     let app = files::static_files(sync::sync_handler(api::api_handler(Router::new())))
         .layer(cors)
@@ -87,3 +120,88 @@ pub async fn start_server() {
 
     info!("Server shutdown complete");
 }
+
+/// Check the block tree of workspaces from command line: `keck check [--repair] <workspace>...`
+///
+/// The reports will be printed as json, exit with 1 if any issue remains or check failed.
+pub async fn check_integrity(args: Vec<String>) {
+    let repair = args.iter().any(|arg| arg == "--repair");
+    let workspaces = args
+        .into_iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    if workspaces.is_empty() {
+        error!("Usage: keck check [--repair] <workspace>...");
+        std::process::exit(1);
+    }
+
+    let context = Context::new(None).await;
+
+    let mut failed = false;
+    for ws_id in workspaces {
+        match context.storage.check_integrity(&ws_id, repair).await {
+            Ok(reports) => {
+                failed |= reports
+                    .iter()
+                    .any(|report| !report.is_ok() && !report.repaired);
+                println!(
+                    "{}",
+                    serde_json::json!({ "workspace": ws_id, "reports": reports })
+                );
+            }
+            Err(e) => {
+                error!("Failed to check integrity of workspace {}: {}", ws_id, e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Sign a collaboration token by `KECK_COLLABORATION_SECRET` from command line:
+/// `keck token [--readonly] [--expires <seconds>] <workspace>`
+///
+/// The token expires in a day by default.
+pub fn sign_token(args: Vec<String>) {
+    let usage = || {
+        error!("Usage: keck token [--readonly] [--expires <seconds>] <workspace>");
+        std::process::exit(1);
+    };
+
+    let mut access = SessionAccess::ReadWrite;
+    let mut expires = 24 * 60 * 60;
+    let mut workspace = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--readonly" => access = SessionAccess::ReadOnly,
+            "--expires" => match args.next().and_then(|s| s.parse::<u64>().ok()) {
+                Some(seconds) => expires = seconds,
+                None => usage(),
+            },
+            _ if workspace.is_none() => workspace = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(workspace) = workspace else {
+        return usage();
+    };
+
+    let Ok(secret) = dotenvy::var("KECK_COLLABORATION_SECRET") else {
+        error!("KECK_COLLABORATION_SECRET is not set");
+        std::process::exit(1);
+    };
+    let expires = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+        + expires;
+
+    println!(
+        "{}",
+        SignedTokenVerifier::new(secret).sign(&workspace, access, expires)
+    );
+}
@@ -0,0 +1,169 @@
+use hmac::{Hmac, Mac};
+use jwst_rpc::SessionAccess;
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verifies the token of a collaboration session, which is sent as a bearer token
+/// or the `token` query parameter of a signed url.
+pub trait CollaborationVerifier: Send + Sync {
+    /// The access to the workspace, `None` if the token is missing, invalid or has no access.
+    fn verify(&self, workspace_id: &str, token: Option<&str>) -> Option<SessionAccess>;
+
+    /// Whether the existing workspaces are readable without a valid token.
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Everyone can read and write all workspaces, only for servers bound to localhost.
+pub struct AllowAll;
+
+impl CollaborationVerifier for AllowAll {
+    fn verify(&self, _workspace_id: &str, _token: Option<&str>) -> Option<SessionAccess> {
+        Some(SessionAccess::ReadWrite)
+    }
+}
+
+/// Tokens signed by a shared secret, in the form of `{access}.{expires}.{signature}`:
+/// - `access` is `r` for read-only or `rw` for read-write
+/// - `expires` is a unix timestamp in seconds
+/// - `signature` is the hex encoded HMAC-SHA256 of the workspace id, access and expires
+pub struct SignedTokenVerifier {
+    secret: Vec<u8>,
+}
+
+impl SignedTokenVerifier {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+        }
+    }
+
+    fn mac(&self, workspace_id: &str, access: &str, expires: u64) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC can take key of any size");
+        mac.update(format!("{workspace_id}\n{access}\n{expires}").as_bytes());
+        mac
+    }
+
+    /// Sign a token of the workspace, which expires at the unix timestamp in seconds.
+    pub fn sign(&self, workspace_id: &str, access: SessionAccess, expires: u64) -> String {
+        let access = match access {
+            SessionAccess::ReadOnly => "r",
+            SessionAccess::ReadWrite => "rw",
+        };
+        let signature = self
+            .mac(workspace_id, access, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        format!("{access}.{expires}.{signature}")
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl CollaborationVerifier for SignedTokenVerifier {
+    fn verify(&self, workspace_id: &str, token: Option<&str>) -> Option<SessionAccess> {
+        let mut parts = token?.split('.');
+        let (access, expires, signature) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let expires = expires.parse::<u64>().ok()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        if expires <= now {
+            return None;
+        }
+
+        // constant time comparison
+        self.mac(workspace_id, access, expires)
+            .verify_slice(&decode_hex(signature)?)
+            .ok()?;
+
+        match access {
+            "r" => Some(SessionAccess::ReadOnly),
+            "rw" => Some(SessionAccess::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+/// Sessions without a valid token of the inner verifier can read the existing workspaces,
+/// for public viewers and dashboards of the workspaces.
+pub struct PublicViewer<V>(pub V);
+
+impl<V: CollaborationVerifier> CollaborationVerifier for PublicViewer<V> {
+    fn verify(&self, workspace_id: &str, token: Option<&str>) -> Option<SessionAccess> {
+        self.0.verify(workspace_id, token)
+    }
+
+    fn is_public(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn signed_token_test() {
+        let verifier = SignedTokenVerifier::new("secret");
+        let expires = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+
+        let token = verifier.sign("ws", SessionAccess::ReadOnly, expires);
+        assert_eq!(
+            verifier.verify("ws", Some(&token)),
+            Some(SessionAccess::ReadOnly)
+        );
+        let token = verifier.sign("ws", SessionAccess::ReadWrite, expires);
+        assert_eq!(
+            verifier.verify("ws", Some(&token)),
+            Some(SessionAccess::ReadWrite)
+        );
+
+        // token of another workspace, secret, access or expired
+        assert_eq!(verifier.verify("other", Some(&token)), None);
+        assert_eq!(
+            SignedTokenVerifier::new("other").verify("ws", Some(&token)),
+            None
+        );
+        assert_eq!(
+            verifier.verify("ws", Some(&token.replacen("rw", "r", 1))),
+            None
+        );
+        let token = verifier.sign("ws", SessionAccess::ReadWrite, 1);
+        assert_eq!(verifier.verify("ws", Some(&token)), None);
+
+        assert_eq!(verifier.verify("ws", None), None);
+        assert_eq!(verifier.verify("ws", Some("rw.1")), None);
+
+        let verifier = PublicViewer(verifier);
+        // the anonymous access depends on whether the workspace exists
+        assert!(verifier.is_public());
+        assert_eq!(verifier.verify("ws", None), None);
+        let token = verifier.0.sign("ws", SessionAccess::ReadWrite, expires);
+        assert_eq!(
+            verifier.verify("ws", Some(&token)),
+            Some(SessionAccess::ReadWrite)
+        );
+    }
+}
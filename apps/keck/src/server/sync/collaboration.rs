@@ -1,34 +1,128 @@
 use super::*;
 use axum::{
-    extract::{ws::WebSocketUpgrade, Path},
-    response::Response,
+    extract::{ws::WebSocketUpgrade, Path, Query, TypedHeader},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
-use jwst_rpc::{handle_connector, socket_connector};
-use serde::Serialize;
+use jwst::DocStorage;
+use jwst_rpc::{handle_connector, handle_multiplexed_connector, socket_connector, SessionAccess};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 #[derive(Serialize)]
 pub struct WebSocketAuthentication {
     protocol: String,
+    readonly: bool,
 }
 
-pub async fn auth_handler(Path(workspace_id): Path<String>) -> Json<WebSocketAuthentication> {
+#[derive(Deserialize)]
+pub struct TokenParam {
+    token: Option<String>,
+}
+
+// anonymous viewers of a public server can only read the existing workspaces,
+// so they can't create workspaces by syncing them
+async fn authorize(
+    context: &Context,
+    workspace_id: &str,
+    token: Option<&str>,
+) -> Option<SessionAccess> {
+    if let Some(access) = context.verifier.verify(workspace_id, token) {
+        return Some(access);
+    }
+    if !context.verifier.is_public() {
+        return None;
+    }
+    match context.storage.docs().exists(workspace_id.into()).await {
+        Ok(exists) => exists.then_some(SessionAccess::ReadOnly),
+        Err(e) => {
+            error!("failed to check workspace {}: {}", workspace_id, e);
+            None
+        }
+    }
+}
+
+// the bearer token takes precedence over the token of signed url
+async fn verify(
+    context: &Context,
+    workspace_id: &str,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    param: TokenParam,
+) -> Option<SessionAccess> {
+    let token = bearer
+        .as_ref()
+        .map(|TypedHeader(Authorization(bearer))| bearer.token())
+        .or(param.token.as_deref());
+    authorize(context, workspace_id, token).await
+}
+
+pub async fn auth_handler(
+    Extension(context): Extension<Arc<Context>>,
+    Path(workspace_id): Path<String>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(param): Query<TokenParam>,
+) -> Response {
     info!("auth: {}", workspace_id);
-    Json(WebSocketAuthentication {
-        protocol: "AFFiNE".to_owned(),
-    })
+    match verify(&context, &workspace_id, bearer, param).await {
+        Some(access) => Json(WebSocketAuthentication {
+            protocol: "AFFiNE".to_owned(),
+            readonly: !access.can_write(),
+        })
+        .into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
 }
 
 pub async fn upgrade_handler(
     Extension(context): Extension<Arc<Context>>,
     Path(workspace): Path<String>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(param): Query<TokenParam>,
     ws: WebSocketUpgrade,
 ) -> Response {
+    let Some(access) = verify(&context, &workspace, bearer, param).await else {
+        warn!("unauthorized collaboration of workspace: {}", workspace);
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
     let identifier = nanoid!();
     ws.protocols(["AFFiNE"]).on_upgrade(move |socket| {
-        handle_connector(context.clone(), workspace.clone(), identifier, move || {
-            socket_connector(socket, &workspace)
-        })
+        handle_connector(
+            context.clone(),
+            workspace.clone(),
+            identifier,
+            access,
+            move || socket_connector(socket, &workspace),
+        )
+    })
+}
+
+// sync many workspaces over a single connection, the token of the connection
+// is used for the workspaces joined without their own token
+pub async fn multiplexed_handler(
+    Extension(context): Extension<Arc<Context>>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(param): Query<TokenParam>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let default_token = bearer
+        .map(|TypedHeader(Authorization(bearer))| bearer.token().to_owned())
+        .or(param.token);
+
+    let identifier = nanoid!();
+    ws.protocols(["AFFiNE"]).on_upgrade(move |socket| {
+        let ctx = context.clone();
+        handle_multiplexed_connector(
+            context,
+            identifier,
+            move |workspace_id, token| {
+                let ctx = ctx.clone();
+                let token = token.or_else(|| default_token.clone());
+                async move { authorize(&ctx, &workspace_id, token.as_deref()).await }
+            },
+            move || socket_connector(socket, "multiplexed"),
+        )
     })
 }
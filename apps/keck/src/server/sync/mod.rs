@@ -1,6 +1,9 @@
+mod auth;
 mod blobs;
 mod collaboration;
 
+pub use auth::{AllowAll, CollaborationVerifier, PublicViewer, SignedTokenVerifier};
+
 use super::*;
 use axum::routing::{get, post, put};
 
@@ -18,6 +21,7 @@ pub fn sync_handler(router: Router) -> Router {
                 ),
         )
     }
+    .route("/collaboration", get(collaboration::multiplexed_handler))
     .nest_service(
         "/collaboration/:workspace",
         post(collaboration::auth_handler).get(collaboration::upgrade_handler),
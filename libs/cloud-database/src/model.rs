@@ -3,6 +3,7 @@
 
 use chrono::naive::serde::{ts_milliseconds, ts_seconds};
 use chrono::{DateTime, Utc};
+use jwst::{SearchOptions, SearchResult};
 use jwst_logger::error;
 use schemars::{JsonSchema, JsonSchema_repr};
 use sea_orm::{FromQueryResult, TryGetable};
@@ -187,6 +188,9 @@ pub struct CreateWorkspace {
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkspaceSearchInput {
     pub query: String,
+    /// pagination and filters
+    #[serde(flatten)]
+    pub options: SearchOptions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -196,7 +200,9 @@ pub struct WorkspaceSearchResults {
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkspaceSearchResult {
-    pub block_id: String,
+    pub workspace_id: String,
+    #[serde(flatten)]
+    pub result: SearchResult,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -71,6 +71,42 @@ class Workspace(workspace: JwstWorkspace) {
         }
         return this.workspace.setSearchIndex(indexFields)
     }
+
+    fun getSearchAnalyzer(): String {
+        return this.workspace.getSearchAnalyzer()
+    }
+
+    fun setSearchAnalyzer(analyzer: String): Boolean {
+        return this.workspace.setSearchAnalyzer(analyzer)
+    }
+
+    val syncState: Optional<String> get() = this.workspace.syncState()
+
+    val syncError: Optional<String> get() = this.workspace.syncError()
+
+    val pendingUpdates: Long get() = this.workspace.pendingUpdates()
+
+    fun observeSync(callback: (state: String, error: String?, pendingUpdates: Long) -> Unit): Boolean {
+        return this.workspace.observeSync { state, error, pendingUpdates ->
+            callback(state, error, pendingUpdates)
+        }
+    }
+
+    fun pauseSync() {
+        this.workspace.pauseSync()
+    }
+
+    fun resumeSync() {
+        this.workspace.resumeSync()
+    }
+
+    fun stopSync() {
+        this.workspace.stopSync()
+    }
+
+    fun setRemote(remote: String) {
+        this.workspace.setRemote(remote)
+    }
 }
 
 class WorkspaceTransaction constructor(internal var trx: JwstWorkspaceTransaction) {
@@ -179,6 +215,34 @@ class Block constructor(private var block: JwstBlock) {
     fun existsChildren(trx: WorkspaceTransaction, block_id: String): Int {
         return this.block.existsChildren(trx.trx, block_id)
     }
+
+    fun textInsert(trx: WorkspaceTransaction, key: String, index: Long, text: String): Boolean {
+        return this.block.textInsert(trx.trx, key, index, text)
+    }
+
+    fun textInsertWithFormat(trx: WorkspaceTransaction, key: String, index: Long, text: String, attributes: String): Boolean {
+        return this.block.textInsertWithFormat(trx.trx, key, index, text, attributes)
+    }
+
+    fun textDelete(trx: WorkspaceTransaction, key: String, index: Long, length: Long): Boolean {
+        return this.block.textDelete(trx.trx, key, index, length)
+    }
+
+    fun textFormat(trx: WorkspaceTransaction, key: String, index: Long, length: Long, attributes: String): Boolean {
+        return this.block.textFormat(trx.trx, key, index, length, attributes)
+    }
+
+    fun textRemoveFormat(trx: WorkspaceTransaction, key: String, index: Long, length: Long, attributes: Array<String>): Boolean {
+        val attributeNames = JwstVecOfStrings()
+        for (item in attributes) {
+            attributeNames.push(item)
+        }
+        return this.block.textRemoveFormat(trx.trx, key, index, length, attributeNames)
+    }
+
+    fun textDelta(trx: WorkspaceTransaction, key: String): Optional<String> {
+        return this.block.textDelta(trx.trx, key)
+    }
 }
 
 class Storage constructor(path: String, private val remote: String = "") {
@@ -251,6 +251,71 @@ public final class Block {
     }
     private static native int do_existsChildren(long self, long trx, @NonNull String block_id);
 
+    public final boolean textInsert(@NonNull WorkspaceTransaction trx, @NonNull String key, long index, @NonNull String text) {
+        long a0 = trx.mNativeObj;
+        boolean ret = do_textInsert(mNativeObj, a0, key, index, text);
+
+        JNIReachabilityFence.reachabilityFence1(trx);
+
+        return ret;
+    }
+    private static native boolean do_textInsert(long self, long trx, @NonNull String key, long index, @NonNull String text);
+
+    public final boolean textInsertWithFormat(@NonNull WorkspaceTransaction trx, @NonNull String key, long index, @NonNull String text, @NonNull String attributes) {
+        long a0 = trx.mNativeObj;
+        boolean ret = do_textInsertWithFormat(mNativeObj, a0, key, index, text, attributes);
+
+        JNIReachabilityFence.reachabilityFence1(trx);
+
+        return ret;
+    }
+    private static native boolean do_textInsertWithFormat(long self, long trx, @NonNull String key, long index, @NonNull String text, @NonNull String attributes);
+
+    public final boolean textDelete(@NonNull WorkspaceTransaction trx, @NonNull String key, long index, long len) {
+        long a0 = trx.mNativeObj;
+        boolean ret = do_textDelete(mNativeObj, a0, key, index, len);
+
+        JNIReachabilityFence.reachabilityFence1(trx);
+
+        return ret;
+    }
+    private static native boolean do_textDelete(long self, long trx, @NonNull String key, long index, long len);
+
+    public final boolean textFormat(@NonNull WorkspaceTransaction trx, @NonNull String key, long index, long len, @NonNull String attributes) {
+        long a0 = trx.mNativeObj;
+        boolean ret = do_textFormat(mNativeObj, a0, key, index, len, attributes);
+
+        JNIReachabilityFence.reachabilityFence1(trx);
+
+        return ret;
+    }
+    private static native boolean do_textFormat(long self, long trx, @NonNull String key, long index, long len, @NonNull String attributes);
+
+    public final boolean textRemoveFormat(@NonNull WorkspaceTransaction trx, @NonNull String key, long index, long len, @NonNull VecOfStrings attributes) {
+        long a0 = trx.mNativeObj;
+
+        long a4 = attributes.mNativeObj;
+        attributes.mNativeObj = 0;
+
+        boolean ret = do_textRemoveFormat(mNativeObj, a0, key, index, len, a4);
+
+        JNIReachabilityFence.reachabilityFence2(trx, attributes);
+
+        return ret;
+    }
+    private static native boolean do_textRemoveFormat(long self, long trx, @NonNull String key, long index, long len, long attributes);
+
+    public final @NonNull java.util.Optional<String> textDelta(@NonNull WorkspaceTransaction trx, @NonNull String key) {
+        long a0 = trx.mNativeObj;
+        String ret = do_textDelta(mNativeObj, a0, key);
+        java.util.Optional<String> convRet = java.util.Optional.ofNullable(ret);
+
+        JNIReachabilityFence.reachabilityFence1(trx);
+
+        return convRet;
+    }
+    private static native @Nullable String do_textDelta(long self, long trx, @NonNull String key);
+
     public synchronized void delete() {
         if (mNativeObj != 0) {
             do_delete(mNativeObj);
@@ -0,0 +1,11 @@
+// Automatically generated by flapigen
+package com.toeverything.jwst.lib;
+import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
+
+public interface OnSyncStatus {
+
+
+    void onStatus(@NonNull String state, @Nullable String error, long pending_updates);
+
+}
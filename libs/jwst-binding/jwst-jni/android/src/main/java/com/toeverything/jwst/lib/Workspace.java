@@ -1,6 +1,7 @@
 // Automatically generated by flapigen
 package com.toeverything.jwst.lib;
 import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
 
 public final class Workspace {
 
@@ -92,6 +93,70 @@ public final class Workspace {
     }
     private static native boolean do_setSearchIndex(long self, long fields);
 
+    public final @NonNull String getSearchAnalyzer() {
+        String ret = do_getSearchAnalyzer(mNativeObj);
+
+        return ret;
+    }
+    private static native @NonNull String do_getSearchAnalyzer(long self);
+
+    public final boolean setSearchAnalyzer(@NonNull String analyzer) {
+        boolean ret = do_setSearchAnalyzer(mNativeObj, analyzer);
+
+        return ret;
+    }
+    private static native boolean do_setSearchAnalyzer(long self, @NonNull String analyzer);
+
+    public final @NonNull java.util.Optional<String> syncState() {
+        String ret = do_syncState(mNativeObj);
+        java.util.Optional<String> convRet = java.util.Optional.ofNullable(ret);
+
+        return convRet;
+    }
+    private static native @Nullable String do_syncState(long self);
+
+    public final @NonNull java.util.Optional<String> syncError() {
+        String ret = do_syncError(mNativeObj);
+        java.util.Optional<String> convRet = java.util.Optional.ofNullable(ret);
+
+        return convRet;
+    }
+    private static native @Nullable String do_syncError(long self);
+
+    public final long pendingUpdates() {
+        long ret = do_pendingUpdates(mNativeObj);
+
+        return ret;
+    }
+    private static native long do_pendingUpdates(long self);
+
+    public final boolean observeSync(@NonNull OnSyncStatus on_status) {
+        boolean ret = do_observeSync(mNativeObj, on_status);
+
+        return ret;
+    }
+    private static native boolean do_observeSync(long self, OnSyncStatus on_status);
+
+    public final void pauseSync() {
+        do_pauseSync(mNativeObj);
+    }
+    private static native void do_pauseSync(long self);
+
+    public final void resumeSync() {
+        do_resumeSync(mNativeObj);
+    }
+    private static native void do_resumeSync(long self);
+
+    public final void stopSync() {
+        do_stopSync(mNativeObj);
+    }
+    private static native void do_stopSync(long self);
+
+    public final void setRemote(@NonNull String remote) {
+        do_setRemote(mNativeObj, remote);
+    }
+    private static native void do_setRemote(long self, @NonNull String remote);
+
     public synchronized void delete() {
         if (mNativeObj != 0) {
             do_delete(mNativeObj);
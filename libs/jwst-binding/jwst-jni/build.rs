@@ -43,6 +43,12 @@ r#"foreign_callback!(
         onTrx = OnWorkspaceTransaction::on_trx(& self , trx : WorkspaceTransaction);
     }
 );"#,
+r#"foreign_callback!(
+    callback OnSyncStatus {
+        self_type OnSyncStatus;
+        onStatus = OnSyncStatus::on_status(& self , state : String , error : Option<String> , pending_updates : u64);
+    }
+);"#,
 r#"
 pub type VecOfStrings = Vec<String>;
 foreign_class!(
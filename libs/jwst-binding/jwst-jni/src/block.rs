@@ -1,5 +1,6 @@
-use super::{generate_interface, JwstBlock, WorkspaceTransaction};
+use super::{generate_interface, JwstBlock, VecOfStrings, WorkspaceTransaction};
 use lib0::any::Any;
+use std::collections::HashMap;
 
 pub struct Block(pub(crate) JwstBlock);
 
@@ -190,4 +191,81 @@ impl Block {
             .map(|i| i as i32)
             .unwrap_or(-1)
     }
+
+    #[generate_interface]
+    pub fn text_insert(
+        &self,
+        trx: &mut WorkspaceTransaction,
+        key: String,
+        index: u32,
+        text: String,
+    ) -> bool {
+        self.0.text_insert(&mut trx.0.trx, &key, index, &text)
+    }
+
+    #[generate_interface]
+    pub fn text_insert_with_format(
+        &self,
+        trx: &mut WorkspaceTransaction,
+        key: String,
+        index: u32,
+        text: String,
+        attributes: String,
+    ) -> bool {
+        match serde_json::from_str::<HashMap<String, Any>>(&attributes) {
+            Ok(attributes) => {
+                self.0
+                    .text_insert_with_format(&mut trx.0.trx, &key, index, &text, attributes)
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[generate_interface]
+    pub fn text_delete(
+        &self,
+        trx: &mut WorkspaceTransaction,
+        key: String,
+        index: u32,
+        len: u32,
+    ) -> bool {
+        self.0.text_delete(&mut trx.0.trx, &key, index, len)
+    }
+
+    #[generate_interface]
+    pub fn text_format(
+        &self,
+        trx: &mut WorkspaceTransaction,
+        key: String,
+        index: u32,
+        len: u32,
+        attributes: String,
+    ) -> bool {
+        match serde_json::from_str::<HashMap<String, Any>>(&attributes) {
+            Ok(attributes) => self
+                .0
+                .text_format(&mut trx.0.trx, &key, index, len, attributes),
+            Err(_) => false,
+        }
+    }
+
+    #[generate_interface]
+    pub fn text_remove_format(
+        &self,
+        trx: &mut WorkspaceTransaction,
+        key: String,
+        index: u32,
+        len: u32,
+        attributes: VecOfStrings,
+    ) -> bool {
+        self.0
+            .text_remove_format(&mut trx.0.trx, &key, index, len, attributes)
+    }
+
+    #[generate_interface]
+    pub fn text_delta(&self, trx: &WorkspaceTransaction, key: String) -> Option<String> {
+        self.0
+            .text_delta(&trx.0.trx, &key)
+            .and_then(|delta| serde_json::to_string(&delta).ok())
+    }
 }
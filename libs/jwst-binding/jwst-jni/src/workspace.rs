@@ -2,11 +2,20 @@ use super::{
     generate_interface, Block, JwstWorkspace, OnWorkspaceTransaction, VecOfStrings,
     WorkspaceTransaction,
 };
+use jwst::{error, SearchAnalyzer};
+use jwst_rpc::{ClientHandle, SyncState};
+use std::thread;
+use tokio::runtime::Builder;
 use yrs::UpdateSubscription;
 
+pub trait OnSyncStatus {
+    fn on_status(&self, state: String, error: Option<String>, pending_updates: u64);
+}
+
 pub struct Workspace {
     pub(crate) workspace: JwstWorkspace,
     pub(crate) _sub: Option<UpdateSubscription>,
+    pub(crate) client: Option<ClientHandle>,
 }
 
 impl Workspace {
@@ -61,4 +70,116 @@ impl Workspace {
     pub fn set_search_index(&self, fields: VecOfStrings) -> bool {
         self.workspace.set_search_index(fields)
     }
+
+    #[generate_interface]
+    pub fn get_search_analyzer(&self) -> String {
+        self.workspace
+            .metadata()
+            .search_analyzer
+            .as_str()
+            .to_owned()
+    }
+
+    #[generate_interface]
+    pub fn set_search_analyzer(&self, analyzer: String) -> bool {
+        match SearchAnalyzer::from_name(&analyzer) {
+            Some(analyzer) => {
+                self.workspace.set_search_analyzer(analyzer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// State of the sync with the remote:
+    /// `connecting`, `syncing`, `synced`, `offline`, `paused` or `stopped`,
+    /// `None` if the workspace isn't synced by this instance.
+    #[generate_interface]
+    pub fn sync_state(&self) -> Option<String> {
+        self.client
+            .as_ref()
+            .map(|client| client.status().state.as_str().to_owned())
+    }
+
+    /// The error of the last disconnection if the sync is offline.
+    #[generate_interface]
+    pub fn sync_error(&self) -> Option<String> {
+        self.client
+            .as_ref()
+            .and_then(|client| match client.status().state {
+                SyncState::Offline(error) => Some(error),
+                _ => None,
+            })
+    }
+
+    /// Number of local updates which are not sent to the remote yet.
+    #[generate_interface]
+    pub fn pending_updates(&self) -> u64 {
+        self.client
+            .as_ref()
+            .map_or(0, |client| client.status().pending_updates as u64)
+    }
+
+    /// Call the callback with the current sync status and then on each change,
+    /// from a separate thread until the sync is stopped.
+    /// Returns `false` if the workspace isn't synced by this instance.
+    #[generate_interface]
+    pub fn observe_sync(&self, on_status: Box<dyn OnSyncStatus + Send>) -> bool {
+        let Some(client) = &self.client else {
+            return false;
+        };
+        let mut status = client.subscribe();
+        thread::spawn(move || {
+            let Ok(rt) = Builder::new_current_thread().build() else {
+                return error!("Failed to create runtime");
+            };
+            rt.block_on(async move {
+                loop {
+                    let (state, pending_updates) = {
+                        let status = status.borrow_and_update();
+                        (status.state.clone(), status.pending_updates as u64)
+                    };
+                    let error = match &state {
+                        SyncState::Offline(error) => Some(error.clone()),
+                        _ => None,
+                    };
+                    on_status.on_status(state.as_str().to_owned(), error, pending_updates);
+                    // the status is closed once the sync is stopped
+                    if status.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        });
+        true
+    }
+
+    #[generate_interface]
+    pub fn pause_sync(&self) {
+        if let Some(client) = &self.client {
+            client.pause();
+        }
+    }
+
+    #[generate_interface]
+    pub fn resume_sync(&self) {
+        if let Some(client) = &self.client {
+            client.resume();
+        }
+    }
+
+    /// Stop the sync, e.g. on logout, the workspace can be connected again later.
+    #[generate_interface]
+    pub fn stop_sync(&self) {
+        if let Some(client) = &self.client {
+            client.stop();
+        }
+    }
+
+    #[generate_interface]
+    pub fn set_remote(&self, remote: String) {
+        if let Some(client) = &self.client {
+            client.set_remote(remote);
+        }
+    }
 }
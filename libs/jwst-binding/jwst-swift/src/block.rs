@@ -1,6 +1,7 @@
 use super::DynamicValue;
 use jwst::{Block as JwstBlock, Workspace};
 use lib0::any::Any;
+use std::collections::HashMap;
 
 pub struct Block {
     pub workspace: Workspace,
@@ -183,4 +184,59 @@ impl Block {
             })
         })
     }
+
+    pub fn text_insert(&self, key: String, index: u32, text: String) -> bool {
+        self.workspace
+            .with_trx(|mut trx| self.block.text_insert(&mut trx.trx, &key, index, &text))
+    }
+
+    pub fn text_insert_with_format(
+        &self,
+        key: String,
+        index: u32,
+        text: String,
+        attributes: String,
+    ) -> bool {
+        match serde_json::from_str::<HashMap<String, Any>>(&attributes) {
+            Ok(attributes) => self.workspace.with_trx(|mut trx| {
+                self.block
+                    .text_insert_with_format(&mut trx.trx, &key, index, &text, attributes)
+            }),
+            Err(_) => false,
+        }
+    }
+
+    pub fn text_delete(&self, key: String, index: u32, len: u32) -> bool {
+        self.workspace
+            .with_trx(|mut trx| self.block.text_delete(&mut trx.trx, &key, index, len))
+    }
+
+    pub fn text_format(&self, key: String, index: u32, len: u32, attributes: String) -> bool {
+        match serde_json::from_str::<HashMap<String, Any>>(&attributes) {
+            Ok(attributes) => self.workspace.with_trx(|mut trx| {
+                self.block
+                    .text_format(&mut trx.trx, &key, index, len, attributes)
+            }),
+            Err(_) => false,
+        }
+    }
+
+    pub fn text_remove_format(
+        &self,
+        key: String,
+        index: u32,
+        len: u32,
+        attributes: Vec<String>,
+    ) -> bool {
+        self.workspace.with_trx(|mut trx| {
+            self.block
+                .text_remove_format(&mut trx.trx, &key, index, len, attributes)
+        })
+    }
+
+    pub fn text_delta(&self, key: String) -> Option<String> {
+        self.workspace
+            .with_trx(|trx| self.block.text_delta(&trx.trx, &key))
+            .and_then(|delta| serde_json::to_string(&delta).ok())
+    }
 }
@@ -69,6 +69,36 @@ mod ffi {
         pub fn get_float(&self, key: String) -> Option<f64>;
 
         pub fn get_integer(&self, key: String) -> Option<i64>;
+
+        pub fn text_insert(self: &Block, key: String, index: u32, text: String) -> bool;
+
+        pub fn text_insert_with_format(
+            self: &Block,
+            key: String,
+            index: u32,
+            text: String,
+            attributes: String,
+        ) -> bool;
+
+        pub fn text_delete(self: &Block, key: String, index: u32, len: u32) -> bool;
+
+        pub fn text_format(
+            self: &Block,
+            key: String,
+            index: u32,
+            len: u32,
+            attributes: String,
+        ) -> bool;
+
+        pub fn text_remove_format(
+            self: &Block,
+            key: String,
+            index: u32,
+            len: u32,
+            attributes: Vec<String>,
+        ) -> bool;
+
+        pub fn text_delta(self: &Block, key: String) -> Option<String>;
     }
 
     extern "Rust" {
@@ -111,6 +141,37 @@ mod ffi {
         fn get_search_index(self: &Workspace) -> Vec<String>;
 
         fn set_search_index(self: &Workspace, fields: Vec<String>) -> bool;
+
+        fn get_search_analyzer(self: &Workspace) -> String;
+
+        fn set_search_analyzer(self: &Workspace, analyzer: String) -> bool;
+
+        fn sync_state(self: &Workspace) -> Option<String>;
+
+        fn sync_error(self: &Workspace) -> Option<String>;
+
+        fn pending_updates(self: &Workspace) -> u64;
+
+        fn observe_sync(self: &Workspace, observer: SyncStatusObserver) -> bool;
+
+        fn pause_sync(self: &Workspace);
+
+        fn resume_sync(self: &Workspace);
+
+        fn stop_sync(self: &Workspace);
+
+        fn set_remote(self: &Workspace, remote: String);
+    }
+
+    extern "Swift" {
+        type SyncStatusObserver;
+
+        fn on_status(
+            self: &SyncStatusObserver,
+            state: String,
+            error: Option<String>,
+            pending_updates: u64,
+        );
     }
 
     extern "Rust" {
@@ -46,7 +46,7 @@ impl Storage {
         if let Some(storage) = &self.storage {
             let rt = Runtime::new().unwrap();
 
-            let mut workspace = rt.block_on(async move {
+            let (mut workspace, client) = rt.block_on(async move {
                 let storage = storage.read().await;
 
                 start_client(&storage, workspace_id, remote).await
@@ -75,6 +75,7 @@ impl Storage {
             Ok(Workspace {
                 workspace,
                 _sub: sub,
+                client,
             })
         } else {
             Err(JwstError::WorkspaceNotInitialized(workspace_id))
@@ -0,0 +1,31 @@
+use super::*;
+use std::collections::HashMap;
+use tokio::sync::{broadcast::channel, RwLock};
+
+/// A [BroadcastBackend] within the process, the nodes sharing it must run in the same process,
+/// useful for tests and a single server with multiple contexts.
+#[derive(Default)]
+pub struct MemoryBackend {
+    topics: RwLock<HashMap<String, BroadcastSender<Vec<u8>>>>,
+}
+
+#[async_trait]
+impl BroadcastBackend for MemoryBackend {
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> JwstResult<()> {
+        if let Some(sender) = self.topics.read().await.get(topic) {
+            // no subscriber is not an error
+            let _ = sender.send(payload);
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> JwstResult<BroadcastReceiver<Vec<u8>>> {
+        let mut topics = self.topics.write().await;
+        // drop the topics without subscribers
+        topics.retain(|_, sender| sender.receiver_count() > 0);
+        Ok(topics
+            .entry(topic.to_owned())
+            .or_insert_with(|| channel(CAPACITY).0)
+            .subscribe())
+    }
+}
@@ -0,0 +1,289 @@
+mod memory;
+mod network;
+
+pub use memory::MemoryBackend;
+pub use network::{BroadcastBroker, NetworkBackend};
+
+use super::{broadcast::BroadcastType, *};
+use async_trait::async_trait;
+use jwst::{JwstResult, Workspace};
+use lru_time_cache::LruCache;
+use nanoid::nanoid;
+use std::collections::HashSet;
+use tokio::sync::broadcast::{
+    error::RecvError, Receiver as BroadcastReceiver, Sender as BroadcastSender,
+};
+
+// capacity of the broadcast channels, same as the local ones
+const CAPACITY: usize = 100;
+
+// kinds of the messages between nodes
+const CONTENT: u8 = 0;
+const AWARENESS: u8 = 1;
+const SERVER: u8 = 2;
+
+/// A pub/sub backend which delivers the broadcast of workspaces across server nodes,
+/// so the clients connected to different nodes can collaborate.
+///
+/// Every subscriber of a topic receives all payloads published to it,
+/// including the payloads published by itself.
+#[async_trait]
+pub trait BroadcastBackend: Send + Sync {
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> JwstResult<()>;
+    async fn subscribe(&self, topic: &str) -> JwstResult<BroadcastReceiver<Vec<u8>>>;
+}
+
+// a message of a node: node id length, node id, kind, data
+fn encode_message(node: &str, kind: u8, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(node.len() + data.len() + 2);
+    message.push(node.len() as u8);
+    message.extend_from_slice(node.as_bytes());
+    message.push(kind);
+    message.extend_from_slice(data);
+    message
+}
+
+fn decode_message(message: &[u8]) -> Option<(&[u8], u8, &[u8])> {
+    let (len, message) = message.split_first()?;
+    let len = *len as usize;
+    if message.len() <= len {
+        return None;
+    }
+    let (node, message) = message.split_at(len);
+    let (kind, data) = message.split_first()?;
+    Some((node, *kind, data))
+}
+
+// payloads received from other nodes, which should not be published back
+fn received_cache() -> LruCache<Vec<u8>, ()> {
+    LruCache::with_expiry_duration_and_capacity(Duration::from_secs(10), 1024)
+}
+
+// removes the topic from the running relays when the relay stops, even if it's cancelled
+struct RelayGuard {
+    relays: Arc<std::sync::Mutex<HashSet<String>>>,
+    topic: String,
+    stopped: bool,
+}
+
+impl RelayGuard {
+    // stop if no client of this node listens the channel except the relay itself,
+    // checked under the lock so a joining client either sees the relay or starts a new one
+    fn stop_if_idle(&mut self, receiver_count: usize) -> bool {
+        let mut relays = self.relays.lock().unwrap();
+        if receiver_count <= 1 {
+            relays.remove(&self.topic);
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+impl Drop for RelayGuard {
+    fn drop(&mut self) {
+        if !self.stopped {
+            self.relays.lock().unwrap().remove(&self.topic);
+        }
+    }
+}
+
+/// Relays the broadcast channels of workspaces to other nodes by a [BroadcastBackend],
+/// used by [RpcContextImpl::join_broadcast] and [RpcContextImpl::join_server_broadcast].
+///
+/// A relay of a channel is started when a client joins it on this node,
+/// and stopped when all clients of this node leave.
+///
+/// [RpcContextImpl::join_broadcast]: crate::RpcContextImpl::join_broadcast
+/// [RpcContextImpl::join_server_broadcast]: crate::RpcContextImpl::join_server_broadcast
+pub struct BroadcastBridge {
+    backend: Arc<dyn BroadcastBackend>,
+    // topics with a running relay
+    relays: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl BroadcastBridge {
+    pub fn new(backend: Arc<dyn BroadcastBackend>) -> Self {
+        Self {
+            backend,
+            relays: Arc::default(),
+        }
+    }
+
+    // returns a guard of the relay if it should be started
+    fn start_relay(&self, topic: &str) -> Option<RelayGuard> {
+        self.relays
+            .lock()
+            .unwrap()
+            .insert(topic.to_owned())
+            .then(|| RelayGuard {
+                relays: self.relays.clone(),
+                topic: topic.to_owned(),
+                stopped: false,
+            })
+    }
+
+    /// Relay the updates and awareness of the workspace.
+    /// Updates from other nodes are applied to the workspace, so they reach the local clients
+    /// and the storage like the updates of local clients.
+    pub(crate) async fn join(
+        &self,
+        workspace: &Workspace,
+        sender: &BroadcastSender<BroadcastType>,
+    ) {
+        let workspace_id = workspace.id();
+        let topic = format!("workspace:{workspace_id}");
+        let Some(mut guard) = self.start_relay(&topic) else {
+            return;
+        };
+
+        let mut remote = match self.backend.subscribe(&topic).await {
+            Ok(remote) => remote,
+            Err(e) => {
+                error!("failed to subscribe broadcast of {workspace_id}: {e}");
+                return;
+            }
+        };
+        let mut local = sender.subscribe();
+
+        let backend = self.backend.clone();
+        let mut workspace = workspace.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            let node = nanoid!();
+            let mut received = received_cache();
+            loop {
+                tokio::select! {
+                    msg = local.recv() => {
+                        let (kind, data) = match msg {
+                            Ok(BroadcastType::BroadcastContent(data)) => (CONTENT, data),
+                            Ok(BroadcastType::BroadcastAwareness(data)) => (AWARENESS, data),
+                            Ok(BroadcastType::CloseAll) | Err(RecvError::Closed) => break,
+                            Ok(_) => continue,
+                            Err(RecvError::Lagged(count)) => {
+                                warn!("broadcast relay of {workspace_id} lagged {count} messages");
+                                continue;
+                            }
+                        };
+                        if received.contains_key(&data) {
+                            continue;
+                        }
+                        let message = encode_message(&node, kind, &data);
+                        if let Err(e) = backend.publish(&topic, message).await {
+                            error!("failed to publish broadcast of {workspace_id}: {e}");
+                        }
+                    },
+                    msg = remote.recv() => match msg {
+                        Ok(message) => match decode_message(&message) {
+                            Some((from, _, _)) if from == node.as_bytes() => {}
+                            Some((_, CONTENT, data)) => {
+                                received.insert(data.to_vec(), ());
+                                // replies are not needed, the update is complete
+                                workspace.sync_decode_message(data).await;
+                            }
+                            Some((_, AWARENESS, data)) => {
+                                received.insert(data.to_vec(), ());
+                                let awareness = BroadcastType::BroadcastAwareness(data.to_vec());
+                                let _ = sender.send(awareness);
+                            }
+                            _ => warn!("invalid broadcast message of {workspace_id}"),
+                        },
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("remote broadcast of {workspace_id} lagged {count} messages");
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = sleep(Duration::from_millis(100)) => {
+                        if guard.stop_if_idle(sender.receiver_count()) {
+                            break;
+                        }
+                    }
+                }
+            }
+            info!("broadcast relay of {workspace_id} has been closed");
+        });
+    }
+
+    /// Relay the updates written by the storage of servers.
+    pub(crate) async fn join_server(&self, id: &str, sender: &BroadcastSender<Vec<u8>>) {
+        let topic = format!("server:{id}");
+        let Some(mut guard) = self.start_relay(&topic) else {
+            return;
+        };
+
+        let mut remote = match self.backend.subscribe(&topic).await {
+            Ok(remote) => remote,
+            Err(e) => {
+                error!("failed to subscribe server broadcast of {id}: {e}");
+                return;
+            }
+        };
+        let mut local = sender.subscribe();
+
+        let backend = self.backend.clone();
+        let sender = sender.clone();
+        let id = id.to_owned();
+        tokio::spawn(async move {
+            let node = nanoid!();
+            let mut received = received_cache();
+            loop {
+                tokio::select! {
+                    msg = local.recv() => match msg {
+                        Ok(data) => {
+                            if received.contains_key(&data) {
+                                continue;
+                            }
+                            let message = encode_message(&node, SERVER, &data);
+                            if let Err(e) = backend.publish(&topic, message).await {
+                                error!("failed to publish server broadcast of {id}: {e}");
+                            }
+                        }
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("server broadcast relay of {id} lagged {count} messages");
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    msg = remote.recv() => match msg {
+                        Ok(message) => match decode_message(&message) {
+                            Some((from, _, _)) if from == node.as_bytes() => {}
+                            Some((_, SERVER, data)) => {
+                                received.insert(data.to_vec(), ());
+                                let _ = sender.send(data.to_vec());
+                            }
+                            _ => warn!("invalid server broadcast message of {id}"),
+                        },
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("remote server broadcast of {id} lagged {count} messages");
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = sleep(Duration::from_millis(100)) => {
+                        if guard.stop_if_idle(sender.receiver_count()) {
+                            break;
+                        }
+                    }
+                }
+            }
+            info!("server broadcast relay of {id} has been closed");
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn message_codec_test() {
+        let message = encode_message("node", CONTENT, &[1, 2, 3]);
+        assert_eq!(
+            decode_message(&message),
+            Some((b"node".as_slice(), CONTENT, [1, 2, 3].as_slice()))
+        );
+        assert_eq!(
+            decode_message(&encode_message("node", AWARENESS, &[])),
+            Some((b"node".as_slice(), AWARENESS, [].as_slice()))
+        );
+        assert_eq!(decode_message(&[]), None);
+        assert_eq!(decode_message(&[4, b'n', b'o']), None);
+    }
+}
@@ -0,0 +1,545 @@
+use super::*;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{lookup_host, tcp::OwnedReadHalf, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        broadcast::channel,
+        mpsc::{self, error::TrySendError},
+        Notify,
+    },
+    task::JoinHandle,
+    time::timeout,
+};
+
+// operations of the frames
+const SUBSCRIBE: u8 = 1;
+const PUBLISH: u8 = 2;
+const MESSAGE: u8 = 3;
+// the handshake of a connection, the payload is the secret of the broker
+const AUTH: u8 = 4;
+
+// the updates larger than this are rejected
+const MAX_PAYLOAD: usize = 64 * 1024 * 1024;
+
+type Topics = Arc<std::sync::Mutex<HashMap<String, BroadcastSender<Vec<u8>>>>>;
+// operation, topic and payload
+type Frame = (u8, String, Vec<u8>);
+
+// a frame: operation, topic length (u16 BE), topic, payload length (u32 BE), payload
+fn encode_frame(op: u8, topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(topic.len() + payload.len() + 7);
+    frame.push(op);
+    frame.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+    frame.extend_from_slice(topic.as_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Frame> {
+    let op = reader.read_u8().await?;
+
+    let mut topic = vec![0; reader.read_u16().await? as usize];
+    reader.read_exact(&mut topic).await?;
+    let topic = String::from_utf8(topic).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_PAYLOAD {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("payload of {len} bytes is too large"),
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).await?;
+
+    Ok((op, topic, payload))
+}
+
+// compare in constant time, so the secret can't be guessed by the time of responses
+fn verify_secret(secret: &[u8], received: &[u8]) -> bool {
+    secret.len() == received.len()
+        && secret
+            .iter()
+            .zip(received)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn check_topic(topic: &str) -> JwstResult<()> {
+    if topic.len() > u16::MAX as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "topic is too long").into());
+    }
+    Ok(())
+}
+
+// the broker closes the connection which doesn't send the handshake in time
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the delay of reconnecting to the broker is doubled after each failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// reads the frames from the broker until the connection is lost
+fn spawn_reader(mut reader: OwnedReadHalf) -> (mpsc::Receiver<Frame>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel(CAPACITY);
+    let handle = tokio::spawn(async move {
+        loop {
+            match read_frame(&mut reader).await {
+                Ok(frame) => {
+                    if tx.send(frame).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("broadcast broker disconnected: {e}");
+                    break;
+                }
+            }
+        }
+    });
+    (rx, handle)
+}
+
+/// A [BroadcastBackend] connected to a [BroadcastBroker] over TCP, so the nodes
+/// connected to the same broker can share their broadcast.
+///
+/// The broker is reconnected at the same address if the connection is lost,
+/// and the topics are subscribed again. The payloads published meanwhile are lost.
+pub struct NetworkBackend {
+    writer: mpsc::Sender<Vec<u8>>,
+    topics: Topics,
+}
+
+impl NetworkBackend {
+    /// Connect to the broker at `addr`, the `secret` must be the one of the broker if it has one,
+    /// otherwise the broker closes the connection.
+    pub async fn connect(addr: impl ToSocketAddrs, secret: Option<String>) -> JwstResult<Self> {
+        let addrs = lookup_host(addr).await?.collect::<Vec<_>>();
+        let stream = TcpStream::connect(addrs.as_slice()).await?;
+        let (tx, rx) = mpsc::channel::<Vec<u8>>(CAPACITY);
+        let topics = Topics::default();
+        let handshake = secret.map(|secret| encode_frame(AUTH, "", secret.as_bytes()));
+
+        tokio::spawn(Self::run(addrs, stream, rx, topics.clone(), handshake));
+
+        Ok(Self { writer: tx, topics })
+    }
+
+    // relays the frames until the backend is dropped
+    async fn run(
+        addrs: Vec<SocketAddr>,
+        mut stream: TcpStream,
+        mut rx: mpsc::Receiver<Vec<u8>>,
+        topics: Topics,
+        handshake: Option<Vec<u8>>,
+    ) {
+        loop {
+            let (reader, mut writer) = stream.into_split();
+            let (mut frames, reader) = spawn_reader(reader);
+
+            // the handshake and the subscriptions of the previous connection
+            let subscriptions = handshake
+                .iter()
+                .cloned()
+                .chain(
+                    topics
+                        .lock()
+                        .unwrap()
+                        .keys()
+                        .map(|topic| encode_frame(SUBSCRIBE, topic, &[])),
+                )
+                .collect::<Vec<_>>();
+            let mut connected = true;
+            for frame in subscriptions {
+                if let Err(e) = writer.write_all(&frame).await {
+                    error!("failed to subscribe to broadcast broker: {e}");
+                    connected = false;
+                    break;
+                }
+            }
+
+            while connected {
+                tokio::select! {
+                    frame = rx.recv() => {
+                        let Some(frame) = frame else {
+                            // the backend was dropped
+                            reader.abort();
+                            return;
+                        };
+                        if let Err(e) = writer.write_all(&frame).await {
+                            error!("failed to write to broadcast broker: {e}");
+                            connected = false;
+                        }
+                    }
+                    frame = frames.recv() => match frame {
+                        Some((MESSAGE, topic, payload)) => {
+                            if let Some(sender) = topics.lock().unwrap().get(&topic) {
+                                let _ = sender.send(payload);
+                            }
+                        }
+                        Some((op, ..)) => warn!("unexpected operation {op} from broadcast broker"),
+                        None => connected = false,
+                    },
+                }
+            }
+            reader.abort();
+
+            let mut delay = MIN_RECONNECT_DELAY;
+            stream = loop {
+                let wait = sleep(delay);
+                tokio::pin!(wait);
+                loop {
+                    tokio::select! {
+                        _ = &mut wait => break,
+                        // the frames can't be delivered while disconnected
+                        frame = rx.recv() => if frame.is_none() {
+                            return;
+                        },
+                    }
+                }
+                match TcpStream::connect(addrs.as_slice()).await {
+                    Ok(stream) => break stream,
+                    Err(e) => {
+                        error!("failed to reconnect to broadcast broker: {e}");
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            };
+            info!("reconnected to broadcast broker");
+        }
+    }
+
+    async fn send(&self, frame: Vec<u8>) -> JwstResult<()> {
+        self.writer
+            .send(frame)
+            .await
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "broadcast broker disconnected").into())
+    }
+}
+
+#[async_trait]
+impl BroadcastBackend for NetworkBackend {
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> JwstResult<()> {
+        check_topic(topic)?;
+        if payload.len() > MAX_PAYLOAD {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("payload of {} bytes is too large", payload.len()),
+            )
+            .into());
+        }
+        self.send(encode_frame(PUBLISH, topic, &payload)).await
+    }
+
+    async fn subscribe(&self, topic: &str) -> JwstResult<BroadcastReceiver<Vec<u8>>> {
+        check_topic(topic)?;
+        // the broker is only asked once for each topic
+        let receiver = match self.topics.lock().unwrap().entry(topic.to_owned()) {
+            Entry::Occupied(entry) => return Ok(entry.get().subscribe()),
+            Entry::Vacant(entry) => entry.insert(channel(CAPACITY).0).subscribe(),
+        };
+        if let Err(e) = self.send(encode_frame(SUBSCRIBE, topic, &[])).await {
+            self.topics.lock().unwrap().remove(topic);
+            return Err(e);
+        }
+        Ok(receiver)
+    }
+}
+
+// a connection subscribing topics of the broker
+#[derive(Clone)]
+struct Subscriber {
+    id: usize,
+    tx: mpsc::Sender<Vec<u8>>,
+    // disconnects the connection which can't keep up with the messages
+    slow: Arc<Notify>,
+}
+
+type Subscribers = Arc<std::sync::Mutex<HashMap<String, Vec<Subscriber>>>>;
+
+/// A minimal broker of [NetworkBackend], which delivers the published payloads
+/// to all connections subscribing the topic, including the publisher itself.
+///
+/// A connection is closed if it can't receive the payloads as fast as they are published,
+/// so a slow connection doesn't block the others.
+///
+/// The payloads are applied to the workspaces of every node without further authorization,
+/// so a connection must send the secret of the broker first if it has one.
+pub struct BroadcastBroker {
+    listener: TcpListener,
+    secret: Option<Arc<[u8]>>,
+}
+
+impl BroadcastBroker {
+    /// Listen on `addr`, a `secret` is required unless the address is a loopback one.
+    pub async fn bind(addr: impl ToSocketAddrs, secret: Option<String>) -> JwstResult<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let secret = secret.filter(|secret| !secret.is_empty());
+        if secret.is_none() && !listener.local_addr()?.ip().is_loopback() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a secret is required to listen on a non-loopback address",
+            )
+            .into());
+        }
+
+        Ok(Self {
+            listener,
+            secret: secret.map(|secret| secret.as_bytes().into()),
+        })
+    }
+
+    pub fn local_addr(&self) -> JwstResult<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accept connections until the listener fails.
+    pub async fn serve(self) -> JwstResult<()> {
+        let subscribers = Subscribers::default();
+        let mut id = 0;
+        loop {
+            let (stream, addr) = self.listener.accept().await?;
+            debug!("broadcast broker accepted {addr}");
+            tokio::spawn(Self::handle_connection(
+                id,
+                stream,
+                subscribers.clone(),
+                self.secret.clone(),
+            ));
+            id += 1;
+        }
+    }
+
+    async fn handle_connection(
+        id: usize,
+        mut stream: TcpStream,
+        subscribers: Subscribers,
+        secret: Option<Arc<[u8]>>,
+    ) {
+        if let Some(secret) = secret {
+            let handshake = timeout(HANDSHAKE_TIMEOUT, read_frame(&mut stream)).await;
+            if !matches!(
+                handshake,
+                Ok(Ok((AUTH, _, payload))) if verify_secret(&secret, &payload)
+            ) {
+                warn!("reject unauthorized broadcast connection {id}");
+                return;
+            }
+        }
+
+        let (mut reader, mut writer) = stream.into_split();
+        let (tx, mut rx) = mpsc::channel::<Vec<u8>>(CAPACITY);
+        let slow = Arc::new(Notify::new());
+
+        let writer = tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                if writer.write_all(&frame).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let frame = tokio::select! {
+                frame = read_frame(&mut reader) => frame,
+                _ = slow.notified() => {
+                    warn!("close slow broadcast connection {id}");
+                    break;
+                }
+            };
+            match frame {
+                Ok((SUBSCRIBE, topic, _)) => {
+                    let mut subscribers = subscribers.lock().unwrap();
+                    let topic = subscribers.entry(topic).or_default();
+                    if !topic.iter().any(|subscriber| subscriber.id == id) {
+                        topic.push(Subscriber {
+                            id,
+                            tx: tx.clone(),
+                            slow: slow.clone(),
+                        });
+                    }
+                }
+                Ok((PUBLISH, topic, payload)) => {
+                    let targets = subscribers
+                        .lock()
+                        .unwrap()
+                        .get(&topic)
+                        .cloned()
+                        .unwrap_or_default();
+                    let frame = encode_frame(MESSAGE, &topic, &payload);
+                    for target in targets {
+                        match target.tx.try_send(frame.clone()) {
+                            Ok(()) => {}
+                            Err(TrySendError::Full(_)) => {
+                                target.slow.notify_one();
+                                Self::remove_connection(&subscribers, target.id);
+                            }
+                            Err(TrySendError::Closed(_)) => {
+                                Self::remove_connection(&subscribers, target.id);
+                            }
+                        }
+                    }
+                }
+                // the handshake is ignored if the broker has no secret
+                Ok((AUTH, ..)) => {}
+                Ok((op, ..)) => warn!("unexpected operation {op} from broadcast connection"),
+                Err(e) => {
+                    debug!("broadcast connection closed: {e}");
+                    break;
+                }
+            }
+        }
+
+        Self::remove_connection(&subscribers, id);
+        // the writer may wait for a slow connection forever
+        writer.abort();
+    }
+
+    fn remove_connection(subscribers: &Subscribers, id: usize) {
+        let mut subscribers = subscribers.lock().unwrap();
+        for topic in subscribers.values_mut() {
+            topic.retain(|subscriber| subscriber.id != id);
+        }
+        subscribers.retain(|_, topic| !topic.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn network_backend_test() {
+        let broker = BroadcastBroker::bind("127.0.0.1:0", None).await.unwrap();
+        let addr = broker.local_addr().unwrap();
+        tokio::spawn(broker.serve());
+
+        let backend1 = NetworkBackend::connect(addr, None).await.unwrap();
+        let backend2 = NetworkBackend::connect(addr, None).await.unwrap();
+
+        let mut rx1 = backend1.subscribe("topic").await.unwrap();
+        let mut rx2 = backend2.subscribe("topic").await.unwrap();
+        let mut other = backend2.subscribe("other").await.unwrap();
+        // wait for the subscriptions to reach the broker
+        sleep(Duration::from_millis(100)).await;
+
+        backend1.publish("topic", vec![1, 2, 3]).await.unwrap();
+        assert_eq!(rx1.recv().await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(rx2.recv().await.unwrap(), vec![1, 2, 3]);
+        assert!(other.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn network_backend_reconnect_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let backend = NetworkBackend::connect(addr, None).await.unwrap();
+        let mut rx = backend.subscribe("topic").await.unwrap();
+
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let (op, topic, _) = read_frame(&mut conn).await.unwrap();
+        assert_eq!((op, topic.as_str()), (SUBSCRIBE, "topic"));
+        // restart the broker
+        drop(conn);
+
+        let (mut conn, _) = timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+        let (op, topic, _) = read_frame(&mut conn).await.unwrap();
+        assert_eq!((op, topic.as_str()), (SUBSCRIBE, "topic"));
+
+        conn.write_all(&encode_frame(MESSAGE, "topic", &[1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn broadcast_broker_secret_test() {
+        // a secret is required unless the broker only listens on loopback
+        assert!(BroadcastBroker::bind("0.0.0.0:0", None).await.is_err());
+
+        let broker = BroadcastBroker::bind("127.0.0.1:0", Some("secret".into()))
+            .await
+            .unwrap();
+        let addr = broker.local_addr().unwrap();
+        tokio::spawn(broker.serve());
+
+        // the connections without the secret are closed before they can publish
+        for handshake in [vec![], encode_frame(AUTH, "", b"wrong")] {
+            let mut conn = TcpStream::connect(addr).await.unwrap();
+            conn.write_all(&handshake).await.unwrap();
+            conn.write_all(&encode_frame(PUBLISH, "topic", &[1]))
+                .await
+                .unwrap();
+            let mut buf = vec![0; 1024];
+            let closed = timeout(Duration::from_secs(5), conn.read(&mut buf)).await;
+            assert!(matches!(closed, Ok(Ok(0)) | Ok(Err(_))));
+        }
+
+        let backend = NetworkBackend::connect(addr, Some("secret".into()))
+            .await
+            .unwrap();
+        let mut rx = backend.subscribe("topic").await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        backend.publish("topic", vec![1, 2, 3]).await.unwrap();
+        assert_eq!(
+            timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn broadcast_broker_slow_subscriber_test() {
+        let broker = BroadcastBroker::bind("127.0.0.1:0", None).await.unwrap();
+        let addr = broker.local_addr().unwrap();
+        tokio::spawn(broker.serve());
+
+        // a subscriber which never reads
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        slow.write_all(&encode_frame(SUBSCRIBE, "topic", &[]))
+            .await
+            .unwrap();
+
+        let backend = NetworkBackend::connect(addr, None).await.unwrap();
+        let mut rx = backend.subscribe("topic").await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        let payload = vec![0; 64 * 1024];
+        for _ in 0..CAPACITY * 8 {
+            backend.publish("topic", payload.clone()).await.unwrap();
+            // the publishes are not blocked by the slow subscriber
+            assert_eq!(
+                timeout(Duration::from_secs(5), rx.recv())
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                payload
+            );
+        }
+
+        // the slow subscriber is disconnected
+        let mut buf = vec![0; 1024 * 1024];
+        let closed = timeout(Duration::from_secs(5), async {
+            loop {
+                match slow.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => continue,
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+}
@@ -1,12 +1,23 @@
-use super::*;
+use super::{queue::OutboundQueue, *};
 use anyhow::Context;
-use futures::{SinkExt, StreamExt};
-use jwst::{DocStorage, JwstResult, Workspace};
+use futures::{
+    future::{select_all, Either, OptionFuture},
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use jwst::{sync_encode_update, DocStorage, JwstResult, Workspace};
 use jwst_storage::JwstStorage;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
 use tokio::{
     net::TcpStream,
-    sync::broadcast::{channel, Receiver},
+    sync::{
+        broadcast::{channel, Receiver},
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        watch,
+    },
 };
 use tokio_tungstenite::{
     connect_async,
@@ -17,6 +28,73 @@ use url::Url;
 
 type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+/// State of the connection between a client and the remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncState {
+    Connecting,
+    /// Connected and waiting for the workspace of the remote.
+    Syncing,
+    /// The workspace is up to date with the remote.
+    Synced,
+    /// Disconnected with the error, will reconnect later.
+    Offline(String),
+    /// Disconnected by [ClientHandle::pause].
+    Paused,
+    /// Disconnected by [ClientHandle::stop].
+    Stopped,
+}
+
+impl SyncState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connecting => "connecting",
+            Self::Syncing => "syncing",
+            Self::Synced => "synced",
+            Self::Offline(_) => "offline",
+            Self::Paused => "paused",
+            Self::Stopped => "stopped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    /// Local changes which are not acknowledged by the remote yet.
+    pub pending_updates: usize,
+}
+
+/// Receives the latest [SyncStatus] of a client.
+pub type SyncStatusReceiver = watch::Receiver<SyncStatus>;
+
+// a workspace synced by a client
+struct SyncedWorkspace {
+    workspace: Workspace,
+    // the token to join the workspace over a multiplexed connection
+    token: Option<String>,
+    // the parent workspace of a subdocument, which authorizes the subdocument
+    parent: Option<String>,
+    // local updates of the workspace
+    rx: Receiver<Vec<u8>>,
+    queue: OutboundQueue,
+}
+
+fn pending_updates(workspaces: &[SyncedWorkspace]) -> usize {
+    workspaces.iter().map(|synced| synced.queue.pending()).sum()
+}
+
+fn update_status(status: &watch::Sender<SyncStatus>, state: Option<SyncState>, pending: usize) {
+    status.send_if_modified(|status| {
+        let modified = status.pending_updates != pending
+            || state.as_ref().map_or(false, |state| &status.state != state);
+        status.pending_updates = pending;
+        if let Some(state) = state {
+            status.state = state;
+        }
+        modified
+    });
+}
+
 async fn prepare_connection(remote: &str) -> JwstResult<Socket> {
     debug!("generate remote config");
     let uri = Url::parse(remote).context("failed to parse remote url".to_string())?;
@@ -57,6 +135,8 @@ async fn join_sync_thread(
     workspace: &Workspace,
     socket: Socket,
     rx: &mut Receiver<Vec<u8>>,
+    status: &watch::Sender<SyncStatus>,
+    queue: &mut OutboundQueue,
 ) -> JwstResult<bool> {
     let (mut socket_tx, mut socket_rx) = socket.split();
 
@@ -70,6 +150,8 @@ async fn join_sync_thread(
                     Ok(msg) => {
                         if let Message::Binary(msg) = msg {
                             debug!("get update from remote: {:?}", msg);
+                            queue.acknowledge(&msg).await;
+                            update_status(status, Some(SyncState::Synced), queue.pending());
                             let mut success = true;
                             // skip empty updates
                             if msg == [0, 2, 2, 0, 0] {
@@ -81,6 +163,8 @@ async fn join_sync_thread(
                                 debug!("send differential update to remote: {:?}", update);
                                 if let Err(e) = socket_tx.send(Message::binary(update)).await {
                                     warn!("send differential update to remote failed: {:?}", e);
+                                    let state = SyncState::Offline(e.to_string());
+                                    update_status(status, Some(state), queue.pending());
                                     if let Err(e) = socket_tx.close().await {
                                         error!("close failed: {}", e);
                                     };
@@ -95,6 +179,8 @@ async fn join_sync_thread(
                     },
                     Err(e) => {
                         error!("remote closed: {e}");
+                        let state = SyncState::Offline(e.to_string());
+                        update_status(status, Some(state), queue.pending());
                         break false
                     },
                 }
@@ -103,11 +189,13 @@ async fn join_sync_thread(
                 debug!("send local update to remote: {:?}", msg);
                 if let Err(e) = socket_tx.send(Message::Binary(msg)).await {
                     warn!("send local update to remote failed: {:?}", e);
+                    update_status(status, Some(SyncState::Offline(e.to_string())), queue.pending());
                     if let Err(e) = socket_tx.close().await{
                         error!("close failed: {}", e);
                     }
                     break true
                 }
+                update_status(status, None, queue.pending());
             }
         }
     };
@@ -118,19 +206,575 @@ async fn join_sync_thread(
 
 async fn run_sync(
     first_sync: Arc<AtomicBool>,
-    workspace: &Workspace,
+    synced: &mut SyncedWorkspace,
     remote: String,
-    rx: &mut Receiver<Vec<u8>>,
+    status: &watch::Sender<SyncStatus>,
 ) -> JwstResult<bool> {
-    let socket = init_connection(workspace, &remote).await?;
-    join_sync_thread(first_sync, workspace, socket, rx).await
+    let SyncedWorkspace {
+        workspace,
+        rx,
+        queue,
+        ..
+    } = synced;
+    update_status(status, Some(SyncState::Connecting), queue.pending());
+    let mut socket = init_connection(workspace, &remote).await?;
+
+    // the queued updates are replayed from the workspace with the unacknowledged changes,
+    // which includes the updates received before
+    while rx.try_recv().is_ok() {}
+    if let Some(update) = queue.pending_update().await {
+        debug!("replay unacknowledged updates: {}", update.len());
+        socket
+            .send(Message::Binary(sync_encode_update(&update)))
+            .await
+            .context("failed to replay updates")?;
+    }
+
+    update_status(status, Some(SyncState::Syncing), queue.pending());
+    join_sync_thread(first_sync, workspace, socket, rx, status, queue).await
+}
+
+// result of a multiplexed sync thread
+enum MultiplexedSync {
+    // the connection is closed, whether the sync ended successfully
+    Closed(bool),
+    // the workspace was loaded during the sync and should join it
+    Joined(SyncedWorkspace),
+    // the sync of the workspace at the index was closed or rejected by the remote
+    Left(usize),
 }
 
-fn start_sync_thread(workspace: &Workspace, remote: String, mut rx: Receiver<Vec<u8>>) {
+async fn join_multiplexed_sync_thread(
+    first_sync: Arc<AtomicBool>,
+    socket_tx: &mut SplitSink<Socket, Message>,
+    socket_rx: &mut SplitStream<Socket>,
+    workspaces: &mut [SyncedWorkspace],
+    joins: &mut UnboundedReceiver<SyncedWorkspace>,
+    synced: &mut HashSet<String>,
+    status: &watch::Sender<SyncStatus>,
+) -> MultiplexedSync {
+    // the local updates are received separately from the workspaces they are applied to
+    let (mut receivers, mut sessions): (Vec<_>, Vec<_>) = workspaces
+        .iter_mut()
+        .map(|synced| {
+            let session = (&mut synced.workspace, &mut synced.queue);
+            (&mut synced.rx, session)
+        })
+        .unzip();
+    let ids = sessions
+        .iter()
+        .enumerate()
+        .map(|(index, (workspace, _))| (workspace.id(), index))
+        .collect::<HashMap<_, _>>();
+    let pending = |sessions: &[(&mut Workspace, &mut OutboundQueue)]| {
+        sessions
+            .iter()
+            .map(|(_, queue)| queue.pending())
+            .sum::<usize>()
+    };
+
+    loop {
+        // all workspaces may have left the sync
+        let local: OptionFuture<_> = (!receivers.is_empty())
+            .then(|| select_all(receivers.iter_mut().map(|rx| Box::pin(rx.recv()))))
+            .into();
+        tokio::select! {
+            Some(msg) = socket_rx.next() => {
+                let msg = match msg {
+                    Ok(Message::Binary(msg)) => msg,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        error!("remote closed: {e}");
+                        let state = SyncState::Offline(e.to_string());
+                        update_status(status, Some(state), pending(&sessions));
+                        break MultiplexedSync::Closed(false)
+                    }
+                };
+                let (id, data) = match MultiplexedMessage::decode(&msg) {
+                    Some(MultiplexedMessage::Sync { id, data }) => (id, data),
+                    // only the workspace leaves, the others keep syncing
+                    Some(MultiplexedMessage::Leave { id }) => {
+                        warn!("remote closed the sync of {id}");
+                        match ids.get(&id) {
+                            Some(&index) => break MultiplexedSync::Left(index),
+                            None => continue,
+                        }
+                    }
+                    Some(MultiplexedMessage::Reject { id, reason }) => {
+                        warn!("remote rejected the sync of {id}: {reason}");
+                        match ids.get(&id) {
+                            Some(&index) => break MultiplexedSync::Left(index),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                let Some(&index) = ids.get(&id) else {
+                    continue
+                };
+                debug!("get update of {id} from remote: {:?}", data);
+                sessions[index].1.acknowledge(&data).await;
+                synced.insert(id.clone());
+                let state = (synced.len() == ids.len()).then_some(SyncState::Synced);
+                update_status(status, state, pending(&sessions));
+                // skip empty updates
+                if data == [0, 2, 2, 0, 0] {
+                    continue;
+                }
+                let buffer = sessions[index].0.sync_decode_message(&data).await;
+                first_sync.store(true, Ordering::Release);
+                for update in buffer {
+                    debug!("send differential update of {id} to remote: {:?}", update);
+                    let msg = MultiplexedMessage::Sync { id: id.clone(), data: update };
+                    if let Err(e) = socket_tx.send(Message::Binary(msg.encode())).await {
+                        warn!("send differential update to remote failed: {:?}", e);
+                        let state = SyncState::Offline(e.to_string());
+                        update_status(status, Some(state), pending(&sessions));
+                        if let Err(e) = socket_tx.close().await {
+                            error!("close failed: {}", e);
+                        };
+                        return MultiplexedSync::Closed(false);
+                    }
+                }
+            }
+            Some((Ok(msg), index, _)) = local => {
+                let (workspace, _) = &sessions[index];
+                debug!("send local update of {} to remote: {:?}", workspace.id(), msg);
+                let msg = MultiplexedMessage::Sync { id: workspace.id(), data: msg };
+                if let Err(e) = socket_tx.send(Message::Binary(msg.encode())).await {
+                    warn!("send local update to remote failed: {:?}", e);
+                    let state = SyncState::Offline(e.to_string());
+                    update_status(status, Some(state), pending(&sessions));
+                    if let Err(e) = socket_tx.close().await{
+                        error!("close failed: {}", e);
+                    }
+                    break MultiplexedSync::Closed(true)
+                }
+                update_status(status, None, pending(&sessions));
+            }
+            Some(joined) = joins.recv() => break MultiplexedSync::Joined(joined),
+        }
+    }
+}
+
+// join the sync of the workspace over a multiplexed connection
+async fn join_workspace(
+    socket_tx: &mut SplitSink<Socket, Message>,
+    synced: &mut SyncedWorkspace,
+) -> JwstResult<()> {
+    let SyncedWorkspace {
+        workspace,
+        token,
+        parent,
+        rx,
+        queue,
+    } = synced;
+    let id = workspace.id();
+    debug!("join workspace {id}");
+    let init_data = workspace
+        .sync_init_message()
+        .await
+        .context("failed to create init message")?;
+    let mut messages = vec![
+        MultiplexedMessage::Join {
+            id: id.clone(),
+            token: token.clone(),
+            parent: parent.clone(),
+        },
+        MultiplexedMessage::Sync {
+            id: id.clone(),
+            data: init_data,
+        },
+    ];
+
+    // same as the single workspace sync, the unacknowledged changes are replayed
+    while rx.try_recv().is_ok() {}
+    if let Some(update) = queue.pending_update().await {
+        debug!("replay unacknowledged updates of {id}: {}", update.len());
+        messages.push(MultiplexedMessage::Sync {
+            id,
+            data: sync_encode_update(&update),
+        });
+    }
+
+    for message in messages {
+        socket_tx
+            .send(Message::Binary(message.encode()))
+            .await
+            .context("failed to join workspace")?;
+    }
+
+    Ok(())
+}
+
+async fn run_multiplexed_sync(
+    first_sync: Arc<AtomicBool>,
+    workspaces: &mut Vec<SyncedWorkspace>,
+    joins: &mut UnboundedReceiver<SyncedWorkspace>,
+    remote: String,
+    status: &watch::Sender<SyncStatus>,
+) -> JwstResult<bool> {
+    // the workspaces loaded while offline join with the others
+    while let Ok(joined) = joins.try_recv() {
+        workspaces.push(joined);
+    }
+
+    update_status(
+        status,
+        Some(SyncState::Connecting),
+        pending_updates(workspaces),
+    );
+    let (mut socket_tx, mut socket_rx) = prepare_connection(&remote).await?.split();
+    for synced in workspaces.iter_mut() {
+        join_workspace(&mut socket_tx, synced).await?;
+    }
+    update_status(
+        status,
+        Some(SyncState::Syncing),
+        pending_updates(workspaces),
+    );
+
+    // workspaces which received the first message from the remote or were rejected
+    let mut synced = HashSet::new();
+
+    debug!("start multiplexed sync thread");
+    let success = loop {
+        let result = join_multiplexed_sync_thread(
+            first_sync.clone(),
+            &mut socket_tx,
+            &mut socket_rx,
+            workspaces,
+            joins,
+            &mut synced,
+            status,
+        )
+        .await;
+        match result {
+            MultiplexedSync::Closed(success) => break success,
+            MultiplexedSync::Left(index) => {
+                // the workspace can be synced again once its local updates are not received
+                let mut left = workspaces.swap_remove(index);
+                left.queue.flush().await;
+                synced.remove(&left.workspace.id());
+                let state = (synced.len() == workspaces.len()).then_some(SyncState::Synced);
+                update_status(status, state, pending_updates(workspaces));
+            }
+            MultiplexedSync::Joined(joined) => {
+                workspaces.push(joined);
+                if let Some(joined) = workspaces.last_mut() {
+                    join_workspace(&mut socket_tx, joined).await?;
+                }
+                update_status(
+                    status,
+                    Some(SyncState::Syncing),
+                    pending_updates(workspaces),
+                );
+            }
+        }
+    };
+    debug!("end multiplexed sync thread");
+
+    Ok(success)
+}
+
+/// Reconnection of a client, the delay is doubled after each failed attempt.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnection.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of the delay which is randomly reduced, from 0 to 1,
+    /// to avoid clients reconnecting at the same time.
+    pub jitter: f64,
+    /// Failed attempts in a row before giving up, the client waits for
+    /// [ClientHandle::resume] or [ClientHandle::set_remote] after that.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.5,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    fn delay(&self, attempts: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .checked_mul(1 << attempts.saturating_sub(1).min(16))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+        delay.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * rand::random::<f64>())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientCommand {
+    Run,
+    Pause,
+    Stop,
+}
+
+#[derive(Debug, Clone)]
+struct ClientControl {
+    remote: String,
+    command: ClientCommand,
+}
+
+/// Controls the background sync started by [start_client] or [start_multiplexed_client].
+///
+/// Dropping the handle doesn't stop the sync.
+#[derive(Clone)]
+pub struct ClientHandle {
+    control: Arc<watch::Sender<ClientControl>>,
+    status: SyncStatusReceiver,
+    // workspaces joining the sync of a multiplexed client
+    joins: Option<UnboundedSender<SyncedWorkspace>>,
+    // tokens of the synced workspaces, which authorize their subdocuments
+    tokens: Arc<HashMap<String, String>>,
+}
+
+impl ClientHandle {
+    /// The latest status of the sync.
+    pub fn status(&self) -> SyncStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Receives the changes of the status.
+    pub fn subscribe(&self) -> SyncStatusReceiver {
+        self.status.clone()
+    }
+
+    /// Disconnect from the remote until [ClientHandle::resume],
+    /// local updates are kept and sent after resuming.
+    pub fn pause(&self) {
+        self.send(|control| control.command = ClientCommand::Pause);
+    }
+
+    /// Connect to the remote again if paused or gave up reconnecting.
+    pub fn resume(&self) {
+        self.send(|control| control.command = ClientCommand::Run);
+    }
+
+    /// Disconnect and end the sync, the workspace can be synced by [start_client] again.
+    pub fn stop(&self) {
+        self.send(|control| control.command = ClientCommand::Stop);
+    }
+
+    /// Reconnect to another remote.
+    pub fn set_remote(&self, remote: String) {
+        self.send(|control| control.remote = remote);
+    }
+
+    /// Load the subdocument of the space in the workspace from the storage and sync it
+    /// with the workspace, returns `None` if the space has no subdocument.
+    ///
+    /// Subdocuments are only synced by [start_multiplexed_client] and authorized by the
+    /// token of the workspace, their local changes are sent once written to the storage
+    /// by their guids. A new subdocument is rejected by the remote until the workspace
+    /// with it is synced, then it can be loaded again.
+    pub async fn load_subdoc(
+        &self,
+        storage: &JwstStorage,
+        workspace: &Workspace,
+        space_id: &str,
+    ) -> JwstResult<Option<Workspace>> {
+        let Some(subdoc) = storage.docs().get_subdoc(workspace, space_id).await? else {
+            return Ok(None);
+        };
+
+        if let Some(joins) = &self.joins {
+            let remote = self.control.borrow().remote.clone();
+            let token = self.tokens.get(&workspace.id()).cloned();
+            let subdocs = prepare_workspaces(storage, &[(subdoc.clone(), token)], &remote).await;
+            for mut synced in subdocs {
+                synced.parent = Some(workspace.id());
+                if joins.send(synced).is_err() {
+                    warn!("failed to sync subdoc {}: sync stopped", subdoc.id());
+                }
+            }
+        }
+
+        Ok(Some(subdoc))
+    }
+
+    fn send(&self, modify: impl FnOnce(&mut ClientControl)) {
+        self.control.send_if_modified(|control| {
+            // the stopped sync can't be controlled anymore
+            if control.command == ClientCommand::Stop {
+                return false;
+            }
+            modify(control);
+            true
+        });
+    }
+}
+
+// wait for the duration or until the control is changed, returns whether the control is changed,
+// the local updates are still counted while offline
+async fn wait_offline(
+    duration: Option<Duration>,
+    workspaces: &[SyncedWorkspace],
+    status: &watch::Sender<SyncStatus>,
+    control: &mut watch::Receiver<ClientControl>,
+) -> bool {
+    let interval = Duration::from_millis(500);
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    loop {
+        let remaining = deadline.map_or(interval, |deadline| {
+            deadline.saturating_duration_since(Instant::now())
+        });
+        if remaining.is_zero() {
+            return false;
+        }
+        tokio::select! {
+            _ = sleep(remaining.min(interval)) => {
+                update_status(status, None, pending_updates(workspaces))
+            }
+            Ok(_) = control.changed() => return true,
+        }
+    }
+}
+
+async fn run_client(
+    first_sync: Arc<AtomicBool>,
+    mut workspaces: Vec<SyncedWorkspace>,
+    mut joins: Option<UnboundedReceiver<SyncedWorkspace>>,
+    status: &watch::Sender<SyncStatus>,
+    mut control: watch::Receiver<ClientControl>,
+    config: ReconnectConfig,
+) {
+    let mut attempts = 0;
+    'reconnect: loop {
+        // the acknowledged states are saved once the connection is closed
+        for synced in workspaces.iter_mut() {
+            synced.queue.flush().await;
+        }
+        let ClientControl { remote, command } = control.borrow_and_update().clone();
+        match command {
+            ClientCommand::Run => {}
+            ClientCommand::Pause => {
+                first_sync.store(true, Ordering::Release);
+                let pending = pending_updates(&workspaces);
+                update_status(status, Some(SyncState::Paused), pending);
+                wait_offline(None, &workspaces, status, &mut control).await;
+                attempts = 0;
+                continue;
+            }
+            ClientCommand::Stop => break,
+        }
+        for synced in workspaces.iter_mut() {
+            synced.queue.set_remote(remote.clone()).await;
+        }
+
+        let result = {
+            // only the multiplexed clients have workspaces joining
+            let sync = if let Some(joins) = joins.as_mut() {
+                Either::Left(run_multiplexed_sync(
+                    first_sync.clone(),
+                    &mut workspaces,
+                    joins,
+                    remote.clone(),
+                    status,
+                ))
+            } else {
+                Either::Right(run_sync(
+                    first_sync.clone(),
+                    &mut workspaces[0],
+                    remote.clone(),
+                    status,
+                ))
+            };
+            tokio::pin!(sync);
+            loop {
+                tokio::select! {
+                    result = &mut sync => break result,
+                    Ok(_) = control.changed() => {
+                        // resuming a running sync keeps the connection
+                        let control = control.borrow();
+                        if control.command != ClientCommand::Run || control.remote != remote {
+                            attempts = 0;
+                            continue 'reconnect;
+                        }
+                    }
+                }
+            }
+        };
+        first_sync.store(true, Ordering::Release);
+        let pending = pending_updates(&workspaces);
+        match result {
+            Ok(_) => {
+                attempts = 0;
+                warn!("Remote sync connection disconnected");
+                if !matches!(status.borrow().state, SyncState::Offline(_)) {
+                    let state = SyncState::Offline("connection closed".into());
+                    update_status(status, Some(state), pending);
+                }
+            }
+            Err(e) => {
+                warn!("Remote sync error: {}", e);
+                update_status(status, Some(SyncState::Offline(e.to_string())), pending);
+            }
+        }
+
+        attempts += 1;
+        if config.max_attempts.map_or(false, |max| attempts >= max) {
+            warn!("Remote sync failed {attempts} times, wait for resuming");
+            wait_offline(None, &workspaces, status, &mut control).await;
+            attempts = 0;
+        } else {
+            let delay = config.delay(attempts);
+            debug!("try to reconnect in {}ms", delay.as_millis());
+            if wait_offline(Some(delay), &workspaces, status, &mut control).await {
+                attempts = 0;
+            }
+        }
+    }
+
+    let pending_updates = pending_updates(&workspaces);
+    // the workspaces can be synced again once the local updates are not received
+    drop(workspaces);
+    drop(joins);
+    first_sync.store(true, Ordering::Release);
+    status.send_replace(SyncStatus {
+        state: SyncState::Stopped,
+        pending_updates,
+    });
+}
+
+fn start_sync_thread(
+    workspaces: Vec<SyncedWorkspace>,
+    remote: String,
+    multiplexed: bool,
+    config: ReconnectConfig,
+) -> ClientHandle {
     debug!("spawn sync thread");
+    let tokens = workspaces
+        .iter()
+        .filter_map(|synced| Some((synced.workspace.id(), synced.token.clone()?)))
+        .collect();
+    let (status, status_rx) = watch::channel(SyncStatus {
+        state: SyncState::Connecting,
+        pending_updates: pending_updates(&workspaces),
+    });
+    let control = Arc::new(watch::channel(ClientControl {
+        remote,
+        command: ClientCommand::Run,
+    }));
     let first_sync = Arc::new(AtomicBool::new(false));
     let first_sync_cloned = first_sync.clone();
-    let workspace = workspace.clone();
+    let (joins, joins_rx) = if multiplexed {
+        let (joins, joins_rx) = unbounded_channel();
+        (Some(joins), Some(joins_rx))
+    } else {
+        (None, None)
+    };
+    let control_rx = control.subscribe();
+    let control_cloned = control.clone();
     std::thread::spawn(move || {
         let Ok(rt) = tokio::runtime::Runtime::new() else {
             return error!("Failed to create runtime");
@@ -141,32 +785,17 @@ fn start_sync_thread(workspace: &Workspace, remote: String, mut rx: Receiver<Vec
                 sleep(Duration::from_secs(2)).await;
                 first_sync_cloned_2.store(true, Ordering::Release);
             });
-            loop {
-                match run_sync(
-                    first_sync_cloned.clone(),
-                    &workspace,
-                    remote.clone(),
-                    &mut rx,
-                )
-                .await
-                {
-                    Ok(true) => {
-                        debug!("sync thread finished");
-                        first_sync_cloned.store(true, Ordering::Release);
-                        break;
-                    }
-                    Ok(false) => {
-                        first_sync_cloned.store(true, Ordering::Release);
-                        warn!("Remote sync connection disconnected, try again in 2 seconds");
-                        sleep(Duration::from_secs(3)).await;
-                    }
-                    Err(e) => {
-                        first_sync_cloned.store(true, Ordering::Release);
-                        warn!("Remote sync error, try again in 3 seconds: {}", e);
-                        sleep(Duration::from_secs(1)).await;
-                    }
-                }
-            }
+            // keep the control alive even if all handles are dropped
+            let _control = control_cloned;
+            run_client(
+                first_sync_cloned,
+                workspaces,
+                joins_rx,
+                &status,
+                control_rx,
+                config,
+            )
+            .await;
 
             debug!("end sync thread");
         });
@@ -177,24 +806,419 @@ fn start_sync_thread(workspace: &Workspace, remote: String, mut rx: Receiver<Vec
     {
         std::thread::sleep(Duration::from_millis(100));
     }
+
+    ClientHandle {
+        control,
+        status: status_rx,
+        joins,
+        tokens: Arc::new(tokens),
+    }
 }
 
+// prepare the sync of the workspaces which are not synced yet, with their tokens
+async fn prepare_workspaces(
+    storage: &JwstStorage,
+    workspaces: &[(Workspace, Option<String>)],
+    remote: &str,
+) -> Vec<SyncedWorkspace> {
+    let mut remotes = storage.docs().remote().write().await;
+    let mut synced = Vec::with_capacity(workspaces.len());
+    for (workspace, token) in workspaces {
+        let id = workspace.id();
+        // the sync of the workspace has been stopped if nobody receives the updates
+        if remotes.get(&id).map_or(true, |tx| tx.receiver_count() == 0) {
+            let (tx, rx) = channel(100);
+            let queue =
+                OutboundQueue::load(storage.docs().clone(), workspace.clone(), remote.into()).await;
+
+            synced.push(SyncedWorkspace {
+                workspace: workspace.clone(),
+                token: token.clone(),
+                parent: None,
+                rx,
+                queue,
+            });
+
+            remotes.insert(id, tx);
+        }
+    }
+    synced
+}
+
+/// Load the workspace and sync it with the remote in background,
+/// returns the handle of the sync if it's started by this call,
+/// `None` if the remote is empty or the workspace is already synced.
 pub async fn start_client(
     storage: &JwstStorage,
     id: String,
     remote: String,
-) -> JwstResult<Workspace> {
-    let workspace = storage.docs().get(id.clone()).await?;
+) -> JwstResult<(Workspace, Option<ClientHandle>)> {
+    start_client_with_config(storage, id, remote, ReconnectConfig::default()).await
+}
 
+pub async fn start_client_with_config(
+    storage: &JwstStorage,
+    id: String,
+    remote: String,
+    config: ReconnectConfig,
+) -> JwstResult<(Workspace, Option<ClientHandle>)> {
+    let workspace = storage.docs().get(id).await?;
+
+    let mut handle = None;
     if !remote.is_empty() {
-        if let Entry::Vacant(entry) = storage.docs().remote().write().await.entry(id.clone()) {
-            let (tx, rx) = channel(100);
+        let workspaces = prepare_workspaces(storage, &[(workspace.clone(), None)], &remote).await;
+        if !workspaces.is_empty() {
+            handle = Some(start_sync_thread(workspaces, remote, false, config));
+        }
+    }
+
+    Ok((workspace, handle))
+}
+
+/// A workspace synced by [start_multiplexed_client].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiplexedWorkspace {
+    pub id: String,
+    /// Token of the workspace if the remote requires one, e.g. signed by the remote.
+    pub token: Option<String>,
+}
+
+impl From<String> for MultiplexedWorkspace {
+    fn from(id: String) -> Self {
+        Self { id, token: None }
+    }
+}
+
+impl From<&str> for MultiplexedWorkspace {
+    fn from(id: &str) -> Self {
+        id.to_owned().into()
+    }
+}
 
-            start_sync_thread(&workspace, remote, rx);
+/// Load the workspaces and sync them over a single connection to the multiplexed
+/// endpoint of the remote, e.g. `ws://localhost:3000/collaboration`,
+/// the workspaces which are already synced are skipped.
+pub async fn start_multiplexed_client(
+    storage: &JwstStorage,
+    workspaces: Vec<MultiplexedWorkspace>,
+    remote: String,
+    config: ReconnectConfig,
+) -> JwstResult<(Vec<Workspace>, Option<ClientHandle>)> {
+    let mut loaded = Vec::with_capacity(workspaces.len());
+    for MultiplexedWorkspace { id, token } in workspaces {
+        loaded.push((storage.docs().get(id).await?, token));
+    }
 
-            entry.insert(tx);
+    let mut handle = None;
+    if !remote.is_empty() {
+        let synced = prepare_workspaces(storage, &loaded, &remote).await;
+        if !synced.is_empty() {
+            handle = Some(start_sync_thread(synced, remote, true, config));
         }
     }
 
-    Ok(workspace)
+    let workspaces = loaded.into_iter().map(|(workspace, _)| workspace).collect();
+    Ok((workspaces, handle))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reconnect_delay_test() {
+        let mut config = ReconnectConfig {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.0,
+            max_attempts: None,
+        };
+        assert_eq!(config.delay(1), Duration::from_secs(1));
+        assert_eq!(config.delay(2), Duration::from_secs(2));
+        assert_eq!(config.delay(4), Duration::from_secs(8));
+        assert_eq!(config.delay(5), Duration::from_secs(10));
+        assert_eq!(config.delay(100), Duration::from_secs(10));
+
+        config.jitter = 0.5;
+        for _ in 0..100 {
+            let delay = config.delay(3);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+        }
+    }
+
+    async fn wait_state(status: &mut SyncStatusReceiver, state: fn(&SyncState) -> bool) {
+        while !state(&status.borrow().state) {
+            status.changed().await.unwrap();
+        }
+    }
+
+    // polls until the condition holds, the sync runs in other threads
+    async fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("condition is not met in time");
+    }
+
+    fn has_block(workspace: &Workspace, block_id: &str) -> bool {
+        workspace.with_trx(|t| {
+            t.get_exists_space("space")
+                .map_or(false, |space| space.exists(&t.trx, block_id))
+        })
+    }
+
+    fn create_block(workspace: &Workspace, block_id: &str) {
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, block_id, "flavor1");
+        });
+    }
+
+    // serves the collaboration of a workspace at `/collaboration/:workspace`
+    // and the multiplexed one at `/collaboration`, returns the url of the latter
+    async fn serve_remote() -> (Arc<MinimumServerContext>, String) {
+        use axum::{
+            extract::{ws::WebSocketUpgrade, Path},
+            routing::get,
+            Extension, Router,
+        };
+        use nanoid::nanoid;
+
+        type ServerContext = Extension<Arc<MinimumServerContext>>;
+        let app = Router::new()
+            .route(
+                "/collaboration",
+                get(
+                    |Extension(context): ServerContext, ws: WebSocketUpgrade| async move {
+                        ws.protocols(["AFFiNE"]).on_upgrade(move |socket| {
+                            handle_multiplexed_connector(
+                                context,
+                                nanoid!(),
+                                |_, _| async { Some(SessionAccess::ReadWrite) },
+                                move || socket_connector(socket, "multiplexed"),
+                            )
+                        })
+                    },
+                ),
+            )
+            .route(
+                "/collaboration/:workspace",
+                get(
+                    |Extension(context): ServerContext,
+                     Path(workspace): Path<String>,
+                     ws: WebSocketUpgrade| async move {
+                        ws.protocols(["AFFiNE"]).on_upgrade(move |socket| {
+                            handle_connector(
+                                context,
+                                workspace.clone(),
+                                nanoid!(),
+                                SessionAccess::ReadWrite,
+                                move || socket_connector(socket, &workspace),
+                            )
+                        })
+                    },
+                ),
+            );
+
+        let context = MinimumServerContext::new().await;
+        let server = axum::Server::bind(&([127, 0, 0, 1], 0).into())
+            .serve(app.layer(Extension(context.clone())).into_make_service());
+        let remote = format!("ws://{}/collaboration", server.local_addr());
+        tokio::spawn(server);
+
+        (context, remote)
+    }
+
+    #[tokio::test]
+    async fn client_handle_test() {
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+
+        // nothing listens on the port
+        let (_, handle) = start_client(&storage, "test".into(), "ws://127.0.0.1:1".into())
+            .await
+            .unwrap();
+        let handle = handle.unwrap();
+        let mut status = handle.subscribe();
+        wait_state(&mut status, |state| matches!(state, SyncState::Offline(_))).await;
+
+        handle.pause();
+        wait_state(&mut status, |state| state == &SyncState::Paused).await;
+        handle.set_remote("ws://127.0.0.1:2".into());
+        handle.resume();
+        wait_state(&mut status, |state| matches!(state, SyncState::Offline(_))).await;
+
+        // the workspace is already synced by the first client
+        let (_, another) = start_client(&storage, "test".into(), "ws://127.0.0.1:1".into())
+            .await
+            .unwrap();
+        assert!(another.is_none());
+        let (_, local) = start_client(&storage, "local".into(), "".into())
+            .await
+            .unwrap();
+        assert!(local.is_none());
+
+        handle.stop();
+        wait_state(&mut status, |state| state == &SyncState::Stopped).await;
+        handle.resume();
+        assert_eq!(handle.status().state, SyncState::Stopped);
+
+        // sync again after stopped
+        let (_, handle) = start_client(&storage, "test".into(), "ws://127.0.0.1:1".into())
+            .await
+            .unwrap();
+        handle.unwrap().stop();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn client_sync_test() {
+        let (server, remote) = serve_remote().await;
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+
+        let (workspace, handle) = start_client(&storage, "test".into(), format!("{remote}/test"))
+            .await
+            .unwrap();
+        let handle = handle.unwrap();
+        let mut status = handle.subscribe();
+        wait_state(&mut status, |state| state == &SyncState::Synced).await;
+
+        let remote_workspace = server.get_workspace("test").await.unwrap();
+        create_block(&workspace, "local");
+        create_block(&remote_workspace, "remote");
+        wait_for(|| has_block(&remote_workspace, "local")).await;
+        wait_for(|| has_block(&workspace, "remote")).await;
+        // the local change is acknowledged by the remote
+        wait_for(|| handle.status().pending_updates == 0).await;
+
+        // the changes made while paused are replayed after resuming
+        handle.pause();
+        wait_state(&mut status, |state| state == &SyncState::Paused).await;
+        create_block(&workspace, "offline");
+        wait_for(|| handle.status().pending_updates > 0).await;
+        handle.resume();
+        wait_state(&mut status, |state| state == &SyncState::Synced).await;
+        wait_for(|| has_block(&remote_workspace, "offline")).await;
+        wait_for(|| handle.status().pending_updates == 0).await;
+
+        handle.stop();
+        wait_state(&mut status, |state| state == &SyncState::Stopped).await;
+    }
+
+    #[tokio::test]
+    async fn multiplexed_client_test() {
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+
+        let (_, handle) = start_client(&storage, "a".into(), "ws://127.0.0.1:1".into())
+            .await
+            .unwrap();
+        let handle = handle.unwrap();
+
+        // the workspace synced by another client is skipped
+        let ids = vec!["a", "b", "c"];
+        let workspaces = ids.iter().map(|&id| id.into()).collect::<Vec<_>>();
+        let remote = "ws://127.0.0.1:1/collaboration".to_string();
+        let (loaded, multiplexed) = start_multiplexed_client(
+            &storage,
+            workspaces.clone(),
+            remote.clone(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(loaded.iter().map(|ws| ws.id()).collect::<Vec<_>>(), ids);
+        let multiplexed = multiplexed.unwrap();
+        let mut status = multiplexed.subscribe();
+        wait_state(&mut status, |state| matches!(state, SyncState::Offline(_))).await;
+
+        let (_, another) =
+            start_multiplexed_client(&storage, workspaces, remote, Default::default())
+                .await
+                .unwrap();
+        assert!(another.is_none());
+
+        handle.stop();
+        multiplexed.stop();
+        wait_state(&mut status, |state| state == &SyncState::Stopped).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn multiplexed_sync_test() {
+        let (server, remote) = serve_remote().await;
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+
+        let (workspaces, handle) = start_multiplexed_client(
+            &storage,
+            vec!["a".into(), "b".into()],
+            remote,
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        let handle = handle.unwrap();
+        let mut status = handle.subscribe();
+        wait_state(&mut status, |state| state == &SyncState::Synced).await;
+
+        // both workspaces are synced over the same connection
+        let remote_a = server.get_workspace("a").await.unwrap();
+        let remote_b = server.get_workspace("b").await.unwrap();
+        create_block(&workspaces[0], "local");
+        create_block(&remote_b, "remote");
+        wait_for(|| has_block(&remote_a, "local")).await;
+        wait_for(|| has_block(&workspaces[1], "remote")).await;
+        wait_for(|| handle.status().pending_updates == 0).await;
+        assert!(!has_block(&remote_b, "local"));
+        assert!(!has_block(&workspaces[0], "remote"));
+
+        // a subdocument loaded later joins the same connection
+        let guid = workspaces[0].with_trx(|mut t| t.create_subdoc("page").guid().to_string());
+        // the subdocument is authorized once the parent with it is synced
+        wait_for(|| remote_a.with_trx(|t| t.subdocs().iter().any(|(_, subdoc)| subdoc == &guid)))
+            .await;
+        let subdoc = handle
+            .load_subdoc(&storage, &workspaces[0], "page")
+            .await
+            .unwrap()
+            .unwrap();
+        create_block(&subdoc, "page");
+        let remote_subdoc = server.get_workspace(&guid).await.unwrap();
+        wait_for(|| has_block(&remote_subdoc, "page")).await;
+
+        handle.stop();
+        wait_state(&mut status, |state| state == &SyncState::Stopped).await;
+    }
+
+    #[tokio::test]
+    async fn subdoc_client_test() {
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+
+        let remote = "ws://127.0.0.1:1/collaboration".to_string();
+        let (workspaces, handle) =
+            start_multiplexed_client(&storage, vec!["ws".into()], remote, Default::default())
+                .await
+                .unwrap();
+        let (workspace, handle) = (&workspaces[0], handle.unwrap());
+        let guid = workspace.with_trx(|mut t| t.create_subdoc("page").guid().to_string());
+
+        assert!(handle
+            .load_subdoc(&storage, workspace, "space")
+            .await
+            .unwrap()
+            .is_none());
+        let subdoc = handle
+            .load_subdoc(&storage, workspace, "page")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(subdoc.id(), guid);
+
+        // the subdocument is synced by the client
+        let (_, another) = start_client(&storage, guid, "ws://127.0.0.1:1".into())
+            .await
+            .unwrap();
+        assert!(another.is_none());
+
+        handle.stop();
+    }
 }
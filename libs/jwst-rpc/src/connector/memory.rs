@@ -104,7 +104,7 @@ pub fn memory_connector(
                             debug!("recv change: {} end", data.len());
                         });
                     }
-                    Message::Close => break,
+                    Message::Close | Message::Disconnect(_) => break,
                     Message::Ping => continue,
                 }
             }
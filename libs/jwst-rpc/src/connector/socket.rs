@@ -1,6 +1,6 @@
 use super::*;
 use axum::{
-    extract::ws::{Message as WebSocketMessage, WebSocket},
+    extract::ws::{CloseFrame, Message as WebSocketMessage, WebSocket},
     Error,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
@@ -21,6 +21,10 @@ impl From<Message> for WebSocketMessage {
         match value {
             Message::Binary(data) => WebSocketMessage::Binary(data),
             Message::Close => WebSocketMessage::Close(None),
+            Message::Disconnect(reason) => WebSocketMessage::Close(Some(CloseFrame {
+                code: reason.code(),
+                reason: reason.reason().into(),
+            })),
             Message::Ping => WebSocketMessage::Ping(vec![]),
         }
     }
@@ -39,6 +43,7 @@ pub fn socket_connector(
         let workspace_id = workspace_id.to_owned();
         tokio::spawn(async move {
             while let Some(msg) = local_receiver.recv().await {
+                let disconnect = matches!(msg, Message::Disconnect(_));
                 if let Err(e) = socket_tx.send(msg.into()).await {
                     let error = e.to_string();
                     if is_connection_closed(e) {
@@ -47,6 +52,10 @@ pub fn socket_connector(
                         error!("socket send error: {}", error);
                     }
                 }
+                if disconnect {
+                    // nothing can be sent after the close frame
+                    break;
+                }
             }
             info!("socket send final: {}", workspace_id);
         });
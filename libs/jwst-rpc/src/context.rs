@@ -1,11 +1,14 @@
 use super::{
+    backend::BroadcastBridge,
     broadcast::{subscribe, BroadcastChannels, BroadcastType},
+    limits::{ConnectionLimiter, ConnectionLimits},
+    session::{ReadOnlyMessages, SessionAccess},
     *,
 };
 use async_trait::async_trait;
 use jwst::{DocStorage, JwstResult, Workspace};
-use jwst_storage::JwstStorage;
-use std::ops::Deref;
+use jwst_storage::{EvictionPolicy, JwstStorage};
+use std::{collections::HashSet, ops::Deref};
 use tokio::sync::{
     broadcast::{channel as broadcast, Receiver as BroadcastReceiver, Sender as BroadcastSender},
     mpsc::{Receiver as MpscReceiver, Sender as MpscSender},
@@ -37,20 +40,71 @@ pub trait RpcContextImpl<'a> {
     fn get_storage(&self) -> &JwstStorage;
     fn get_channel(&self) -> &BroadcastChannels;
 
+    /// Relay the broadcast to other nodes, `None` if the server runs as a single node.
+    fn get_bridge(&self) -> Option<&BroadcastBridge> {
+        None
+    }
+
+    /// Limits of the messages received by each collaboration connection.
+    fn get_limits(&self) -> ConnectionLimits {
+        ConnectionLimits::default()
+    }
+
     async fn get_workspace(&self, id: &str) -> JwstResult<Workspace> {
         self.get_storage().create_workspace(id).await
     }
 
+    /// Unload the workspaces without connected clients by the policy,
+    /// their broadcast channels are dropped with them.
+    async fn evict_workspaces(&self, policy: &EvictionPolicy) -> Vec<String> {
+        let in_use = self
+            .get_channel()
+            .read()
+            .await
+            .iter()
+            .filter(|(_, tx)| tx.receiver_count() > 0)
+            .map(|(id, _)| id.clone())
+            .collect::<HashSet<_>>();
+
+        let evicted = self
+            .get_storage()
+            .evict_workspaces(policy, |id| in_use.contains(id))
+            .await;
+
+        let mut channel = self.get_channel().write().await;
+        for id in &evicted {
+            if let Entry::Occupied(tx) = channel.entry(id.clone()) {
+                if tx.get().receiver_count() == 0 {
+                    tx.remove();
+                }
+            }
+        }
+
+        evicted
+    }
+
     async fn join_server_broadcast(&self, id: &str) -> BroadcastReceiver<Vec<u8>> {
-        let id = id.into();
-        match self.get_storage().docs().remote().write().await.entry(id) {
-            Entry::Occupied(tx) => tx.get().subscribe(),
+        let (tx, rx) = match self
+            .get_storage()
+            .docs()
+            .remote()
+            .write()
+            .await
+            .entry(id.into())
+        {
+            Entry::Occupied(tx) => (tx.get().clone(), tx.get().subscribe()),
             Entry::Vacant(v) => {
                 let (tx, rx) = broadcast(100);
-                v.insert(tx);
-                rx
+                v.insert(tx.clone());
+                (tx, rx)
             }
+        };
+
+        if let Some(bridge) = self.get_bridge() {
+            bridge.join_server(id, &tx).await;
         }
+
+        rx
     }
 
     async fn join_broadcast(
@@ -75,6 +129,11 @@ pub trait RpcContextImpl<'a> {
         self.save_update(&id, identifier, broadcast_tx.subscribe())
             .await;
 
+        // relay thread to other nodes
+        if let Some(bridge) = self.get_bridge() {
+            bridge.join(workspace, &broadcast_tx).await;
+        }
+
         broadcast_tx
     }
 
@@ -139,6 +198,7 @@ pub trait RpcContextImpl<'a> {
         &self,
         id: &str,
         identifier: &str,
+        access: SessionAccess,
         local_tx: MpscSender<Message>,
         mut remote_rx: MpscReceiver<Vec<u8>>,
     ) {
@@ -149,8 +209,39 @@ pub trait RpcContextImpl<'a> {
             .get_workspace(&id)
             .await
             .expect("workspace not found");
+        let mut limiter = ConnectionLimiter::new(self.get_limits());
         tokio::spawn(async move {
             while let Some(binary) = remote_rx.recv().await {
+                if let Err(reason) = limiter.check(binary.len()) {
+                    warn!(
+                        "close connection of {identifier:?} in {}: {}",
+                        workspace.id(),
+                        reason.reason()
+                    );
+                    let _ = local_tx.send(Message::Disconnect(reason)).await;
+                    break;
+                }
+
+                let binary = if access.can_write() {
+                    binary
+                } else {
+                    let ReadOnlyMessages { messages, rejected } = ReadOnlyMessages::filter(&binary);
+                    if rejected > 0 {
+                        warn!("reject {rejected} updates of read-only session {identifier:?}");
+                        if local_tx
+                            .send(Message::Binary(ReadOnlyMessages::rejection()))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    if messages.is_empty() {
+                        continue;
+                    }
+                    messages
+                };
+
                 let ts = Instant::now();
                 let message = workspace.sync_decode_message(&binary).await;
                 if ts.elapsed().as_micros() > 50 {
@@ -1,14 +1,30 @@
+mod backend;
 mod broadcast;
 mod client;
 mod connector;
 mod context;
+mod limits;
+mod multiplex;
+mod queue;
+mod session;
 mod utils;
 
+pub use backend::{
+    BroadcastBackend, BroadcastBridge, BroadcastBroker, MemoryBackend, NetworkBackend,
+};
 pub use broadcast::{BroadcastChannels, BroadcastType};
-pub use client::start_client;
+pub use client::{
+    start_client, start_client_with_config, start_multiplexed_client, ClientHandle,
+    MultiplexedWorkspace, ReconnectConfig, SyncState, SyncStatus, SyncStatusReceiver,
+};
 pub use connector::{memory_connector, socket_connector};
 pub use context::RpcContextImpl;
-pub use utils::{connect_memory_workspace, MinimumServerContext};
+pub use limits::{CloseReason, ConnectionLimits};
+pub use multiplex::{handle_multiplexed_connector, MultiplexedMessage};
+pub use session::SessionAccess;
+pub use utils::{
+    connect_memory_workspace, connect_memory_workspace_with_access, MinimumServerContext,
+};
 
 use jwst::{debug, error, info, trace, warn};
 use std::{collections::hash_map::Entry, sync::Arc, time::Instant};
@@ -21,6 +37,8 @@ use tokio::{
 pub enum Message {
     Binary(Vec<u8>),
     Close,
+    /// Close the connection with the reason, e.g. a limit is exceeded.
+    Disconnect(CloseReason),
     Ping,
 }
 
@@ -28,14 +46,18 @@ pub async fn handle_connector(
     context: Arc<impl RpcContextImpl<'static> + Send + Sync + 'static>,
     workspace_id: String,
     identifier: String,
+    access: SessionAccess,
     get_channel: impl FnOnce() -> (Sender<Message>, Receiver<Vec<u8>>, Sender<bool>),
 ) {
-    info!("{} collaborate with workspace {}", identifier, workspace_id);
+    info!(
+        "{} collaborate with workspace {} by {:?}",
+        identifier, workspace_id, access
+    );
 
     let (tx, rx, first_init) = get_channel();
 
     context
-        .apply_change(&workspace_id, &identifier, tx.clone(), rx)
+        .apply_change(&workspace_id, &identifier, access, tx.clone(), rx)
         .await;
 
     let mut ws = context
@@ -225,6 +247,142 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn connection_limits_test() {
+        let workspace_id = format!("test{}", rand::random::<usize>());
+
+        let (server, ws, init_state) = MinimumServerContext::init_workspace(
+            MinimumServerContext::new_with_limits(ConnectionLimits {
+                max_message_size: Some(1),
+                ..Default::default()
+            })
+            .await,
+            &workspace_id,
+        )
+        .await;
+
+        let (doc, _, tx_handler, rx_handler) =
+            connect_memory_workspace(server, &init_state, &workspace_id).await;
+
+        doc.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "block1", "flavor1");
+        });
+
+        // the connection is closed by the server instead of applying the update
+        tx_handler.await.unwrap();
+        rx_handler.join().unwrap();
+
+        ws.with_trx(|t| assert!(t.get_exists_space("space").is_none()));
+    }
+
+    #[tokio::test]
+    async fn readonly_session_test() {
+        let workspace_id = format!("test{}", rand::random::<usize>());
+
+        let (server, ws, init_state) =
+            MinimumServerContext::new_with_workspace(&workspace_id).await;
+
+        let (doc1, tx1, _, _) = connect_memory_workspace_with_access(
+            server.clone(),
+            &init_state,
+            &workspace_id,
+            SessionAccess::ReadOnly,
+        )
+        .await;
+        let (doc2, _, _, _) =
+            connect_memory_workspace(server.clone(), &init_state, &workspace_id).await;
+
+        doc1.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "readonly", "flavor1");
+        });
+        doc2.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "writable", "flavor1");
+        });
+
+        // wait for the updates to be applied
+        sleep(Duration::from_millis(500)).await;
+
+        ws.with_trx(|t| {
+            let space = t.get_exists_space("space").unwrap();
+            assert!(!space.exists(&t.trx, "readonly"));
+            assert!(space.exists(&t.trx, "writable"));
+        });
+        // read-only sessions still receive the updates
+        doc1.with_trx(|t| {
+            let space = t.get_exists_space("space").unwrap();
+            assert!(space.exists(&t.trx, "writable"));
+        });
+
+        tx1.send(Message::Close).await.unwrap();
+    }
+
+    // a client of node1 writes, a client of node2 receives
+    async fn cross_node_sync(backend: Arc<dyn BroadcastBackend>) {
+        let workspace_id = format!("test{}", rand::random::<usize>());
+
+        let (server1, _, init_state1) = MinimumServerContext::init_workspace(
+            MinimumServerContext::new_with_backend(backend.clone()).await,
+            &workspace_id,
+        )
+        .await;
+        let (server2, ws2, init_state2) = MinimumServerContext::init_workspace(
+            MinimumServerContext::new_with_backend(backend).await,
+            &workspace_id,
+        )
+        .await;
+
+        let (doc1, _, _, _) = connect_memory_workspace(server1, &init_state1, &workspace_id).await;
+        let (mut doc2, tx2, tx_handler, rx_handler) =
+            connect_memory_workspace(server2, &init_state2, &workspace_id).await;
+
+        // close connection after doc1 is broadcasted
+        let sub = doc2
+            .observe(move |_, _| {
+                futures::executor::block_on(async {
+                    let _ = tx2.send(Message::Close).await;
+                });
+            })
+            .unwrap();
+
+        doc1.with_trx(|mut t| {
+            let space = t.get_space("space");
+            let block1 = space.create(&mut t.trx, "block1", "flavor1");
+            block1.set(&mut t.trx, "key1", "val1");
+        });
+
+        tx_handler.await.unwrap();
+        rx_handler.join().unwrap();
+
+        drop(sub);
+
+        for ws in [doc2, ws2] {
+            ws.with_trx(|mut t| {
+                let space = t.get_space("space");
+                let block1 = space.get(&mut t.trx, "block1").unwrap();
+
+                assert_eq!(block1.flavor(&t.trx), "flavor1");
+                assert_eq!(block1.get(&t.trx, "key1").unwrap().to_string(), "val1");
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_backend_sync_test() {
+        cross_node_sync(Arc::new(MemoryBackend::default())).await;
+    }
+
+    #[tokio::test]
+    async fn network_backend_sync_test() {
+        let broker = BroadcastBroker::bind("127.0.0.1:0", None).await.unwrap();
+        let addr = broker.local_addr().unwrap();
+        tokio::spawn(broker.serve());
+
+        cross_node_sync(Arc::new(NetworkBackend::connect(addr, None).await.unwrap())).await;
+    }
+
     #[ignore = "somewhat slow, only natively tested"]
     #[tokio::test(flavor = "multi_thread")]
     async fn sync_stress_test() -> JwstResult<()> {
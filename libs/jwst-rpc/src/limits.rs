@@ -0,0 +1,144 @@
+use super::*;
+
+/// Why the server closes a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// More messages than [ConnectionLimits::max_messages_per_second].
+    MessageRate,
+    /// A message larger than [ConnectionLimits::max_message_size].
+    MessageSize,
+    /// More bytes than [ConnectionLimits::max_total_bytes] in total.
+    TotalBytes,
+}
+
+impl CloseReason {
+    /// The close code of websocket.
+    pub fn code(&self) -> u16 {
+        match self {
+            // message too big
+            Self::MessageSize => 1009,
+            // policy violation
+            Self::MessageRate | Self::TotalBytes => 1008,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::MessageRate => "message rate limit exceeded",
+            Self::MessageSize => "message size limit exceeded",
+            Self::TotalBytes => "total bytes limit exceeded",
+        }
+    }
+}
+
+/// Limits of the messages received by a collaboration connection,
+/// the connection is closed with a [CloseReason] once a limit is exceeded.
+///
+/// Nothing is limited by default, except the workspaces joined by a multiplexed connection.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimits {
+    /// Messages per second on average, bursts up to the same number are allowed.
+    pub max_messages_per_second: Option<u32>,
+    /// Bytes of a single message.
+    pub max_message_size: Option<usize>,
+    /// Bytes of all messages during the connection.
+    pub max_total_bytes: Option<u64>,
+    /// Workspaces joined by a multiplexed connection at the same time,
+    /// [ConnectionLimits::DEFAULT_MAX_WORKSPACES] if not set.
+    pub max_workspaces: Option<usize>,
+}
+
+impl ConnectionLimits {
+    pub const DEFAULT_MAX_WORKSPACES: usize = 64;
+
+    pub fn max_workspaces(&self) -> usize {
+        self.max_workspaces.unwrap_or(Self::DEFAULT_MAX_WORKSPACES)
+    }
+}
+
+// checks the messages of a connection, the rate is limited by a token bucket
+pub(crate) struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    tokens: f64,
+    refilled: Instant,
+    total_bytes: u64,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self {
+            tokens: limits.max_messages_per_second.unwrap_or_default() as f64,
+            limits,
+            refilled: Instant::now(),
+            total_bytes: 0,
+        }
+    }
+
+    pub fn check(&mut self, size: usize) -> Result<(), CloseReason> {
+        self.check_at(size, Instant::now())
+    }
+
+    fn check_at(&mut self, size: usize, now: Instant) -> Result<(), CloseReason> {
+        if let Some(max_size) = self.limits.max_message_size {
+            if size > max_size {
+                return Err(CloseReason::MessageSize);
+            }
+        }
+
+        if let Some(max_total) = self.limits.max_total_bytes {
+            self.total_bytes += size as u64;
+            if self.total_bytes > max_total {
+                return Err(CloseReason::TotalBytes);
+            }
+        }
+
+        if let Some(rate) = self.limits.max_messages_per_second {
+            let rate = rate as f64;
+            let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(rate);
+            self.refilled = now;
+            if self.tokens < 1.0 {
+                return Err(CloseReason::MessageRate);
+            }
+            self.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn connection_limiter_test() {
+        let mut limiter = ConnectionLimiter::new(ConnectionLimits::default());
+        for _ in 0..1000 {
+            assert_eq!(limiter.check(1024 * 1024), Ok(()));
+        }
+
+        let mut limiter = ConnectionLimiter::new(ConnectionLimits {
+            max_message_size: Some(10),
+            max_total_bytes: Some(25),
+            ..Default::default()
+        });
+        assert_eq!(limiter.check(11), Err(CloseReason::MessageSize));
+        assert_eq!(limiter.check(10), Ok(()));
+        assert_eq!(limiter.check(10), Ok(()));
+        assert_eq!(limiter.check(10), Err(CloseReason::TotalBytes));
+
+        let mut limiter = ConnectionLimiter::new(ConnectionLimits {
+            max_messages_per_second: Some(2),
+            ..Default::default()
+        });
+        let now = limiter.refilled;
+        assert_eq!(limiter.check_at(1, now), Ok(()));
+        assert_eq!(limiter.check_at(1, now), Ok(()));
+        assert_eq!(limiter.check_at(1, now), Err(CloseReason::MessageRate));
+        // a token is refilled in half a second
+        let now = now + Duration::from_millis(500);
+        assert_eq!(limiter.check_at(1, now), Ok(()));
+        assert_eq!(limiter.check_at(1, now), Err(CloseReason::MessageRate));
+    }
+}
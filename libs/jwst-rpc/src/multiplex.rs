@@ -0,0 +1,482 @@
+use super::{limits::ConnectionLimiter, *};
+use std::{collections::HashMap, future::Future};
+use tokio::{
+    sync::mpsc::{channel, error::TrySendError},
+    task::JoinHandle,
+};
+
+const JOIN: u8 = 0;
+const SYNC: u8 = 1;
+const LEAVE: u8 = 2;
+const REJECT: u8 = 3;
+
+/// Messages of a connection which syncs many workspaces, in the form of
+/// `[kind: u8][id length: u16 BE][id][payload]`.
+///
+/// The id is the id of a workspace or the guid of a subdocument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultiplexedMessage {
+    /// Join the sync of a workspace, with the token of the workspace if required.
+    ///
+    /// A subdocument is joined with its parent workspace, and authorized by the token
+    /// of the parent. The payload is `[token length: u16 BE][token][parent]`.
+    Join {
+        id: String,
+        token: Option<String>,
+        parent: Option<String>,
+    },
+    /// A y-sync message of a joined workspace.
+    Sync { id: String, data: Vec<u8> },
+    /// Leave the sync of a workspace, or the sync is closed by the server.
+    Leave { id: String },
+    /// The server rejects the workspace, e.g. the token is invalid.
+    Reject { id: String, reason: String },
+}
+
+impl MultiplexedMessage {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Join { id, .. }
+            | Self::Sync { id, .. }
+            | Self::Leave { id }
+            | Self::Reject { id, .. } => id,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let join;
+        let (kind, payload) = match self {
+            Self::Join { token, parent, .. } => {
+                let token = token.as_deref().unwrap_or_default().as_bytes();
+                let parent = parent.as_deref().unwrap_or_default().as_bytes();
+                join = [&(token.len() as u16).to_be_bytes()[..], token, parent].concat();
+                (JOIN, join.as_slice())
+            }
+            Self::Sync { data, .. } => (SYNC, data.as_slice()),
+            Self::Leave { .. } => (LEAVE, [].as_slice()),
+            Self::Reject { reason, .. } => (REJECT, reason.as_bytes()),
+        };
+        let id = self.id().as_bytes();
+
+        let mut binary = Vec::with_capacity(3 + id.len() + payload.len());
+        binary.push(kind);
+        binary.extend_from_slice(&(id.len() as u16).to_be_bytes());
+        binary.extend_from_slice(id);
+        binary.extend_from_slice(payload);
+        binary
+    }
+
+    pub fn decode(binary: &[u8]) -> Option<Self> {
+        let (&kind, binary) = binary.split_first()?;
+        let (id, payload) = split_string(binary)?;
+
+        match kind {
+            JOIN => {
+                let (token, parent) = split_string(payload)?;
+                let parent = String::from_utf8(parent.to_vec()).ok()?;
+                Some(Self::Join {
+                    id,
+                    token: (!token.is_empty()).then_some(token),
+                    parent: (!parent.is_empty()).then_some(parent),
+                })
+            }
+            SYNC => Some(Self::Sync {
+                id,
+                data: payload.to_vec(),
+            }),
+            LEAVE => Some(Self::Leave { id }),
+            REJECT => Some(Self::Reject {
+                id,
+                reason: String::from_utf8(payload.to_vec()).ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// splits the string prefixed by its length in u16 BE
+fn split_string(binary: &[u8]) -> Option<(String, &[u8])> {
+    let len = u16::from_be_bytes(binary.get(..2)?.try_into().ok()?) as usize;
+    let string = String::from_utf8(binary.get(2..2 + len)?.to_vec()).ok()?;
+    Some((string, &binary[2 + len..]))
+}
+
+// whether the document can be joined as a subdocument of the parent workspace,
+// a new subdocument can only be joined once the parent with it is synced to the server
+async fn is_subdoc(context: &impl RpcContextImpl<'static>, parent: &str, guid: &str) -> bool {
+    match context.get_workspace(parent).await {
+        Ok(workspace) => {
+            workspace.with_trx(|t| t.subdocs().iter().any(|(_, subdoc)| subdoc == guid))
+        }
+        Err(e) => {
+            warn!("failed to get workspace {parent}: {e}");
+            false
+        }
+    }
+}
+
+// a workspace joined by a multiplexed connection
+struct JoinedSession {
+    // messages from the client
+    remote_tx: Sender<Vec<u8>>,
+    // forwards the messages of the session to the connection
+    forward: JoinHandle<()>,
+}
+
+fn join_session(
+    context: Arc<impl RpcContextImpl<'static> + Send + Sync + 'static>,
+    id: String,
+    identifier: String,
+    access: SessionAccess,
+    tx: Sender<Message>,
+) -> JoinedSession {
+    let (local_tx, mut local_rx) = channel::<Message>(100);
+    let (remote_tx, remote_rx) = channel::<Vec<u8>>(512);
+    let (first_init_tx, mut first_init_rx) = channel::<bool>(1);
+
+    let forward = {
+        let id = id.clone();
+        tokio::spawn(async move {
+            while let Some(message) = local_rx.recv().await {
+                let message = match message {
+                    Message::Binary(data) => Message::Binary(
+                        MultiplexedMessage::Sync {
+                            id: id.clone(),
+                            data,
+                        }
+                        .encode(),
+                    ),
+                    Message::Close => {
+                        let leave = MultiplexedMessage::Leave { id: id.clone() };
+                        let _ = tx.send(Message::Binary(leave.encode())).await;
+                        break;
+                    }
+                    // the limits apply to the whole connection
+                    message @ (Message::Disconnect(_) | Message::Ping) => message,
+                };
+                if tx.send(message).await.is_err() {
+                    // connection was closed
+                    break;
+                }
+            }
+        })
+    };
+
+    tokio::spawn({
+        let id = id.clone();
+        async move {
+            if let Some(true) = first_init_rx.recv().await {
+                debug!("multiplexed session init success: {}", id);
+            } else {
+                error!("multiplexed session init failed: {}", id);
+            }
+        }
+    });
+
+    tokio::spawn(handle_connector(
+        context,
+        id,
+        identifier,
+        access,
+        move || (local_tx, remote_rx, first_init_tx),
+    ));
+
+    JoinedSession { remote_tx, forward }
+}
+
+/// Sync many workspaces over a single connection with [MultiplexedMessage]s,
+/// each joined workspace has its own session as [handle_connector].
+///
+/// `authorize` resolves the access of a workspace by the token of [MultiplexedMessage::Join],
+/// `None` to reject the workspace. A subdocument has the access of its parent workspace,
+/// and is rejected unless it's listed in the subdocuments of the parent.
+///
+/// The [ConnectionLimits] apply to the whole connection, and at most
+/// [ConnectionLimits::max_workspaces] workspaces can be joined at the same time.
+pub async fn handle_multiplexed_connector<F>(
+    context: Arc<impl RpcContextImpl<'static> + Send + Sync + 'static>,
+    identifier: String,
+    authorize: impl Fn(String, Option<String>) -> F,
+    get_channel: impl FnOnce() -> (Sender<Message>, Receiver<Vec<u8>>, Sender<bool>),
+) where
+    F: Future<Output = Option<SessionAccess>>,
+{
+    info!("{} collaborate with multiplexed workspaces", identifier);
+
+    let (tx, mut rx, first_init) = get_channel();
+    if first_init.send(true).await.is_err() {
+        return;
+    }
+
+    let limits = context.get_limits();
+    let max_workspaces = limits.max_workspaces();
+    let mut limiter = ConnectionLimiter::new(limits);
+
+    let mut sessions = HashMap::<String, JoinedSession>::new();
+    while let Some(binary) = rx.recv().await {
+        if let Err(reason) = limiter.check(binary.len()) {
+            warn!(
+                "close multiplexed connection of {identifier}: {}",
+                reason.reason()
+            );
+            let _ = tx.send(Message::Disconnect(reason)).await;
+            break;
+        }
+
+        let Some(message) = MultiplexedMessage::decode(&binary) else {
+            warn!("invalid multiplexed message from {identifier}");
+            continue;
+        };
+
+        let reply = match message {
+            MultiplexedMessage::Join { id, token, parent } => {
+                if sessions.contains_key(&id) {
+                    continue;
+                }
+                if sessions.len() >= max_workspaces {
+                    warn!("too many workspaces joined by {identifier}, reject {id}");
+                    MultiplexedMessage::Reject {
+                        id,
+                        reason: "too many workspaces".into(),
+                    }
+                } else {
+                    let access = match &parent {
+                        Some(parent) => match authorize(parent.clone(), token).await {
+                            Some(access) if is_subdoc(&*context, parent, &id).await => Some(access),
+                            _ => None,
+                        },
+                        None => authorize(id.clone(), token).await,
+                    };
+                    if let Some(access) = access {
+                        let session = join_session(
+                            context.clone(),
+                            id.clone(),
+                            identifier.clone(),
+                            access,
+                            tx.clone(),
+                        );
+                        sessions.insert(id, session);
+                        continue;
+                    }
+                    warn!("unauthorized collaboration of workspace: {}", id);
+                    MultiplexedMessage::Reject {
+                        id,
+                        reason: "unauthorized".into(),
+                    }
+                }
+            }
+            MultiplexedMessage::Sync { id, data } => {
+                let Some(session) = sessions.get(&id) else {
+                    let reason = "workspace not joined".into();
+                    if tx
+                        .send(Message::Binary(
+                            MultiplexedMessage::Reject { id, reason }.encode(),
+                        ))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                    continue;
+                };
+                // a slow session must not block the other workspaces of the connection
+                match session.remote_tx.try_send(data) {
+                    Ok(()) => continue,
+                    Err(TrySendError::Full(_)) => {
+                        warn!("multiplexed session of {id} is too slow, leave it: {identifier}");
+                    }
+                    // the session was closed by the server
+                    Err(TrySendError::Closed(_)) => {}
+                }
+                if let Some(session) = sessions.remove(&id) {
+                    session.forward.abort();
+                }
+                MultiplexedMessage::Leave { id }
+            }
+            MultiplexedMessage::Leave { id } => {
+                if let Some(session) = sessions.remove(&id) {
+                    session.forward.abort();
+                }
+                continue;
+            }
+            MultiplexedMessage::Reject { .. } => continue,
+        };
+
+        if tx.send(Message::Binary(reply.encode())).await.is_err() {
+            // connection was closed
+            break;
+        }
+    }
+
+    for session in sessions.into_values() {
+        session.forward.abort();
+    }
+    info!("{} multiplexed collaboration ended", identifier);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jwst::DocStorage;
+
+    #[test]
+    fn multiplexed_message_test() {
+        let messages = [
+            MultiplexedMessage::Join {
+                id: "ws1".into(),
+                token: Some("token".into()),
+                parent: None,
+            },
+            MultiplexedMessage::Join {
+                id: "ws1".into(),
+                token: None,
+                parent: None,
+            },
+            MultiplexedMessage::Join {
+                id: "subdoc".into(),
+                token: Some("token".into()),
+                parent: Some("ws1".into()),
+            },
+            MultiplexedMessage::Sync {
+                id: "ws2".into(),
+                data: vec![0, 1, 2],
+            },
+            MultiplexedMessage::Leave { id: "".into() },
+            MultiplexedMessage::Reject {
+                id: "ws3".into(),
+                reason: "unauthorized".into(),
+            },
+        ];
+        for message in messages {
+            assert_eq!(MultiplexedMessage::decode(&message.encode()), Some(message));
+        }
+
+        assert_eq!(MultiplexedMessage::decode(&[]), None);
+        assert_eq!(MultiplexedMessage::decode(&[SYNC, 0, 4, b'w']), None);
+        assert_eq!(MultiplexedMessage::decode(&[9, 0, 0]), None);
+    }
+
+    // joins the workspaces over a multiplexed connection, returns the rejected workspaces
+    // with the reasons until the workspace `end` is rejected
+    async fn join_workspaces<F>(
+        context: Arc<MinimumServerContext>,
+        authorize: impl Fn(String, Option<String>) -> F + Send + 'static,
+        joins: Vec<MultiplexedMessage>,
+    ) -> Vec<(String, String)>
+    where
+        F: Future<Output = Option<SessionAccess>> + Send + 'static,
+    {
+        let (tx, mut local_rx) = channel(100);
+        let (remote_tx, rx) = channel(100);
+        let (first_init, _first_init_rx) = channel(1);
+        tokio::spawn(handle_multiplexed_connector(
+            context,
+            "test".into(),
+            authorize,
+            move || (tx, rx, first_init),
+        ));
+
+        let end = MultiplexedMessage::Join {
+            id: "end".into(),
+            token: Some("invalid".into()),
+            parent: None,
+        };
+        for join in joins.iter().chain([&end]) {
+            remote_tx.send(join.encode()).await.unwrap();
+        }
+
+        // the messages of the joined workspaces are skipped
+        let mut rejected = vec![];
+        while let Some(message) = local_rx.recv().await {
+            let Message::Binary(binary) = message else {
+                continue;
+            };
+            if let Some(MultiplexedMessage::Reject { id, reason }) =
+                MultiplexedMessage::decode(&binary)
+            {
+                if id == "end" {
+                    return rejected;
+                }
+                rejected.push((id, reason));
+            }
+        }
+        panic!("the connection is closed");
+    }
+
+    #[tokio::test]
+    async fn multiplexed_workspaces_limit_test() {
+        let context = MinimumServerContext::new_with_limits(ConnectionLimits {
+            max_workspaces: Some(2),
+            ..Default::default()
+        })
+        .await;
+
+        let joins = ["a", "b", "c"]
+            .into_iter()
+            .map(|id| MultiplexedMessage::Join {
+                id: id.into(),
+                token: None,
+                parent: None,
+            })
+            .collect();
+        let rejected = join_workspaces(
+            context,
+            |id, _| async move { (id != "end").then_some(SessionAccess::ReadWrite) },
+            joins,
+        )
+        .await;
+        assert_eq!(
+            rejected,
+            vec![("c".to_string(), "too many workspaces".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn multiplexed_subdoc_test() {
+        let context = MinimumServerContext::new().await;
+        let workspace = context.get_workspace("ws").await.unwrap();
+        let (guid, another) = workspace.with_trx(|mut t| {
+            let guid = t.create_subdoc("page").guid().to_string();
+            (guid, t.create_subdoc("another").guid().to_string())
+        });
+        context.get_workspace("other").await.unwrap();
+
+        let join = |id: &str, token: &str| MultiplexedMessage::Join {
+            id: id.into(),
+            token: Some(token.into()),
+            parent: Some("ws".into()),
+        };
+        let joins = vec![
+            join(&guid, "token"),
+            join("unknown", "token"),
+            join("other", "token"),
+            join(&another, "invalid"),
+        ];
+        // only the parent workspace is authorized by the token
+        let rejected = join_workspaces(
+            context.clone(),
+            |id, token| async move {
+                (id == "ws" && token.as_deref() == Some("token"))
+                    .then_some(SessionAccess::ReadWrite)
+            },
+            joins,
+        )
+        .await;
+        let unauthorized = |id: &str| (id.to_string(), "unauthorized".to_string());
+        // the unknown guid isn't created as a subdocument of the parent
+        assert_eq!(
+            rejected,
+            vec![
+                unauthorized("unknown"),
+                unauthorized("other"),
+                unauthorized(&another)
+            ]
+        );
+        assert!(!context
+            .get_storage()
+            .docs()
+            .exists("unknown".into())
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,211 @@
+use super::*;
+use jwst::Workspace;
+use jwst_storage::DocAutoStorage;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use y_sync::sync::{Message as YMessage, MessageReader, SyncMessage};
+use yrs::{
+    updates::{
+        decoder::{Decode, DecoderV1},
+        encoder::Encode,
+    },
+    ReadTxn, StateVector, Transact, Update,
+};
+
+// the acknowledged state is saved at most once in the interval
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Local updates of a workspace which are not acknowledged by the remote yet.
+///
+/// The updates are persisted by the storage of the workspace, only the state vector
+/// acknowledged by the remote is tracked, so the unacknowledged updates are replayed
+/// after reconnecting, even if the app was killed while offline.
+///
+/// The acknowledged state is saved at most once per second and by [OutboundQueue::flush],
+/// the updates acknowledged in between may be replayed again, which is harmless.
+pub(crate) struct OutboundQueue {
+    docs: DocAutoStorage,
+    workspace: Workspace,
+    remote: String,
+    acknowledged: StateVector,
+    // whether the acknowledged state has changed since it was saved
+    unsaved: bool,
+    saved: Instant,
+    // the last counted pending changes, in case the workspace is being written
+    pending: AtomicUsize,
+}
+
+impl OutboundQueue {
+    pub async fn load(docs: DocAutoStorage, workspace: Workspace, remote: String) -> Self {
+        let acknowledged = match docs.remote_state(&workspace.id(), &remote).await {
+            Ok(Some(state)) => StateVector::decode_v1(&state).unwrap_or_else(|e| {
+                warn!("failed to decode remote state of {}: {e}", workspace.id());
+                StateVector::default()
+            }),
+            Ok(None) => StateVector::default(),
+            Err(e) => {
+                warn!("failed to load remote state of {}: {e}", workspace.id());
+                StateVector::default()
+            }
+        };
+
+        Self {
+            docs,
+            workspace,
+            remote,
+            acknowledged,
+            unsaved: false,
+            saved: Instant::now(),
+            pending: AtomicUsize::new(0),
+        }
+    }
+
+    /// Track the changes acknowledged by another remote.
+    pub async fn set_remote(&mut self, remote: String) {
+        if self.remote != remote {
+            self.flush().await;
+            *self = Self::load(self.docs.clone(), self.workspace.clone(), remote).await;
+        }
+    }
+
+    fn count_pending(&self, trx: &impl ReadTxn) -> usize {
+        let pending = trx
+            .state_vector()
+            .iter()
+            .map(|(client, clock)| clock.saturating_sub(self.acknowledged.get(client)) as usize)
+            .sum();
+        self.pending.store(pending, Ordering::Relaxed);
+        pending
+    }
+
+    /// Number of local changes which are not acknowledged, counted by the clocks of clients.
+    ///
+    /// The last counted number is returned if the workspace is being written.
+    pub fn pending(&self) -> usize {
+        match self.workspace.doc().try_transact() {
+            Ok(trx) => self.count_pending(&trx),
+            Err(_) => self.pending.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The update of the changes which are not acknowledged, `None` if there are none.
+    ///
+    /// Waits for the transaction if the workspace is being written.
+    pub async fn pending_update(&self) -> Option<Vec<u8>> {
+        let doc = self.workspace.doc();
+        let mut waited = false;
+        loop {
+            if let Ok(trx) = doc.try_transact() {
+                if self.count_pending(&trx) == 0 {
+                    return None;
+                }
+                return Some(trx.encode_state_as_update_v1(&self.acknowledged));
+            }
+            if !waited {
+                debug!("wait for the transaction of {}", self.workspace.id());
+                waited = true;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Acknowledge the changes known by the remote from its messages:
+    /// the state vector of its sync request and the updates it sends.
+    pub async fn acknowledge(&mut self, binary: &[u8]) {
+        let mut decoder = DecoderV1::from(binary);
+        let mut changed = false;
+        for message in MessageReader::new(&mut decoder).flatten() {
+            let state = match message {
+                YMessage::Sync(SyncMessage::SyncStep1(state)) => state,
+                YMessage::Sync(SyncMessage::SyncStep2(update) | SyncMessage::Update(update)) => {
+                    match Update::decode_v1(&update) {
+                        Ok(update) => update.state_vector(),
+                        Err(_) => continue,
+                    }
+                }
+                _ => continue,
+            };
+            for (client, clock) in state.iter() {
+                if *clock > self.acknowledged.get(client) {
+                    self.acknowledged.set_max(*client, *clock);
+                    changed = true;
+                }
+            }
+        }
+
+        self.unsaved |= changed;
+        if self.unsaved && self.saved.elapsed() >= SAVE_INTERVAL {
+            self.save().await;
+        }
+    }
+
+    /// Save the acknowledged state if it has changed since the last save.
+    pub async fn flush(&mut self) {
+        if self.unsaved {
+            self.save().await;
+        }
+    }
+
+    async fn save(&mut self) {
+        let state = self.acknowledged.encode_v1();
+        match self
+            .docs
+            .set_remote_state(&self.workspace.id(), &self.remote, state)
+            .await
+        {
+            Ok(()) => self.unsaved = false,
+            Err(e) => warn!(
+                "failed to save remote state of {}: {e}",
+                self.workspace.id()
+            ),
+        }
+        self.saved = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jwst::sync_encode_update;
+    use jwst_storage::JwstStorage;
+
+    #[tokio::test]
+    async fn outbound_queue_test() {
+        let storage = JwstStorage::new("sqlite::memory:").await.unwrap();
+        let workspace = storage.docs().get("test".into()).await.unwrap();
+        let remote = "ws://localhost/collaboration/test".to_string();
+
+        let mut queue =
+            OutboundQueue::load(storage.docs().clone(), workspace.clone(), remote.clone()).await;
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "block1", "flavor1");
+        });
+        assert!(queue.pending() > 0);
+        let update = queue.pending_update().await.unwrap();
+
+        // the remote echoes the update after applying it
+        queue.acknowledge(&sync_encode_update(&update)).await;
+        assert_eq!(queue.pending(), 0);
+        assert_eq!(queue.pending_update().await, None);
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "block2", "flavor1");
+        });
+        let pending = queue.pending();
+        assert!(pending > 0);
+
+        // the acknowledged state isn't saved until the interval passed or flushed
+        let loaded =
+            OutboundQueue::load(storage.docs().clone(), workspace.clone(), remote.clone()).await;
+        assert!(loaded.pending() > pending);
+        queue.flush().await;
+
+        // the acknowledged state survives restarts
+        let queue = OutboundQueue::load(storage.docs().clone(), workspace.clone(), remote).await;
+        assert_eq!(queue.pending(), pending);
+        let queue = OutboundQueue::load(storage.docs().clone(), workspace, "other".into()).await;
+        assert!(queue.pending() > pending);
+    }
+}
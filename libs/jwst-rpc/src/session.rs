@@ -0,0 +1,83 @@
+use y_sync::sync::{Message as YMessage, MessageReader, SyncMessage};
+use yrs::updates::{decoder::DecoderV1, encoder::Encode};
+
+// an update without any change in v1 encoding: no client structs and no deleted items
+const EMPTY_UPDATE: [u8; 2] = [0, 0];
+
+/// The access of a collaboration session to its workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionAccess {
+    /// The session receives the workspace and its updates, but can't change it.
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+impl SessionAccess {
+    pub fn can_write(&self) -> bool {
+        matches!(self, Self::ReadWrite)
+    }
+}
+
+/// Messages of a read-only session after removing the content updates,
+/// requests of the workspace state and awareness are kept.
+pub(crate) struct ReadOnlyMessages {
+    /// The kept messages, empty if nothing is kept.
+    pub messages: Vec<u8>,
+    /// Number of rejected updates which change the workspace.
+    pub rejected: usize,
+}
+
+impl ReadOnlyMessages {
+    pub fn filter(binary: &[u8]) -> Self {
+        let mut decoder = DecoderV1::from(binary);
+        let mut messages = vec![];
+        let mut rejected = 0;
+        for message in MessageReader::new(&mut decoder) {
+            match message {
+                Ok(YMessage::Sync(
+                    SyncMessage::SyncStep2(update) | SyncMessage::Update(update),
+                )) => {
+                    // viewers reply the state request of server with an empty update
+                    if update != EMPTY_UPDATE {
+                        rejected += 1;
+                    }
+                }
+                Ok(message) => messages.extend(message.encode_v1()),
+                Err(_) => break,
+            }
+        }
+        Self { messages, rejected }
+    }
+
+    /// The message telling the client its updates are rejected.
+    pub fn rejection() -> Vec<u8> {
+        YMessage::Auth(Some("read-only session".into())).encode_v1()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use yrs::StateVector;
+
+    #[test]
+    fn readonly_messages_test() {
+        let step1 = YMessage::Sync(SyncMessage::SyncStep1(StateVector::default())).encode_v1();
+        let awareness = YMessage::AwarenessQuery.encode_v1();
+        let empty = YMessage::Sync(SyncMessage::SyncStep2(EMPTY_UPDATE.to_vec())).encode_v1();
+        let update = YMessage::Sync(SyncMessage::Update(vec![1, 0, 0])).encode_v1();
+
+        let filtered = ReadOnlyMessages::filter(&[step1.clone(), update.clone()].concat());
+        assert_eq!(filtered.messages, step1);
+        assert_eq!(filtered.rejected, 1);
+
+        let filtered = ReadOnlyMessages::filter(&[empty, awareness.clone()].concat());
+        assert_eq!(filtered.messages, awareness);
+        assert_eq!(filtered.rejected, 0);
+
+        let filtered = ReadOnlyMessages::filter(&update);
+        assert!(filtered.messages.is_empty());
+        assert_eq!(filtered.rejected, 1);
+    }
+}
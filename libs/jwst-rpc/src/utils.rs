@@ -12,10 +12,32 @@ use yrs::{updates::decoder::Decode, Doc, ReadTxn, StateVector, Transact, Update}
 pub struct MinimumServerContext {
     channel: BroadcastChannels,
     storage: JwstStorage,
+    bridge: Option<BroadcastBridge>,
+    limits: ConnectionLimits,
 }
 
 impl MinimumServerContext {
     pub async fn new() -> Arc<Self> {
+        Self::new_with_options(None, ConnectionLimits::default()).await
+    }
+
+    /// A node sharing the broadcast with other nodes using the same backend.
+    pub async fn new_with_backend(backend: Arc<dyn BroadcastBackend>) -> Arc<Self> {
+        Self::new_with_options(
+            Some(BroadcastBridge::new(backend)),
+            ConnectionLimits::default(),
+        )
+        .await
+    }
+
+    pub async fn new_with_limits(limits: ConnectionLimits) -> Arc<Self> {
+        Self::new_with_options(None, limits).await
+    }
+
+    async fn new_with_options(
+        bridge: Option<BroadcastBridge>,
+        limits: ConnectionLimits,
+    ) -> Arc<Self> {
         let storage = JwstStorage::new(
             &std::env::var("DATABASE_URL")
                 .map(|url| format!("{url}_binary"))
@@ -27,13 +49,21 @@ impl MinimumServerContext {
         Arc::new(Self {
             channel: RwLock::new(HashMap::new()),
             storage,
+            bridge,
+            limits,
         })
     }
 
     pub async fn new_with_workspace(
         workspace_id: &str,
     ) -> (Arc<MinimumServerContext>, Workspace, Vec<u8>) {
-        let server = Self::new().await;
+        Self::init_workspace(Self::new().await, workspace_id).await
+    }
+
+    pub async fn init_workspace(
+        server: Arc<MinimumServerContext>,
+        workspace_id: &str,
+    ) -> (Arc<MinimumServerContext>, Workspace, Vec<u8>) {
         let ws = server.get_workspace(workspace_id).await.unwrap();
 
         let init_state = ws
@@ -53,6 +83,14 @@ impl RpcContextImpl<'_> for MinimumServerContext {
     fn get_channel(&self) -> &BroadcastChannels {
         &self.channel
     }
+
+    fn get_bridge(&self) -> Option<&BroadcastBridge> {
+        self.bridge.as_ref()
+    }
+
+    fn get_limits(&self) -> ConnectionLimits {
+        self.limits.clone()
+    }
 }
 
 pub async fn connect_memory_workspace(
@@ -64,6 +102,20 @@ pub async fn connect_memory_workspace(
     Sender<Message>,
     TokioJoinHandler<()>,
     StdJoinHandler<()>,
+) {
+    connect_memory_workspace_with_access(server, init_state, id, SessionAccess::ReadWrite).await
+}
+
+pub async fn connect_memory_workspace_with_access(
+    server: Arc<MinimumServerContext>,
+    init_state: &[u8],
+    id: &str,
+    access: SessionAccess,
+) -> (
+    Workspace,
+    Sender<Message>,
+    TokioJoinHandler<()>,
+    StdJoinHandler<()>,
 ) {
     let doc = Doc::new();
     doc.transact_mut()
@@ -83,6 +135,7 @@ pub async fn connect_memory_workspace(
                 server,
                 workspace_id,
                 nanoid!(),
+                access,
                 move || (tx, rx, first_init_tx),
             ));
         });
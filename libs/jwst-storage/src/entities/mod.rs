@@ -5,3 +5,4 @@ pub mod prelude;
 pub mod blobs;
 pub mod docs;
 pub mod optimized_blobs;
+pub mod remote_states;
@@ -11,7 +11,7 @@ use governor::{
     state::{InMemoryState, NotKeyed},
 };
 use governor::{Quota, RateLimiter};
-use jwst::{DocStorage, JwstError, JwstResult, Workspace};
+use jwst::{DocStorage, IntegrityReport, JwstError, JwstResult, Workspace};
 use jwst_logger::{debug, error, info, trace, warn};
 use path_ext::PathExt;
 use sea_orm::{prelude::*, ConnectOptions, Database, DbErr, QuerySelect, Set};
@@ -19,7 +19,7 @@ use std::{num::NonZeroU32, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use url::Url;
 
-pub use storage::JwstStorage;
+pub use storage::{DocAutoStorage, EvictionPolicy, JwstStorage, LoadedWorkspace};
 
 pub struct Bucket {
     bucket: Arc<RateLimiter<NotKeyed, InMemoryState, QuantaClock, NoOpMiddleware<QuantaInstant>>>,
@@ -3,6 +3,7 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_initial_blob_table;
 mod m20220101_000002_initial_doc_table;
 mod m20230321_000001_blob_optimized_table;
+mod m20230412_000001_remote_state_table;
 mod schema;
 
 pub struct Migrator;
@@ -14,6 +15,7 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_initial_blob_table::Migration),
             Box::new(m20220101_000002_initial_doc_table::Migration),
             Box::new(m20230321_000001_blob_optimized_table::Migration),
+            Box::new(m20230412_000001_remote_state_table::Migration),
         ]
     }
 }
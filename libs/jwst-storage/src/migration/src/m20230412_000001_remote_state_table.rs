@@ -0,0 +1,47 @@
+use super::schema::RemoteStates;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230412_000001_remote_state_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Define how to apply this migration: Create the RemoteStates table.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RemoteStates::Table)
+                    .col(ColumnDef::new(RemoteStates::Workspace).string().not_null())
+                    .col(ColumnDef::new(RemoteStates::Remote).string().not_null())
+                    .col(ColumnDef::new(RemoteStates::State).binary().not_null())
+                    .col(
+                        ColumnDef::new(RemoteStates::Timestamp)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(RemoteStates::Workspace)
+                            .col(RemoteStates::Remote),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // Define how to rollback this migration: Drop the RemoteStates table.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RemoteStates::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
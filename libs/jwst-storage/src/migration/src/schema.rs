@@ -29,3 +29,12 @@ pub enum OptimizedBlobs {
     Timestamp,
     Params,
 }
+
+#[derive(Iden)]
+pub enum RemoteStates {
+    Table,
+    Workspace,
+    Remote,
+    State,
+    Timestamp,
+}
@@ -1,11 +1,13 @@
 use super::{entities::prelude::*, *};
-use jwst::{sync_encode_update, DocStorage, Workspace};
+use jwst::{constants, remove_search_index, sync_encode_update, DocStorage, Workspace};
 use jwst_storage_migration::{Migrator, MigratorTrait};
+use sea_orm::sea_query::OnConflict;
 use std::{
     collections::hash_map::Entry,
     panic::{catch_unwind, AssertUnwindSafe},
+    time::Instant,
 };
-use yrs::{updates::decoder::Decode, Doc, ReadTxn, StateVector, Transact, Update};
+use yrs::{updates::decoder::Decode, Doc, Map, ReadTxn, StateVector, Transact, Update, Value};
 
 const MAX_TRIM_UPDATE_LIMIT: u64 = 500;
 
@@ -35,19 +37,49 @@ fn migrate_update(updates: Vec<<Docs as EntityTrait>::Model>, doc: Doc) -> Doc {
     doc
 }
 
+// the guids of the subdocuments, read from the doc directly since a workspace
+// would set up its plugins, e.g. open the search index
+fn subdoc_guids(doc: &Doc) -> Vec<String> {
+    let trx = doc.transact();
+    let Some(subdocs) = trx.get_map(constants::space::SUBDOCS) else {
+        return vec![];
+    };
+    subdocs
+        .iter(&trx)
+        .filter_map(|(_, value)| match value {
+            Value::YDoc(doc) => Some(doc.guid().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
 type DocsModel = <Docs as EntityTrait>::Model;
 type DocsActiveModel = super::entities::docs::ActiveModel;
 type DocsColumn = <Docs as EntityTrait>::Column;
+type RemoteStatesActiveModel = super::entities::remote_states::ActiveModel;
+type RemoteStatesColumn = <RemoteStates as EntityTrait>::Column;
+
+struct CachedWorkspace {
+    workspace: Workspace,
+    // the last time the workspace was read or written
+    accessed: Instant,
+}
 
 pub struct DocDBStorage {
     bucket: Arc<Bucket>,
     pub(super) pool: DatabaseConnection,
-    workspaces: RwLock<HashMap<String, Workspace>>,
+    workspaces: RwLock<HashMap<String, CachedWorkspace>>,
     remote: RwLock<HashMap<String, Sender<Vec<u8>>>>,
+    // the root directory of the persisted search indexes, stored in memory if not set
+    search_index_root: Option<PathBuf>,
 }
 
 impl DocDBStorage {
-    pub async fn init_with_pool(pool: DatabaseConnection, bucket: Arc<Bucket>) -> JwstResult<Self> {
+    pub async fn init_with_pool(
+        pool: DatabaseConnection,
+        bucket: Arc<Bucket>,
+        search_index_root: Option<PathBuf>,
+    ) -> JwstResult<Self> {
         Migrator::up(&pool, None)
             .await
             .context("failed to run migration")?;
@@ -57,6 +89,7 @@ impl DocDBStorage {
             pool,
             workspaces: RwLock::new(HashMap::new()),
             remote: RwLock::new(HashMap::new()),
+            search_index_root,
         })
     }
 
@@ -64,13 +97,185 @@ impl DocDBStorage {
         let is_sqlite = is_sqlite(database);
         let pool = create_connection(database, is_sqlite).await?;
 
-        Self::init_with_pool(pool, get_bucket(is_sqlite)).await
+        Self::init_with_pool(pool, get_bucket(is_sqlite), None).await
     }
 
     pub fn remote(&self) -> &RwLock<HashMap<String, Sender<Vec<u8>>>> {
         &self.remote
     }
 
+    /// Workspaces loaded in memory and the last time they were read or written.
+    pub async fn loaded(&self) -> Vec<(Workspace, Instant)> {
+        self.workspaces
+            .read()
+            .await
+            .values()
+            .map(|cached| (cached.workspace.clone(), cached.accessed))
+            .collect()
+    }
+
+    /// Flush the workspace to the database and remove it from memory.
+    ///
+    /// If `accessed` is given, the workspace is kept if it was accessed after that time.
+    /// Returns false if the workspace is not loaded or kept.
+    pub async fn unload(&self, workspace_id: &str, accessed: Option<Instant>) -> JwstResult<bool> {
+        let (workspace, last_accessed) = match self.workspaces.read().await.get(workspace_id) {
+            Some(cached) => (cached.workspace.clone(), cached.accessed),
+            None => return Ok(false),
+        };
+        if accessed.map_or(false, |accessed| last_accessed > accessed) {
+            return Ok(false);
+        }
+
+        let update = workspace.sync_migration(50).ok_or_else(|| {
+            JwstError::StorageError(anyhow::anyhow!(
+                "failed to flush workspace {workspace_id}: wait transact timeout"
+            ))
+        })?;
+        {
+            let _lock = self.bucket.get_lock().await;
+            // the snapshot is appended instead of replacing the stored updates,
+            // the updates written after it was taken are not lost
+            Self::insert(&self.pool, workspace_id, &update)
+                .await
+                .context("failed to flush workspace")
+                .map_err(JwstError::StorageError)?;
+        }
+
+        // the workspace is kept if it was accessed while flushing
+        let mut workspaces = self.workspaces.write().await;
+        match workspaces.entry(workspace_id.into()) {
+            Entry::Occupied(cached) if cached.get().accessed == last_accessed => {
+                cached.remove();
+            }
+            _ => return Ok(false),
+        }
+        drop(workspaces);
+
+        if let Entry::Occupied(remote) = self.remote.write().await.entry(workspace_id.into()) {
+            if remote.get().receiver_count() == 0 {
+                remote.remove();
+            }
+        }
+        info!("unload workspace: {workspace_id}");
+
+        Ok(true)
+    }
+
+    /// The state vector of the workspace acknowledged by the remote, in v1 encoding.
+    pub async fn remote_state(
+        &self,
+        workspace_id: &str,
+        remote: &str,
+    ) -> JwstResult<Option<Vec<u8>>> {
+        let _lock = self.bucket.get_lock().await;
+
+        let state = RemoteStates::find()
+            .filter(RemoteStatesColumn::Workspace.eq(workspace_id))
+            .filter(RemoteStatesColumn::Remote.eq(remote))
+            .one(&self.pool)
+            .await
+            .context("failed to get remote state")?;
+        Ok(state.map(|state| state.state))
+    }
+
+    pub async fn set_remote_state(
+        &self,
+        workspace_id: &str,
+        remote: &str,
+        state: Vec<u8>,
+    ) -> JwstResult<()> {
+        let _lock = self.bucket.get_lock().await;
+
+        trace!("set remote state: {workspace_id}, {remote}");
+        RemoteStates::insert(RemoteStatesActiveModel {
+            workspace: Set(workspace_id.into()),
+            remote: Set(remote.into()),
+            state: Set(state),
+            timestamp: Set(Utc::now().into()),
+        })
+        .on_conflict(
+            OnConflict::columns([RemoteStatesColumn::Workspace, RemoteStatesColumn::Remote])
+                .update_columns([RemoteStatesColumn::State, RemoteStatesColumn::Timestamp])
+                .to_owned(),
+        )
+        .exec(&self.pool)
+        .await
+        .context("failed to save remote state")?;
+        Ok(())
+    }
+
+    /// Load the subdocument of the space in the workspace, its updates are stored by its guid,
+    /// returns `None` if the space has no subdocument.
+    ///
+    /// The subdocument is cached as a workspace with the guid as id, the changes of it
+    /// should be written by [DocStorage::write_update] with the guid.
+    pub async fn get_subdoc(
+        &self,
+        workspace: &Workspace,
+        space_id: &str,
+    ) -> JwstResult<Option<Workspace>> {
+        let Some(subdoc) = workspace.with_trx(|t| t.get_subdoc(space_id)) else {
+            return Ok(None);
+        };
+        let guid = subdoc.guid().to_string();
+        trace!("get subdoc of {space_id}: {guid}");
+
+        self.get_or_create(guid, || subdoc).await.map(Some)
+    }
+
+    // the cache is not locked while waiting for the bucket, which is locked before the cache
+    // by the writes, or they would wait for each other
+    async fn cached(&self, workspace_id: &str) -> Option<Workspace> {
+        self.workspaces
+            .write()
+            .await
+            .get_mut(workspace_id)
+            .map(|cached| {
+                trace!("get workspace cache: {workspace_id}");
+                cached.accessed = Instant::now();
+                cached.workspace.clone()
+            })
+    }
+
+    async fn get_or_create(
+        &self,
+        workspace_id: String,
+        doc: impl FnOnce() -> Doc,
+    ) -> JwstResult<Workspace> {
+        trace!("get workspace: enter");
+        if let Some(workspace) = self.cached(&workspace_id).await {
+            return Ok(workspace);
+        }
+
+        debug!("init workspace cache: get lock");
+        let _lock = self.bucket.get_lock().await;
+        // the workspace may be loaded while waiting for the lock
+        if let Some(workspace) = self.cached(&workspace_id).await {
+            return Ok(workspace);
+        }
+
+        info!("init workspace cache: {workspace_id}");
+        let doc = Self::create_doc(&self.pool, &workspace_id, doc())
+            .await
+            .context("failed to create workspace")
+            .map_err(JwstError::StorageError)?;
+
+        let workspace = Workspace::from_doc_with_search_index(
+            doc,
+            &workspace_id,
+            self.search_index_root.as_deref(),
+        );
+        self.workspaces.write().await.insert(
+            workspace_id,
+            CachedWorkspace {
+                workspace: workspace.clone(),
+                accessed: Instant::now(),
+            },
+        );
+        Ok(workspace)
+    }
+
     async fn all<C>(conn: &C, table: &str) -> JwstResult<Vec<DocsModel>>
     where
         C: ConnectionTrait,
@@ -207,12 +412,11 @@ impl DocDBStorage {
         Ok(())
     }
 
-    async fn create_doc<C>(conn: &C, workspace: &str) -> JwstResult<Doc>
+    async fn create_doc<C>(conn: &C, workspace: &str, mut doc: Doc) -> JwstResult<Doc>
     where
         C: ConnectionTrait,
     {
         trace!("start create doc: {workspace}");
-        let mut doc = Doc::new();
 
         let all_data = Self::all(conn, workspace).await?;
 
@@ -245,27 +449,7 @@ impl DocStorage for DocDBStorage {
     }
 
     async fn get(&self, workspace_id: String) -> JwstResult<Workspace> {
-        trace!("get workspace: enter");
-        match self.workspaces.write().await.entry(workspace_id.clone()) {
-            Entry::Occupied(ws) => {
-                trace!("get workspace cache: {workspace_id}");
-                Ok(ws.get().clone())
-            }
-            Entry::Vacant(v) => {
-                debug!("init workspace cache: get lock");
-                let _lock = self.bucket.get_lock().await;
-
-                info!("init workspace cache: {workspace_id}");
-                let id = workspace_id.clone();
-                let doc = Self::create_doc(&self.pool, &id)
-                    .await
-                    .context("failed to create workspace")
-                    .map_err(JwstError::StorageError)?;
-
-                let ws = Workspace::from_doc(doc, workspace_id);
-                Ok(v.insert(ws).clone())
-            }
-        }
+        self.get_or_create(workspace_id, Doc::new).await
     }
 
     async fn write_full_update(&self, workspace_id: String, data: Vec<u8>) -> JwstResult<()> {
@@ -285,6 +469,10 @@ impl DocStorage for DocDBStorage {
     }
 
     async fn write_update(&self, workspace_id: String, data: &[u8]) -> JwstResult<()> {
+        if let Some(cached) = self.workspaces.write().await.get_mut(&workspace_id) {
+            cached.accessed = Instant::now();
+        }
+
         debug!("write_update: get lock");
         let _lock = self.bucket.get_lock().await;
 
@@ -301,12 +489,46 @@ impl DocStorage for DocDBStorage {
         debug!("delete workspace: get lock");
         let _lock = self.bucket.get_lock().await;
 
-        debug!("delete workspace cache: {workspace_id}");
-        self.workspaces.write().await.remove(&workspace_id);
-        DocDBStorage::drop(&self.pool, &workspace_id)
+        // the subdocuments are stored by their guids, they are listed from the stored updates
+        // if the workspace is not loaded, a nonexistent workspace is not created
+        let loaded = self
+            .workspaces
+            .read()
             .await
-            .context("failed to delete workspace")
-            .map_err(JwstError::StorageError)?;
+            .get(&workspace_id)
+            .map(|cached| cached.workspace.clone());
+        let subdocs = match loaded {
+            Some(workspace) => workspace
+                .with_trx(|t| t.subdocs())
+                .into_iter()
+                .map(|(_, guid)| guid)
+                .collect(),
+            None => {
+                let updates = Self::all(&self.pool, &workspace_id).await?;
+                subdoc_guids(&migrate_update(updates, Doc::new()))
+            }
+        };
+
+        for id in subdocs.into_iter().chain([workspace_id.clone()]) {
+            debug!("delete workspace cache: {id}");
+            self.workspaces.write().await.remove(&id);
+            DocDBStorage::drop(&self.pool, &id)
+                .await
+                .context("failed to delete workspace")
+                .map_err(JwstError::StorageError)?;
+            RemoteStates::delete_many()
+                .filter(RemoteStatesColumn::Workspace.eq(id.as_str()))
+                .exec(&self.pool)
+                .await
+                .context("failed to delete remote states")
+                .map_err(JwstError::StorageError)?;
+        }
+
+        if let Some(root) = &self.search_index_root {
+            if let Err(e) = remove_search_index(root, &workspace_id) {
+                warn!("failed to remove search index of workspace {workspace_id}: {e}");
+            }
+        }
 
         Ok(())
     }
@@ -357,6 +579,16 @@ pub async fn docs_storage_test(pool: &DocDBStorage) -> anyhow::Result<()> {
     );
     assert_eq!(DocDBStorage::count(conn, "basic").await?, 1);
 
+    // acknowledged states are kept for each remote
+    assert_eq!(pool.remote_state("basic", "remote1").await?, None);
+    pool.set_remote_state("basic", "remote1", vec![1]).await?;
+    pool.set_remote_state("basic", "remote1", vec![2]).await?;
+    pool.set_remote_state("basic", "remote2", vec![3]).await?;
+    assert_eq!(pool.remote_state("basic", "remote1").await?, Some(vec![2]));
+    assert_eq!(pool.remote_state("basic", "remote2").await?, Some(vec![3]));
+    pool.delete("basic".into()).await?;
+    assert_eq!(pool.remote_state("basic", "remote1").await?, None);
+
     Ok(())
 }
 
@@ -14,9 +14,13 @@ pub(super) use database::{docs_storage_partial_test, docs_storage_test};
 pub struct DocAutoStorage(pub(super) Arc<DocDBStorage>);
 
 impl DocAutoStorage {
-    pub async fn init_with_pool(pool: DatabaseConnection, bucket: Arc<Bucket>) -> JwstResult<Self> {
+    pub async fn init_with_pool(
+        pool: DatabaseConnection,
+        bucket: Arc<Bucket>,
+        search_index_root: Option<PathBuf>,
+    ) -> JwstResult<Self> {
         Ok(Self(Arc::new(
-            DocDBStorage::init_with_pool(pool, bucket).await?,
+            DocDBStorage::init_with_pool(pool, bucket, search_index_root).await?,
         )))
     }
 
@@ -27,6 +31,30 @@ impl DocAutoStorage {
     pub fn remote(&self) -> &RwLock<HashMap<String, Sender<Vec<u8>>>> {
         self.0.remote()
     }
+
+    pub async fn loaded(&self) -> Vec<(Workspace, Instant)> {
+        self.0.loaded().await
+    }
+
+    pub async fn unload(&self, id: &str, accessed: Option<Instant>) -> JwstResult<bool> {
+        self.0.unload(id, accessed).await
+    }
+
+    pub async fn remote_state(&self, id: &str, remote: &str) -> JwstResult<Option<Vec<u8>>> {
+        self.0.remote_state(id, remote).await
+    }
+
+    pub async fn set_remote_state(&self, id: &str, remote: &str, state: Vec<u8>) -> JwstResult<()> {
+        self.0.set_remote_state(id, remote, state).await
+    }
+
+    pub async fn get_subdoc(
+        &self,
+        workspace: &Workspace,
+        space_id: &str,
+    ) -> JwstResult<Option<Workspace>> {
+        self.0.get_subdoc(workspace, space_id).await
+    }
 }
 
 #[async_trait]
@@ -4,10 +4,35 @@ mod test;
 
 use super::*;
 use blobs::BlobAutoStorage;
-use docs::DocAutoStorage;
+pub use docs::DocAutoStorage;
 use std::{collections::HashMap, time::Instant};
 use tokio::sync::Mutex;
 
+/// A workspace loaded in memory, returned from [JwstStorage::loaded_workspaces].
+#[derive(Debug, Clone)]
+pub struct LoadedWorkspace {
+    pub id: String,
+    /// Time since the workspace was last read or written.
+    pub idle: Duration,
+    /// Size of the encoded workspace in bytes, an estimate of its memory usage.
+    pub size: usize,
+}
+
+/// Which workspaces are unloaded by [JwstStorage::evict_workspaces].
+#[derive(Debug, Clone, Default)]
+pub struct EvictionPolicy {
+    /// Unload the workspaces idle longer than this.
+    pub idle_timeout: Option<Duration>,
+    /// Unload the least recently used workspaces until the total size is within the budget.
+    pub memory_budget: Option<usize>,
+}
+
+impl EvictionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.idle_timeout.is_none() && self.memory_budget.is_none()
+    }
+}
+
 pub struct JwstStorage {
     pool: DatabaseConnection,
     blobs: BlobAutoStorage,
@@ -17,6 +42,15 @@ pub struct JwstStorage {
 
 impl JwstStorage {
     pub async fn new(database: &str) -> JwstResult<Self> {
+        Self::new_with_search_index(database, None).await
+    }
+
+    /// Persist the search index of every workspace in a sub directory of `search_index_root`,
+    /// so the index can be reused after restart. The index is stored in memory if not set.
+    pub async fn new_with_search_index(
+        database: &str,
+        search_index_root: Option<PathBuf>,
+    ) -> JwstResult<Self> {
         let is_sqlite = is_sqlite(database);
         let pool = create_connection(database, is_sqlite).await?;
         let bucket = get_bucket(is_sqlite);
@@ -30,7 +64,7 @@ impl JwstStorage {
         let blobs = BlobAutoStorage::init_with_pool(pool.clone(), bucket.clone())
             .await
             .context("Failed to init blobs")?;
-        let docs = DocAutoStorage::init_with_pool(pool.clone(), bucket.clone())
+        let docs = DocAutoStorage::init_with_pool(pool.clone(), bucket.clone(), search_index_root)
             .await
             .context("Failed to init docs")?;
 
@@ -42,7 +76,10 @@ impl JwstStorage {
         })
     }
 
-    pub async fn new_with_sqlite(file: &str) -> JwstResult<Self> {
+    pub async fn new_with_sqlite(
+        file: &str,
+        search_index_root: Option<PathBuf>,
+    ) -> JwstResult<Self> {
         use std::fs::create_dir;
 
         let data = PathBuf::from("./data");
@@ -50,12 +87,15 @@ impl JwstStorage {
             create_dir(&data).context("Failed to create data directory")?;
         }
 
-        Self::new(&format!(
-            "sqlite:{}?mode=rwc",
-            data.join(PathBuf::from(file).name_str())
-                .with_extension("db")
-                .display()
-        ))
+        Self::new_with_search_index(
+            &format!(
+                "sqlite:{}?mode=rwc",
+                data.join(PathBuf::from(file).name_str())
+                    .with_extension("db")
+                    .display()
+            ),
+            search_index_root,
+        )
         .await
     }
 
@@ -118,6 +158,142 @@ impl JwstStorage {
         }
     }
 
+    /// Check the block tree of all spaces in the workspace,
+    /// the issues will be repaired and persisted if `repair` is true.
+    pub async fn check_integrity<S>(
+        &self,
+        workspace_id: S,
+        repair: bool,
+    ) -> JwstResult<Vec<IntegrityReport>>
+    where
+        S: AsRef<str>,
+    {
+        let workspace = self.get_workspace(workspace_id.as_ref()).await?;
+        let (reports, update) = workspace.with_trx(|mut t| {
+            let reports = t.check_integrity(repair);
+            let update = reports
+                .iter()
+                .any(|report| report.repaired)
+                .then(|| t.trx.encode_update_v1());
+            (reports, update)
+        });
+
+        if let Some(update) = update {
+            self.docs
+                .write_update(workspace_id.as_ref().into(), &update)
+                .await?;
+        }
+
+        Ok(reports)
+    }
+
+    // loaded workspaces with the time of last access, least recently used first,
+    // the sizes are only computed if required since every workspace is encoded for it
+    async fn loaded(&self, with_size: bool) -> Vec<(LoadedWorkspace, Instant)> {
+        let mut loaded = self
+            .docs
+            .loaded()
+            .await
+            .into_iter()
+            .map(|(workspace, accessed)| {
+                let size = if with_size {
+                    workspace
+                        .sync_migration(50)
+                        .map(|update| update.len())
+                        .unwrap_or_default()
+                } else {
+                    0
+                };
+                let workspace = LoadedWorkspace {
+                    id: workspace.id(),
+                    idle: accessed.elapsed(),
+                    size,
+                };
+                (workspace, accessed)
+            })
+            .collect::<Vec<_>>();
+        loaded.sort_by_key(|(_, accessed)| *accessed);
+        loaded
+    }
+
+    /// Workspaces loaded in memory, least recently used first.
+    pub async fn loaded_workspaces(&self) -> Vec<LoadedWorkspace> {
+        self.loaded(true)
+            .await
+            .into_iter()
+            .map(|(workspace, _)| workspace)
+            .collect()
+    }
+
+    /// Flush the workspace to the database and unload it from memory,
+    /// returns false if the workspace is not loaded.
+    ///
+    /// The caller should make sure the workspace is not in use, e.g. by collaboration,
+    /// otherwise the workspace will be loaded again as a different instance.
+    pub async fn unload_workspace<S>(&self, workspace_id: S) -> JwstResult<bool>
+    where
+        S: AsRef<str>,
+    {
+        let unloaded = self.docs.unload(workspace_id.as_ref(), None).await?;
+        if unloaded {
+            self.last_migrate.lock().await.remove(workspace_id.as_ref());
+        }
+        Ok(unloaded)
+    }
+
+    /// Unload the workspaces by the policy, the workspaces which are `in_use` are kept.
+    /// Returns the ids of unloaded workspaces.
+    pub async fn evict_workspaces<F>(&self, policy: &EvictionPolicy, in_use: F) -> Vec<String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        if policy.is_empty() {
+            return vec![];
+        }
+
+        let loaded = self.loaded(policy.memory_budget.is_some()).await;
+        let mut total = loaded
+            .iter()
+            .map(|(workspace, _)| workspace.size)
+            .sum::<usize>();
+
+        let mut evicted = vec![];
+        for (workspace, accessed) in loaded {
+            let idle = policy
+                .idle_timeout
+                .map_or(false, |timeout| workspace.idle > timeout);
+            let over_budget = policy.memory_budget.map_or(false, |budget| total > budget);
+            if !(idle || over_budget) || in_use(&workspace.id) {
+                continue;
+            }
+
+            // skip the workspaces accessed since they were listed
+            match self.docs.unload(&workspace.id, Some(accessed)).await {
+                Ok(true) => {
+                    total -= workspace.size;
+                    self.last_migrate.lock().await.remove(&workspace.id);
+                    evicted.push(workspace.id);
+                }
+                Ok(false) => {}
+                Err(e) => error!("failed to evict workspace {}: {}", workspace.id, e),
+            }
+        }
+
+        if !evicted.is_empty() {
+            if policy.memory_budget.is_some() {
+                info!(
+                    "evicted {} workspaces, {} bytes loaded",
+                    evicted.len(),
+                    total
+                );
+            } else {
+                info!("evicted {} workspaces", evicted.len());
+            }
+        }
+
+        evicted
+    }
+
     pub async fn full_migrate(
         &self,
         workspace_id: String,
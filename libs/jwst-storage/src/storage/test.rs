@@ -16,6 +16,186 @@ async fn sqlite_storage_test() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn sqlite_eviction_test() -> anyhow::Result<()> {
+    let storage = JwstStorage::new("sqlite::memory:").await?;
+
+    for id in ["a", "b", "c"] {
+        let workspace = storage.create_workspace(id).await?;
+        // changes are not written to the database until the workspace is unloaded
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "block", "affine:text");
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let loaded = storage.loaded_workspaces().await;
+    assert_eq!(
+        loaded.iter().map(|ws| ws.id.as_str()).collect::<Vec<_>>(),
+        vec!["a", "b", "c"]
+    );
+    assert!(loaded.iter().all(|ws| ws.size > 0));
+
+    // the least recently used workspace is evicted first, in use workspaces are kept
+    let policy = EvictionPolicy {
+        memory_budget: Some(loaded[0].size + loaded[2].size),
+        ..Default::default()
+    };
+    assert_eq!(
+        storage.evict_workspaces(&policy, |id| id == "a").await,
+        vec!["b"]
+    );
+
+    let policy = EvictionPolicy {
+        idle_timeout: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    assert_eq!(
+        storage.evict_workspaces(&policy, |_| false).await,
+        vec!["a", "c"]
+    );
+    assert!(storage.loaded_workspaces().await.is_empty());
+    assert!(!storage.unload_workspace("a").await?);
+
+    // unloaded workspaces are flushed
+    for id in ["a", "b", "c"] {
+        let workspace = storage.get_workspace(id).await?;
+        workspace
+            .with_trx(|t| assert!(t.get_exists_space("space").unwrap().exists(&t.trx, "block")));
+    }
+    assert!(storage.unload_workspace("a").await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sqlite_unload_race_test() -> anyhow::Result<()> {
+    let storage = JwstStorage::new("sqlite::memory:").await?;
+    let docs = &storage.docs().0;
+
+    let workspace = storage.create_workspace("a").await?;
+    workspace.with_trx(|mut t| {
+        t.get_space("space").create(&mut t.trx, "block1", "text");
+    });
+    // the update of another client is written while the workspace is unloaded
+    let update = Workspace::new("a").with_trx(|mut t| {
+        t.get_space("space").create(&mut t.trx, "block2", "text");
+        t.trx.encode_update_v1()
+    });
+    let (unloaded, written) = tokio::join!(
+        docs.unload("a", None),
+        docs.write_update("a".into(), &update)
+    );
+    written?;
+    if !unloaded? {
+        assert!(docs.unload("a", None).await?);
+    }
+
+    let workspace = storage.get_workspace("a").await?;
+    workspace.with_trx(|t| {
+        let space = t.get_exists_space("space").unwrap();
+        assert!(space.exists(&t.trx, "block1"));
+        assert!(space.exists(&t.trx, "block2"));
+    });
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sqlite_subdoc_test() -> anyhow::Result<()> {
+    let storage = JwstStorage::new("sqlite::memory:").await?;
+
+    let workspace = storage.create_workspace("ws").await?;
+    let (guid, update) = workspace.with_trx(|mut t| {
+        let guid = t.create_subdoc("page").guid().to_string();
+        (guid, t.trx.encode_update_v1())
+    });
+    storage.docs().write_update("ws".into(), &update).await?;
+    assert!(storage
+        .docs()
+        .get_subdoc(&workspace, "space")
+        .await?
+        .is_none());
+
+    let page = storage
+        .docs()
+        .get_subdoc(&workspace, "page")
+        .await?
+        .unwrap();
+    assert_eq!(page.id(), guid);
+    let update = page.with_trx(|mut t| {
+        t.get_space("page").create(&mut t.trx, "block", "text");
+        t.trx.encode_update_v1()
+    });
+    storage.docs().write_update(guid.clone(), &update).await?;
+
+    // the subdocument is stored by its guid, apart from the workspace
+    assert!(storage.unload_workspace(&guid).await?);
+    assert!(storage.unload_workspace("ws").await?);
+    let workspace = storage.get_workspace("ws").await?;
+    workspace.with_trx(|t| assert!(t.get_exists_space("page").is_none()));
+    let page = storage
+        .docs()
+        .get_subdoc(&workspace, "page")
+        .await?
+        .unwrap();
+    page.with_trx(|t| assert!(t.get_exists_space("page").unwrap().exists(&t.trx, "block")));
+
+    // the subdocuments of an unloaded workspace are deleted with it,
+    // including the remote states of those never loaded
+    let (never, update) = workspace.with_trx(|mut t| {
+        let guid = t.create_subdoc("never").guid().to_string();
+        (guid, t.trx.encode_update_v1())
+    });
+    storage.docs().write_update("ws".into(), &update).await?;
+    for id in [&guid, &never] {
+        storage
+            .docs()
+            .set_remote_state(id, "remote", vec![0])
+            .await?;
+    }
+    assert!(storage.unload_workspace(&guid).await?);
+    assert!(storage.unload_workspace("ws").await?);
+    storage.docs().delete("ws".into()).await?;
+    assert!(!storage.docs().exists("ws".into()).await?);
+    assert!(!storage.docs().exists(guid.clone()).await?);
+    for id in [&guid, &never] {
+        assert_eq!(storage.docs().remote_state(id, "remote").await?, None);
+    }
+
+    // a nonexistent workspace is not created by deleting it
+    storage.docs().delete("none".into()).await?;
+    assert!(!storage.docs().exists("none".into()).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sqlite_search_index_test() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("jwst-index-{}", rand::random::<u64>()));
+    let (first, second) = (root.join("first"), root.join("second"));
+
+    // the index directory is configured per storage, not shared by the process
+    let storage =
+        JwstStorage::new_with_search_index("sqlite::memory:", Some(first.clone())).await?;
+    let other = JwstStorage::new_with_search_index("sqlite::memory:", Some(second.clone())).await?;
+    let workspace = storage.create_workspace("ws").await?;
+    other.create_workspace("ws").await?;
+    assert_eq!(workspace.search_index_root(), Some(first.as_path()));
+    assert!(jwst::search_index_dir(&first, "ws").exists());
+    assert!(jwst::search_index_dir(&second, "ws").exists());
+
+    assert!(storage.unload_workspace("ws").await?);
+    storage.docs().delete("ws".into()).await?;
+    assert!(!jwst::search_index_dir(&first, "ws").exists());
+    assert!(jwst::search_index_dir(&second, "ws").exists());
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
 #[ignore = "need postgres server"]
 #[cfg(feature = "postgres")]
 #[tokio::test]
@@ -0,0 +1,194 @@
+use super::*;
+use yrs::{
+    types::{
+        text::{Diff, YChange},
+        Attrs, Value,
+    },
+    Text, TextPrelim, TextRef,
+};
+
+/// An owned deep copy of a value in the document.
+/// It can be written into another transaction or document after the source has been read,
+/// nested shared types will be recreated, so the copy doesn't share any state with the source.
+pub(super) enum Snapshot {
+    Any(Any),
+    Text(Vec<(Any, Attrs)>),
+    Array(Vec<Snapshot>),
+    Map(Vec<(String, Snapshot)>),
+}
+
+impl Snapshot {
+    pub(super) fn new<T>(trx: &T, value: Value) -> Self
+    where
+        T: ReadTxn,
+    {
+        match value {
+            Value::Any(any) => Self::Any(any),
+            Value::YText(text) => Self::Text(
+                text.diff(trx, YChange::identity)
+                    .into_iter()
+                    .map(|diff: Diff<YChange>| {
+                        let insert = match diff.insert {
+                            Value::Any(any) => any,
+                            value => value.to_json(trx),
+                        };
+                        (insert, diff.attributes.map(|a| *a).unwrap_or_default())
+                    })
+                    .collect(),
+            ),
+            Value::YArray(array) => {
+                Self::Array(array.iter(trx).map(|v| Self::new(trx, v)).collect())
+            }
+            Value::YMap(map) => Self::Map(
+                map.iter(trx)
+                    .map(|(key, value)| (key.to_owned(), Self::new(trx, value)))
+                    .collect(),
+            ),
+            value => Self::Any(value.to_json(trx)),
+        }
+    }
+
+    /// Replace `{{name}}` placeholders in strings and rich text chunks with the variables.
+    pub(super) fn substitute(&mut self, variables: &HashMap<String, String>) {
+        match self {
+            Self::Any(any) => substitute_any(any, variables),
+            Self::Text(delta) => delta
+                .iter_mut()
+                .for_each(|(insert, _)| substitute_any(insert, variables)),
+            Self::Array(values) => values
+                .iter_mut()
+                .for_each(|value| value.substitute(variables)),
+            Self::Map(entries) => entries
+                .iter_mut()
+                .for_each(|(_, value)| value.substitute(variables)),
+        }
+    }
+
+    fn insert_into_map(self, trx: &mut TransactionMut, map: &MapRef, key: &str) {
+        match self {
+            Self::Any(any) => {
+                map.insert(trx, key, any);
+            }
+            Self::Text(delta) => {
+                let text = map.insert(trx, key, TextPrelim::new(""));
+                Self::write_text(trx, &text, delta);
+            }
+            Self::Array(values) => {
+                let array = map.insert(trx, key, ArrayPrelim::<_, Any>::from([]));
+                Self::write_array(trx, &array, values);
+            }
+            Self::Map(entries) => {
+                let target = map.insert(trx, key, MapPrelim::<Any>::new());
+                Self::write_map(trx, &target, entries);
+            }
+        }
+    }
+
+    fn push_into_array(self, trx: &mut TransactionMut, array: &ArrayRef) {
+        match self {
+            Self::Any(any) => {
+                array.push_back(trx, any);
+            }
+            Self::Text(delta) => {
+                let text = array.push_back(trx, TextPrelim::new(""));
+                Self::write_text(trx, &text, delta);
+            }
+            Self::Array(values) => {
+                let target = array.push_back(trx, ArrayPrelim::<_, Any>::from([]));
+                Self::write_array(trx, &target, values);
+            }
+            Self::Map(entries) => {
+                let target = array.push_back(trx, MapPrelim::<Any>::new());
+                Self::write_map(trx, &target, entries);
+            }
+        }
+    }
+
+    fn write_text(trx: &mut TransactionMut, text: &TextRef, delta: Vec<(Any, Attrs)>) {
+        for (insert, attributes) in delta {
+            let index = text.len(trx);
+            match insert {
+                Any::String(chunk) => text.insert_with_attributes(trx, index, &chunk, attributes),
+                embed => {
+                    text.insert_embed_with_attributes(trx, index, embed, attributes);
+                }
+            }
+        }
+    }
+
+    fn write_array(trx: &mut TransactionMut, array: &ArrayRef, values: Vec<Snapshot>) {
+        for value in values {
+            value.push_into_array(trx, array);
+        }
+    }
+
+    pub(super) fn write_map(
+        trx: &mut TransactionMut,
+        map: &MapRef,
+        entries: Vec<(String, Snapshot)>,
+    ) {
+        for (key, value) in entries {
+            value.insert_into_map(trx, map, &key);
+        }
+    }
+}
+
+fn substitute_any(any: &mut Any, variables: &HashMap<String, String>) {
+    match any {
+        Any::String(content) if content.contains("{{") => {
+            *content = substitute_str(content, variables).into();
+        }
+        Any::Array(values) => values
+            .iter_mut()
+            .for_each(|value| substitute_any(value, variables)),
+        Any::Map(entries) => entries
+            .values_mut()
+            .for_each(|value| substitute_any(value, variables)),
+        _ => {}
+    }
+}
+
+// replace the placeholders in a single pass, so a substituted value containing
+// `{{name}}` is never expanded again, placeholders of unknown variables are kept
+fn substitute_str(content: &str, variables: &HashMap<String, String>) -> String {
+    let mut substituted = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let variable = rest[2..]
+            .find("}}")
+            .and_then(|end| variables.get(&rest[2..end + 2]).map(|value| (end, value)));
+        match variable {
+            Some((end, value)) => {
+                substituted.push_str(value);
+                rest = &rest[end + 4..];
+            }
+            None => {
+                // keep the brace, the placeholder may start right after it
+                substituted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+// take an owned copy of the entries of the map except the skipped keys,
+// they can be written by [Snapshot::write_map]
+pub(super) fn snapshot_map<T>(trx: &T, map: &MapRef, skipped: &[&str]) -> Vec<(String, Snapshot)>
+where
+    T: ReadTxn,
+{
+    map.iter(trx)
+        .filter(|(key, _)| !skipped.contains(key))
+        .map(|(key, value)| (key.to_owned(), Snapshot::new(trx, value)))
+        .collect()
+}
+
+// deep copy all entries of the source map into the target map
+pub(super) fn copy_map(trx: &mut TransactionMut, source: &MapRef, target: &MapRef) {
+    let entries = snapshot_map(&*trx, source, &[]);
+    Snapshot::write_map(trx, target, entries);
+}
@@ -0,0 +1,229 @@
+use super::{
+    copy::{snapshot_map, Snapshot},
+    *,
+};
+use nanoid::nanoid;
+
+// these fields will be regenerated for the duplicated block
+const SKIPPED_FIELDS: [&str; 6] = [
+    sys::CHILDREN,
+    sys::CREATED,
+    sys::FLAVOR,
+    sys::FLAVOUR,
+    sys::PARENT,
+    sys::TEMPLATE,
+];
+
+pub(super) struct BlockSnapshot {
+    block_id: String,
+    flavor: String,
+    content: Vec<(String, Snapshot)>,
+    children: Vec<String>,
+}
+
+impl BlockSnapshot {
+    pub(super) fn substitute(&mut self, variables: &HashMap<String, String>) {
+        for (_, value) in self.content.iter_mut() {
+            value.substitute(variables);
+        }
+    }
+}
+
+impl Block {
+    // take an owned copy of the block and its descendants
+    pub(super) fn snapshot<T>(&self, trx: &T) -> Option<Vec<BlockSnapshot>>
+    where
+        T: ReadTxn,
+    {
+        let space = self.space(trx)?;
+
+        Some(
+            self.subtree(trx, &space)
+                .iter()
+                .filter_map(|block_id| space.get(trx, block_id))
+                .map(|block| BlockSnapshot {
+                    block_id: block.block_id(),
+                    flavor: block.flavor(trx),
+                    content: snapshot_map(trx, &block.block, &SKIPPED_FIELDS),
+                    children: block.children(trx),
+                })
+                .collect(),
+        )
+    }
+
+    // create the blocks of snapshot with new block ids, the first block is the root,
+    // return `None` if the parent doesn't belong to the space
+    pub(super) fn restore(
+        trx: &mut TransactionMut,
+        space: &Space,
+        snapshot: Vec<BlockSnapshot>,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+    ) -> Option<Block> {
+        if let Some(parent) = parent {
+            if parent.id != space.id() || parent.space_id != space.space_id() {
+                return None;
+            }
+        }
+
+        let ids = snapshot
+            .iter()
+            .map(|block| (block.block_id.clone(), nanoid!()))
+            .collect::<HashMap<_, _>>();
+
+        let blocks = snapshot
+            .into_iter()
+            .map(|block| {
+                let new_block = space.create(trx, &ids[&block.block_id], &block.flavor);
+                Snapshot::write_map(trx, &new_block.block, block.content);
+                (new_block, block.children)
+            })
+            .collect::<Vec<_>>();
+
+        // remap children and parent references, dangling children will be dropped
+        for (block, children) in &blocks {
+            for child_id in children.iter().filter_map(|child| ids.get(child)) {
+                if let Some(child) = space.get(trx, child_id) {
+                    child.set_parent(trx, block.block_id());
+                    block.children.push_back(trx, child_id.clone());
+                }
+            }
+        }
+
+        let (root, _) = blocks.into_iter().next()?;
+        if let Some(parent) = parent {
+            match pos {
+                Some(pos) => parent.insert_children_at(trx, &root, pos),
+                None => parent.push_children(trx, &root),
+            }
+        }
+
+        Some(root)
+    }
+
+    /// Duplicate the block and its descendants with new block ids into a space of the same workspace.
+    /// Children and parent references are remapped to the new ids, text and nested values are deep copied.
+    /// The duplicated block will be inserted into `parent` if given, at `pos` or appended.
+    ///
+    /// Return `None` if the space or parent doesn't belong to the workspace of the block.
+    pub fn duplicate(
+        &self,
+        trx: &mut TransactionMut,
+        space: &Space,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+    ) -> Option<Block> {
+        if space.id() != self.id {
+            return None;
+        }
+
+        let snapshot = self.snapshot(trx)?;
+        Self::restore(trx, space, snapshot, parent, pos)
+    }
+
+    /// Clone the block and its descendants with new block ids into a space of another workspace.
+    /// `trx` is the transaction of the block's workspace, `target_trx` is the transaction of the
+    /// target space's workspace, see [Block::duplicate] for details.
+    pub fn clone_into<T>(
+        &self,
+        trx: &T,
+        target_trx: &mut TransactionMut,
+        space: &Space,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+    ) -> Option<Block>
+    where
+        T: ReadTxn,
+    {
+        let snapshot = self.snapshot(trx)?;
+        Self::restore(target_trx, space, snapshot, parent, pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn duplicate() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let a = space.create(&mut t.trx, "a", "affine:page");
+            let b = space.create(&mut t.trx, "b", "affine:frame");
+            let c = space.create(&mut t.trx, "c", "affine:paragraph");
+
+            a.push_children(&mut t.trx, &b);
+            b.push_children(&mut t.trx, &c);
+            b.set(&mut t.trx, "xywh", "[0,0,720,480]");
+            c.text_insert(&mut t.trx, "text", 0, "hello");
+
+            let new = b.duplicate(&mut t.trx, &space, Some(&a), None).unwrap();
+
+            assert_ne!(new.block_id(), "b");
+            assert_eq!(a.children(&t.trx), vec!["b".to_owned(), new.block_id()]);
+            assert_eq!(new.parent(&t.trx), Some("a".to_owned()));
+            assert_eq!(new.flavor(&t.trx), "affine:frame");
+            assert_eq!(
+                new.get(&t.trx, "xywh").unwrap().to_string(),
+                "[0,0,720,480]"
+            );
+
+            let children = new.children(&t.trx);
+            assert_eq!(children.len(), 1);
+            assert_ne!(children[0], "c");
+
+            let new_c = space.get(&t.trx, &children[0]).unwrap();
+            assert_eq!(new_c.parent(&t.trx), Some(new.block_id()));
+            assert_eq!(new_c.flavor(&t.trx), "affine:paragraph");
+            assert_eq!(new_c.text_len(&t.trx, "text"), Some(5));
+
+            // the copy doesn't share state with the source
+            new_c.text_insert(&mut t.trx, "text", 5, " world");
+            assert_eq!(
+                new_c.get(&t.trx, "text").unwrap().to_string(),
+                "hello world"
+            );
+            assert_eq!(c.get(&t.trx, "text").unwrap().to_string(), "hello");
+        });
+    }
+
+    #[test]
+    fn clone_into() {
+        let source = Workspace::new("source");
+        let target = Workspace::new("target");
+
+        source.with_trx(|mut s| {
+            let space = s.get_space("space");
+
+            let a = space.create(&mut s.trx, "a", "affine:frame");
+            let b = space.create(&mut s.trx, "b", "affine:paragraph");
+            a.push_children(&mut s.trx, &b);
+            b.set(&mut s.trx, "text", "hello");
+
+            target.with_trx(|mut t| {
+                let space = t.get_space("space");
+
+                let new = a
+                    .clone_into(&s.trx, &mut t.trx, &space, None, None)
+                    .unwrap();
+
+                assert_eq!(space.blocks.len(&t.trx), 2);
+                assert_eq!(new.parent(&t.trx), None);
+
+                let children = new.children(&t.trx);
+                assert_eq!(children.len(), 1);
+                assert_eq!(
+                    space
+                        .get(&t.trx, &children[0])
+                        .and_then(|b| b.get(&t.trx, "text"))
+                        .unwrap()
+                        .to_string(),
+                    "hello"
+                );
+            });
+        });
+    }
+}
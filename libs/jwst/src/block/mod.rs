@@ -1,3 +1,9 @@
+mod copy;
+mod duplicate;
+mod relocate;
+mod template;
+mod text;
+
 use super::{constants::sys, utils::JS_INT_RANGE, *};
 use lib0::any::Any;
 use serde::{Serialize, Serializer};
@@ -7,6 +13,8 @@ use yrs::{
     TransactionMut,
 };
 
+pub use text::TextDelta;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Block {
     id: String,
@@ -136,6 +144,14 @@ impl Block {
         }
     }
 
+    // get the space which the block belongs to
+    fn space<T>(&self, trx: &T) -> Option<Space>
+    where
+        T: ReadTxn,
+    {
+        Space::from_exists(trx, self.doc.clone(), &self.id, &self.space_id)
+    }
+
     pub(crate) fn log_update(&self, trx: &mut TransactionMut, action: HistoryOperation) {
         let array = ArrayPrelim::from([
             Any::Number(self.operator as f64),
@@ -192,7 +208,13 @@ impl Block {
                 self.block.remove(trx, &key);
                 self.log_update(trx, HistoryOperation::Delete);
             }
-            Any::Buffer(_) | Any::Array(_) | Any::Map(_) => {}
+            Any::Array(array) => {
+                // stored as Y.Array, e.g. the tags of a block
+                self.block
+                    .insert(trx, key, ArrayPrelim::<_, Any>::from(array.into_vec()));
+                self.log_update(trx, HistoryOperation::Update);
+            }
+            Any::Buffer(_) | Any::Map(_) => {}
         }
     }
 
@@ -340,10 +362,24 @@ impl Block {
             .collect()
     }
 
-    fn set_parent(&self, trx: &mut TransactionMut, block_id: String) {
+    pub(crate) fn set_parent(&self, trx: &mut TransactionMut, block_id: String) {
         self.block.insert(trx, sys::PARENT, block_id);
     }
 
+    pub(crate) fn remove_parent(&self, trx: &mut TransactionMut) {
+        self.block.remove(trx, sys::PARENT);
+    }
+
+    // remove the children entries at the positions, positions must be in ascending order
+    pub(crate) fn remove_children_at(&self, trx: &mut TransactionMut, positions: &[u32]) {
+        for pos in positions.iter().rev() {
+            self.children.remove(trx, *pos);
+        }
+        if !positions.is_empty() {
+            self.log_update(trx, HistoryOperation::Delete);
+        }
+    }
+
     pub fn push_children(&self, trx: &mut TransactionMut, block: &Block) {
         self.remove_children(trx, block);
         block.set_parent(trx, self.block_id.clone());
@@ -0,0 +1,220 @@
+use super::*;
+use std::collections::HashSet;
+
+impl Block {
+    /// Collect the block id and the ids of all its descendants in depth first order.
+    /// Missing children and cyclic references will be skipped.
+    pub fn subtree<T>(&self, trx: &T, space: &Space) -> Vec<String>
+    where
+        T: ReadTxn,
+    {
+        let mut visited = HashSet::new();
+        let mut stack = vec![self.block_id.clone()];
+        let mut subtree = vec![];
+
+        while let Some(block_id) = stack.pop() {
+            if !visited.insert(block_id.clone()) {
+                continue;
+            }
+            if let Some(block) = space.get(trx, &block_id) {
+                stack.extend(block.children(trx).into_iter().rev());
+                subtree.push(block_id);
+            }
+        }
+
+        subtree
+    }
+
+    // find the block which contains this block in its children,
+    // fallback to scan the whole space if the parent pointer is missing or stale
+    fn find_parent<T>(&self, trx: &T, space: &Space) -> Option<Block>
+    where
+        T: ReadTxn,
+    {
+        self.parent(trx)
+            .and_then(|parent| space.get(trx, parent))
+            .filter(|parent| parent.children_exists(trx, &self.block_id))
+            .or_else(|| {
+                space.blocks(trx, |mut blocks| {
+                    blocks.find(|block| block.children_exists(trx, &self.block_id))
+                })
+            })
+    }
+
+    // remove the block from the children of its current parent
+    fn detach(&self, trx: &mut TransactionMut, space: &Space) {
+        if let Some(parent) = self.find_parent(trx, space) {
+            if let Some(pos) = parent.exists_children(trx, &self.block_id) {
+                parent.children.remove(trx, pos as u32);
+                parent.log_update(trx, HistoryOperation::Delete);
+            }
+        }
+        self.remove_parent(trx);
+    }
+
+    /// Move the block and its descendants under another parent in the same space.
+    /// The block will be inserted at `pos` of the parent's children, or appended if `pos` is `None`.
+    ///
+    /// Return false if the parent belongs to another space, or the parent is the block itself
+    /// or one of its descendants.
+    pub fn move_to(&self, trx: &mut TransactionMut, parent: &Block, pos: Option<u32>) -> bool {
+        if parent.id != self.id || parent.space_id != self.space_id {
+            return false;
+        }
+        let Some(space) = self.space(trx) else {
+            return false;
+        };
+        if self.subtree(trx, &space).contains(&parent.block_id) {
+            return false;
+        }
+
+        self.detach(trx, &space);
+        match pos {
+            Some(pos) => parent.insert_children_at(trx, self, pos),
+            None => parent.push_children(trx, self),
+        }
+
+        true
+    }
+
+    /// Move the block and its descendants into another space, block ids are kept.
+    /// The block will be detached from its parent, and inserted into `parent` if given.
+    ///
+    /// Return the moved block in the target space, or `None` if the parent doesn't belong to
+    /// the target space, or any block id of the subtree already exists in the target space.
+    pub fn move_into_space(
+        &self,
+        trx: &mut TransactionMut,
+        space: &Space,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+    ) -> Option<Block> {
+        if space.id() != self.id {
+            return None;
+        }
+        if let Some(parent) = parent {
+            if parent.id != self.id || parent.space_id != space.space_id() {
+                return None;
+            }
+        }
+        let source = self.space(trx)?;
+
+        if source.space_id() == space.space_id() {
+            return match parent {
+                Some(parent) => self.move_to(trx, parent, pos).then(|| self.clone()),
+                None => {
+                    self.detach(trx, &source);
+                    Some(self.clone())
+                }
+            };
+        }
+
+        let subtree = self.subtree(trx, &source);
+        if subtree.iter().any(|block_id| space.exists(trx, block_id)) {
+            return None;
+        }
+
+        self.detach(trx, &source);
+
+        // history of blocks is stored in `space:updated` which is shared by all spaces,
+        // so only the block content need to be moved
+        for block_id in &subtree {
+            if let Some(block) = source.blocks.get(trx, block_id).and_then(|b| b.to_ymap()) {
+                let target = space
+                    .blocks
+                    .insert(trx, block_id.as_str(), MapPrelim::<Any>::new());
+                copy::copy_map(trx, &block, &target);
+                source.blocks.remove(trx, block_id);
+            }
+        }
+
+        let block = Block::from(trx, space, &self.block_id, self.operator)?;
+        if let Some(parent) = parent {
+            match pos {
+                Some(pos) => parent.insert_children_at(trx, &block, pos),
+                None => parent.push_children(trx, &block),
+            }
+        }
+
+        Some(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn move_to() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let a = space.create(&mut t.trx, "a", "affine:frame");
+            let b = space.create(&mut t.trx, "b", "affine:text");
+            let c = space.create(&mut t.trx, "c", "affine:text");
+            let d = space.create(&mut t.trx, "d", "affine:frame");
+
+            a.push_children(&mut t.trx, &b);
+            a.push_children(&mut t.trx, &c);
+
+            assert!(b.move_to(&mut t.trx, &d, None));
+            assert_eq!(a.children(&t.trx), vec!["c".to_owned()]);
+            assert_eq!(d.children(&t.trx), vec!["b".to_owned()]);
+            assert_eq!(b.parent(&t.trx), Some("d".to_owned()));
+
+            assert!(c.move_to(&mut t.trx, &d, Some(0)));
+            assert!(a.children(&t.trx).is_empty());
+            assert_eq!(d.children(&t.trx), vec!["c".to_owned(), "b".to_owned()]);
+
+            // cannot move a block into itself or its descendants
+            assert!(!d.move_to(&mut t.trx, &d, None));
+            assert!(!d.move_to(&mut t.trx, &b, None));
+
+            assert!(d.move_to(&mut t.trx, &a, None));
+            assert_eq!(a.subtree(&t.trx, &space), vec!["a", "d", "c", "b"]);
+        });
+    }
+
+    #[test]
+    fn move_into_space() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let source = t.get_space("source");
+            let target = t.get_space("target");
+
+            let a = source.create(&mut t.trx, "a", "affine:frame");
+            let b = source.create(&mut t.trx, "b", "affine:frame");
+            let c = source.create(&mut t.trx, "c", "affine:text");
+            let x = target.create(&mut t.trx, "x", "affine:frame");
+
+            a.push_children(&mut t.trx, &b);
+            b.push_children(&mut t.trx, &c);
+            c.text_insert(&mut t.trx, "text", 0, "hello");
+
+            let b = b
+                .move_into_space(&mut t.trx, &target, Some(&x), None)
+                .unwrap();
+
+            assert!(a.children(&t.trx).is_empty());
+            assert!(!source.exists(&t.trx, "b"));
+            assert!(!source.exists(&t.trx, "c"));
+
+            assert_eq!(x.children(&t.trx), vec!["b".to_owned()]);
+            assert_eq!(b.parent(&t.trx), Some("x".to_owned()));
+            assert_eq!(b.children(&t.trx), vec!["c".to_owned()]);
+
+            let c = target.get(&t.trx, "c").unwrap();
+            assert_eq!(c.flavor(&t.trx), "affine:text");
+            assert_eq!(c.text_len(&t.trx, "text"), Some(5));
+            assert_eq!(c.get(&t.trx, "text").unwrap().to_string(), "hello");
+
+            // block id conflicts in target space
+            let y = source.create(&mut t.trx, "x", "affine:frame");
+            assert_eq!(y.move_into_space(&mut t.trx, &target, None, None), None);
+            assert!(source.exists(&t.trx, "x"));
+        });
+    }
+}
@@ -0,0 +1,44 @@
+use super::*;
+
+impl Block {
+    /// Name of the template if the block is the root of a template.
+    pub fn template_name<T>(&self, trx: &T) -> Option<String>
+    where
+        T: ReadTxn,
+    {
+        self.block.get(trx, sys::TEMPLATE).map(|v| v.to_string(trx))
+    }
+
+    // copy the block and its descendants into the template space as a named template
+    pub(crate) fn save_as_template(
+        &self,
+        trx: &mut TransactionMut,
+        space: &Space,
+        name: &str,
+    ) -> Option<Block> {
+        let template = self.duplicate(trx, space, None, None)?;
+        template.block.insert(trx, sys::TEMPLATE, name.to_owned());
+
+        Some(template)
+    }
+
+    // create a copy of the block and its descendants with the variables substituted
+    pub(crate) fn instantiate(
+        &self,
+        trx: &mut TransactionMut,
+        space: &Space,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+        variables: &HashMap<String, String>,
+    ) -> Option<Block> {
+        if space.id() != self.id {
+            return None;
+        }
+
+        let mut snapshot = self.snapshot(trx)?;
+        for block in snapshot.iter_mut() {
+            block.substitute(variables);
+        }
+        Self::restore(trx, space, snapshot, parent, pos)
+    }
+}
@@ -0,0 +1,243 @@
+use super::*;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use yrs::{
+    types::{
+        text::{Diff, YChange},
+        Attrs, Value,
+    },
+    Text, TextPrelim, TextRef,
+};
+
+/// A chunk of rich text in the delta format used by Yjs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({"insert": "hello", "attributes": {"bold": true}}))]
+pub struct TextDelta {
+    #[schema(value_type = Object)]
+    pub insert: Any,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Object)]
+    pub attributes: Option<HashMap<String, Any>>,
+}
+
+fn to_attrs(attributes: HashMap<String, Any>) -> Attrs {
+    attributes
+        .into_iter()
+        .map(|(key, value)| (key.into(), value))
+        .collect()
+}
+
+impl Block {
+    fn text_ref<T>(&self, trx: &T, key: &str) -> Option<TextRef>
+    where
+        T: ReadTxn,
+    {
+        let key = format!("prop:{key}");
+        self.block.get(trx, &key).and_then(|v| v.to_ytext())
+    }
+
+    // get the rich text of a property, a plain string value will be
+    // converted into rich text, `None` if the property is any other value
+    fn get_or_create_text(&self, trx: &mut TransactionMut, key: &str) -> Option<TextRef> {
+        let key = format!("prop:{key}");
+        let content = match self.block.get(trx, &key) {
+            Some(Value::YText(text)) => return Some(text),
+            Some(Value::Any(Any::String(content))) => content.to_string(),
+            Some(_) => return None,
+            None => String::new(),
+        };
+        Some(self.block.insert(trx, key, TextPrelim::new(content)))
+    }
+
+    /// Length of the rich text property, `None` if the property is not a rich text.
+    pub fn text_len<T>(&self, trx: &T, key: &str) -> Option<u32>
+    where
+        T: ReadTxn,
+    {
+        self.text_ref(trx, key).map(|text| text.len(trx))
+    }
+
+    /// Insert a chunk into the rich text property at `index`.
+    /// The index will be clamped to the length of text.
+    /// Return false if the property is neither a rich text nor a plain string.
+    pub fn text_insert(
+        &self,
+        trx: &mut TransactionMut,
+        key: &str,
+        index: u32,
+        chunk: &str,
+    ) -> bool {
+        let Some(text) = self.get_or_create_text(trx, key) else {
+            return false;
+        };
+        let index = index.min(text.len(trx));
+        text.insert(trx, index, chunk);
+
+        self.log_update(trx, HistoryOperation::Update);
+        true
+    }
+
+    /// Insert a formatted chunk into the rich text property at `index`.
+    /// Return false if the property is neither a rich text nor a plain string.
+    pub fn text_insert_with_format(
+        &self,
+        trx: &mut TransactionMut,
+        key: &str,
+        index: u32,
+        chunk: &str,
+        attributes: HashMap<String, Any>,
+    ) -> bool {
+        let Some(text) = self.get_or_create_text(trx, key) else {
+            return false;
+        };
+        let index = index.min(text.len(trx));
+        text.insert_with_attributes(trx, index, chunk, to_attrs(attributes));
+
+        self.log_update(trx, HistoryOperation::Update);
+        true
+    }
+
+    /// Delete `len` characters from the rich text property starting at `index`.
+    /// Return false if nothing was deleted.
+    pub fn text_delete(&self, trx: &mut TransactionMut, key: &str, index: u32, len: u32) -> bool {
+        if let Some(text) = self.text_ref(trx, key) {
+            let text_len = text.len(trx);
+            if index < text_len && len > 0 {
+                text.remove_range(trx, index, len.min(text_len - index));
+                self.log_update(trx, HistoryOperation::Update);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Apply formatting attributes to a range of the rich text property.
+    /// Attributes with a null value will remove the formatting.
+    pub fn text_format(
+        &self,
+        trx: &mut TransactionMut,
+        key: &str,
+        index: u32,
+        len: u32,
+        attributes: HashMap<String, Any>,
+    ) -> bool {
+        if let Some(text) = self.text_ref(trx, key) {
+            let text_len = text.len(trx);
+            if index < text_len && len > 0 && !attributes.is_empty() {
+                text.format(trx, index, len.min(text_len - index), to_attrs(attributes));
+                self.log_update(trx, HistoryOperation::Update);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Remove formatting attributes from a range of the rich text property.
+    pub fn text_remove_format(
+        &self,
+        trx: &mut TransactionMut,
+        key: &str,
+        index: u32,
+        len: u32,
+        attributes: Vec<String>,
+    ) -> bool {
+        let attributes = attributes.into_iter().map(|key| (key, Any::Null)).collect();
+        self.text_format(trx, key, index, len, attributes)
+    }
+
+    /// Read the rich text property as delta, `None` if the property is not a rich text.
+    pub fn text_delta<T>(&self, trx: &T, key: &str) -> Option<Vec<TextDelta>>
+    where
+        T: ReadTxn,
+    {
+        self.text_ref(trx, key).map(|text| {
+            text.diff(trx, YChange::identity)
+                .into_iter()
+                .map(|diff: Diff<YChange>| TextDelta {
+                    insert: diff.insert.to_json(trx),
+                    attributes: diff
+                        .attributes
+                        .filter(|attributes| !attributes.is_empty())
+                        .map(|attributes| {
+                            attributes
+                                .iter()
+                                .map(|(key, value)| (key.to_string(), value.clone()))
+                                .collect()
+                        }),
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn text_edit() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let block = space.create(&mut t.trx, "a", "affine:paragraph");
+            block.set(&mut t.trx, "text", "hello world");
+            assert_eq!(block.text_len(&t.trx, "text"), None);
+
+            block.text_insert(&mut t.trx, "text", 5, ",");
+            assert!(block.text_delete(&mut t.trx, "text", 0, 1));
+            block.text_insert(&mut t.trx, "text", 0, "H");
+            block.text_insert(&mut t.trx, "text", 100, "!");
+
+            assert_eq!(block.text_len(&t.trx, "text"), Some(13));
+            assert_eq!(
+                block.get(&t.trx, "text").unwrap().to_string(),
+                "Hello, world!"
+            );
+
+            assert!(!block.text_delete(&mut t.trx, "text", 13, 1));
+            assert!(!block.text_delete(&mut t.trx, "not_exists", 0, 1));
+
+            // other values are kept instead of being replaced by a rich text
+            block.set(&mut t.trx, "flag", true);
+            assert!(!block.text_insert(&mut t.trx, "flag", 0, "1"));
+            assert!(!block.text_insert_with_format(&mut t.trx, "flag", 0, "1", HashMap::new()));
+            assert_eq!(block.get(&t.trx, "flag"), Some(Any::Bool(true)));
+        });
+    }
+
+    #[test]
+    fn text_format() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let block = space.create(&mut t.trx, "a", "affine:paragraph");
+            block.text_insert(&mut t.trx, "text", 0, "hello world");
+
+            let bold = HashMap::from([("bold".to_owned(), Any::Bool(true))]);
+            assert!(block.text_format(&mut t.trx, "text", 0, 5, bold.clone()));
+
+            assert_eq!(
+                block.text_delta(&t.trx, "text"),
+                Some(vec![
+                    TextDelta {
+                        insert: Any::String("hello".into()),
+                        attributes: Some(bold),
+                    },
+                    TextDelta {
+                        insert: Any::String(" world".into()),
+                        attributes: None,
+                    },
+                ])
+            );
+
+            assert!(block.text_remove_format(&mut t.trx, "text", 0, 5, vec!["bold".to_owned()]));
+
+            let delta = block.text_delta(&t.trx, "text").unwrap();
+            assert!(delta.iter().all(|delta| delta.attributes.is_none()));
+        });
+    }
+}
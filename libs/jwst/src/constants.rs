@@ -15,6 +15,9 @@ pub mod sys {
     /// `sys:parent`
     pub const PARENT: &str = "sys:parent";
 
+    /// `sys:template`
+    pub const TEMPLATE: &str = "sys:template";
+
     /// `sys:version`
     pub const VERSION: &str = "sys:version";
 }
@@ -25,4 +28,10 @@ pub mod space {
 
     /// `space:meta`
     pub const META: &str = "space:meta";
+
+    /// `space:templates`
+    pub const TEMPLATES: &str = "space:templates";
+
+    /// `space:subdocs`
+    pub const SUBDOCS: &str = "space:subdocs";
 }
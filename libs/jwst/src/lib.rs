@@ -7,17 +7,25 @@ mod workspaces;
 
 pub mod constants;
 
-pub use block::Block;
+pub use block::{Block, TextDelta};
 pub use history::{
     parse_history, parse_history_client, BlockHistory, HistoryOperation, RawHistory,
 };
-pub use space::Space;
+pub use space::{IntegrityIssue, IntegrityReport, Space};
 pub use tracing::{debug, error, info, log::LevelFilter, trace, warn};
 pub use types::{BlobMetadata, BlobStorage, DocStorage, JwstError, JwstResult};
 pub use utils::{sync_encode_update, Base64DecodeError, Base64Engine, URL_SAFE_ENGINE};
-pub use workspaces::{MapSubscription, Workspace, WorkspaceMetadata, WorkspaceTransaction};
 #[cfg(feature = "workspace-search")]
-pub use workspaces::{SearchResult, SearchResults};
+pub use workspaces::{
+    remove_search_index, search_index_dir, SearchError, SearchOptions, SearchQuery, SearchRange,
+    SearchResult, SearchResults,
+};
+pub use workspaces::{
+    BlockChange, BlockObserver, MapSubscription, PageLink, PluginImpl, PluginRegister,
+    SearchAnalyzer, SearchFieldType, Template, Workspace, WorkspaceMetadata, WorkspaceTransaction,
+};
+#[cfg(feature = "workspace-vector-search")]
+pub use workspaces::{Embedder, HashEmbedder, VectorSearchResult};
 
 #[inline]
 pub fn print_versions(pkg_name: &str, pkg_version: &str) {
@@ -0,0 +1,336 @@
+use super::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+/// A problem found in the block tree of a space.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// `sys:parent` of the block points to a block which doesn't exist.
+    Orphan { block_id: String, parent: String },
+    /// An entry of `sys:children` points to a block which doesn't exist.
+    DanglingChild { block_id: String, child_id: String },
+    /// The child appears more than once in `sys:children`, or is also contained by another block.
+    DuplicateChild { block_id: String, child_id: String },
+    /// `sys:parent` of the block doesn't match the block which contains it.
+    ParentMismatch {
+        block_id: String,
+        parent: Option<String>,
+        expected: Option<String>,
+    },
+    /// The blocks contain each other through `sys:children`, in containment order.
+    Cycle { block_ids: Vec<String> },
+}
+
+/// The result of checking the block tree of a space.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct IntegrityReport {
+    pub space_id: String,
+    pub issues: Vec<IntegrityIssue>,
+    /// Whether the issues have been repaired.
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Analysis {
+    issues: Vec<IntegrityIssue>,
+    // positions of the children entries which should be removed, in ascending order
+    removals: HashMap<String, Vec<u32>>,
+    // the parent which should be written into `sys:parent`, `None` to remove it
+    parents: HashMap<String, Option<String>>,
+}
+
+impl Space {
+    fn analyze<T>(&self, trx: &T) -> Analysis
+    where
+        T: ReadTxn,
+    {
+        let mut blocks = self.blocks(trx, |blocks| {
+            blocks
+                .map(|block| (block.block_id(), block.parent(trx), block.children(trx)))
+                .collect::<Vec<_>>()
+        });
+        blocks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let exists = blocks
+            .iter()
+            .map(|(block_id, ..)| block_id.as_str())
+            .collect::<HashSet<_>>();
+        let declared = blocks
+            .iter()
+            .map(|(block_id, parent, _)| (block_id.as_str(), parent.as_deref()))
+            .collect::<HashMap<_, _>>();
+
+        let mut analysis = Analysis::default();
+        let mut removals: HashMap<&str, Vec<u32>> = HashMap::new();
+        let mut containers: HashMap<&str, Vec<(&str, u32)>> = HashMap::new();
+
+        for (block_id, _, children) in &blocks {
+            let mut seen = HashSet::new();
+            let mut duplicated = HashSet::new();
+            for (pos, child_id) in children.iter().enumerate() {
+                let child_id = child_id.as_str();
+                if !exists.contains(child_id) {
+                    if seen.insert(child_id) {
+                        analysis.issues.push(IntegrityIssue::DanglingChild {
+                            block_id: block_id.clone(),
+                            child_id: child_id.to_owned(),
+                        });
+                    }
+                } else if !seen.insert(child_id) {
+                    if duplicated.insert(child_id) {
+                        analysis.issues.push(IntegrityIssue::DuplicateChild {
+                            block_id: block_id.clone(),
+                            child_id: child_id.to_owned(),
+                        });
+                    }
+                } else {
+                    containers
+                        .entry(child_id)
+                        .or_default()
+                        .push((block_id.as_str(), pos as u32));
+                    continue;
+                }
+                removals
+                    .entry(block_id.as_str())
+                    .or_default()
+                    .push(pos as u32);
+            }
+        }
+
+        // a block can only be contained by one block, prefer the one in `sys:parent`
+        let mut parent_of: HashMap<&str, (&str, u32)> = HashMap::new();
+        for (child_id, _, _) in &blocks {
+            let Some(entries) = containers.get(child_id.as_str()) else {
+                continue;
+            };
+            let chosen = entries
+                .iter()
+                .find(|(parent, _)| declared.get(child_id.as_str()) == Some(&Some(*parent)))
+                .unwrap_or(&entries[0]);
+            for (parent, pos) in entries.iter().filter(|entry| *entry != chosen) {
+                analysis.issues.push(IntegrityIssue::DuplicateChild {
+                    block_id: parent.to_string(),
+                    child_id: child_id.clone(),
+                });
+                removals.entry(*parent).or_default().push(*pos);
+            }
+            parent_of.insert(child_id.as_str(), *chosen);
+        }
+
+        // every block has at most one parent now, so cycles can be found by walking up
+        let mut visited = HashSet::new();
+        let mut broken = HashSet::new();
+        for (block_id, _, _) in &blocks {
+            let mut path = vec![];
+            let mut current = Some(block_id.as_str());
+            while let Some(block_id) = current {
+                if visited.contains(block_id) {
+                    break;
+                }
+                if let Some(start) = path.iter().position(|id| *id == block_id) {
+                    let mut cycle = path[start..].to_vec();
+                    cycle.reverse();
+                    let head = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap_or(0);
+                    cycle.rotate_left(head);
+
+                    // break the cycle by detaching the smallest block from its parent
+                    if let Some((parent, pos)) = parent_of.remove(cycle[0]) {
+                        removals.entry(parent).or_default().push(pos);
+                    }
+                    broken.insert(cycle[0]);
+                    analysis.issues.push(IntegrityIssue::Cycle {
+                        block_ids: cycle.into_iter().map(ToOwned::to_owned).collect(),
+                    });
+                    break;
+                }
+                path.push(block_id);
+                current = parent_of.get(block_id).map(|(parent, _)| *parent);
+            }
+            visited.extend(path);
+        }
+
+        for (block_id, parent, _) in &blocks {
+            let parent = parent.as_deref();
+            let expected = parent_of.get(block_id.as_str()).map(|(parent, _)| *parent);
+            if parent == expected {
+                continue;
+            }
+            if !broken.contains(block_id.as_str()) {
+                analysis.issues.push(match (parent, expected) {
+                    (Some(parent), None) if !exists.contains(parent) => IntegrityIssue::Orphan {
+                        block_id: block_id.clone(),
+                        parent: parent.to_owned(),
+                    },
+                    (parent, expected) => IntegrityIssue::ParentMismatch {
+                        block_id: block_id.clone(),
+                        parent: parent.map(ToOwned::to_owned),
+                        expected: expected.map(ToOwned::to_owned),
+                    },
+                });
+            }
+            analysis
+                .parents
+                .insert(block_id.clone(), expected.map(ToOwned::to_owned));
+        }
+
+        analysis.removals = removals
+            .into_iter()
+            .map(|(block_id, mut positions)| {
+                positions.sort_unstable();
+                positions.dedup();
+                (block_id.to_owned(), positions)
+            })
+            .collect();
+
+        analysis
+    }
+
+    /// Check the block tree of the space, reporting orphans, dangling children,
+    /// duplicate children, mismatched parents and cycles.
+    pub fn check_integrity<T>(&self, trx: &T) -> IntegrityReport
+    where
+        T: ReadTxn,
+    {
+        IntegrityReport {
+            space_id: self.space_id(),
+            issues: self.analyze(trx).issues,
+            repaired: false,
+        }
+    }
+
+    /// Check and repair the block tree of the space:
+    /// - dangling and duplicate children entries will be removed, a block contained by
+    ///   multiple blocks will be kept in the block of its `sys:parent`.
+    /// - cycles will be broken by detaching the block with the smallest id.
+    /// - `sys:parent` will be rewritten to the containing block, or removed if not contained.
+    ///
+    /// No block will be deleted, orphans become root blocks of the space.
+    pub fn repair_integrity(&self, trx: &mut TransactionMut) -> IntegrityReport {
+        let Analysis {
+            issues,
+            removals,
+            parents,
+        } = self.analyze(trx);
+
+        for (block_id, positions) in removals {
+            if let Some(block) = self.get(trx, &block_id) {
+                block.remove_children_at(trx, &positions);
+            }
+        }
+        for (block_id, parent) in parents {
+            if let Some(block) = self.get(trx, &block_id) {
+                match parent {
+                    Some(parent) => block.set_parent(trx, parent),
+                    None => block.remove_parent(trx),
+                }
+            }
+        }
+
+        if !issues.is_empty() {
+            info!(
+                "repaired {} integrity issues in space: {}",
+                issues.len(),
+                self.space_id
+            );
+        }
+
+        IntegrityReport {
+            space_id: self.space_id(),
+            repaired: !issues.is_empty(),
+            issues,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integrity() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let a = space.create(&mut t.trx, "a", "affine:page");
+            let b = space.create(&mut t.trx, "b", "affine:frame");
+            let c = space.create(&mut t.trx, "c", "affine:paragraph");
+            a.push_children(&mut t.trx, &b);
+            b.push_children(&mut t.trx, &c);
+
+            assert!(space.check_integrity(&t.trx).is_ok());
+
+            // dangling child and orphan
+            let x = space.create(&mut t.trx, "x", "affine:frame");
+            let y = space.create(&mut t.trx, "y", "affine:paragraph");
+            x.push_children(&mut t.trx, &y);
+            a.push_children(&mut t.trx, &x);
+            space.remove(&mut t.trx, "x");
+
+            // contained by multiple blocks
+            let d = space.create(&mut t.trx, "d", "affine:frame");
+            d.push_children(&mut t.trx, &c);
+
+            // cycle
+            let e = space.create(&mut t.trx, "e", "affine:frame");
+            let f = space.create(&mut t.trx, "f", "affine:frame");
+            e.push_children(&mut t.trx, &f);
+            f.push_children(&mut t.trx, &e);
+
+            // mismatched parent
+            b.set_parent(&mut t.trx, "d".to_owned());
+
+            let report = space.check_integrity(&t.trx);
+            assert!(!report.repaired);
+            assert_eq!(
+                report.issues,
+                vec![
+                    IntegrityIssue::DanglingChild {
+                        block_id: "a".to_owned(),
+                        child_id: "x".to_owned(),
+                    },
+                    IntegrityIssue::DuplicateChild {
+                        block_id: "b".to_owned(),
+                        child_id: "c".to_owned(),
+                    },
+                    IntegrityIssue::Cycle {
+                        block_ids: vec!["e".to_owned(), "f".to_owned()],
+                    },
+                    IntegrityIssue::ParentMismatch {
+                        block_id: "b".to_owned(),
+                        parent: Some("d".to_owned()),
+                        expected: Some("a".to_owned()),
+                    },
+                    IntegrityIssue::Orphan {
+                        block_id: "y".to_owned(),
+                        parent: "x".to_owned(),
+                    },
+                ]
+            );
+
+            let report = space.repair_integrity(&mut t.trx);
+            assert!(report.repaired);
+            assert_eq!(report.issues.len(), 5);
+
+            assert_eq!(a.children(&t.trx), vec!["b".to_owned()]);
+            assert!(b.children(&t.trx).is_empty());
+            assert_eq!(d.children(&t.trx), vec!["c".to_owned()]);
+            assert_eq!(b.parent(&t.trx), Some("a".to_owned()));
+            assert_eq!(y.parent(&t.trx), None);
+            assert_eq!(e.parent(&t.trx), None);
+            assert_eq!(e.children(&t.trx), vec!["f".to_owned()]);
+            assert!(f.children(&t.trx).is_empty());
+
+            assert!(space.check_integrity(&t.trx).is_ok());
+        });
+    }
+}
@@ -1,8 +1,11 @@
+mod integrity;
 mod transaction;
 
 use super::{block::MarkdownState, *};
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use transaction::SpaceTransaction;
+
+pub use integrity::{IntegrityIssue, IntegrityReport};
 use yrs::{Doc, Map, MapRef, ReadTxn, Transact, TransactionMut, WriteTxn};
 
 pub struct Space {
@@ -36,8 +39,9 @@ impl Space {
         }
     }
 
-    pub fn from_exists<I, S>(trx: &TransactionMut, doc: Doc, id: I, space_id: S) -> Option<Self>
+    pub fn from_exists<T, I, S>(trx: &T, doc: Doc, id: I, space_id: S) -> Option<Self>
     where
+        T: ReadTxn,
         I: AsRef<str>,
         S: AsRef<str>,
     {
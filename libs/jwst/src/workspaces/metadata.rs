@@ -1,17 +1,81 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use lib0::any::Any;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use yrs::{Map, MapRef, Transaction};
 
 pub const SEARCH_INDEX: &str = "search_index";
+pub const SEARCH_ANALYZER: &str = "search_analyzer";
+pub const SEARCH_FIELDS: &str = "search_fields";
+
+/// The analyzer used to tokenize the search index fields and queries.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema, ToSchema, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchAnalyzer {
+    /// N-grams of 1 to 10 characters, works for any language but the index is large.
+    #[default]
+    Gram,
+    /// English words with stemming, stop words are removed.
+    English,
+    /// Chinese segmentation by jieba, mixed english words are handled like `English`.
+    Chinese,
+    /// Overlapping bigrams of CJK characters, other words are kept as is.
+    CjkBigram,
+}
+
+impl SearchAnalyzer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gram => "gram",
+            Self::English => "english",
+            Self::Chinese => "chinese",
+            Self::CjkBigram => "cjk_bigram",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Gram, Self::English, Self::Chinese, Self::CjkBigram]
+            .into_iter()
+            .find(|analyzer| analyzer.as_str() == name)
+    }
+}
+
+/// The type of a block property indexed besides the text fields of `search_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsonSchema, ToSchema, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchFieldType {
+    /// A string or an array of strings, matched exactly.
+    Tag,
+    /// A number, can be filtered by range.
+    Number,
+    /// A timestamp in milliseconds or a RFC 3339 / `YYYY-MM-DD` string, can be filtered by range.
+    Date,
+}
+
+impl SearchFieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tag => "tag",
+            Self::Number => "number",
+            Self::Date => "date",
+        }
+    }
+}
 
 #[derive(Debug, Clone, JsonSchema, Serialize, Deserialize)]
 pub struct WorkspaceMetadata {
     pub name: Option<String>,
     // pub avatar: Option<String>,
     pub search_index: Vec<String>,
+    #[serde(default)]
+    pub search_analyzer: SearchAnalyzer,
+    /// Typed block properties to index, by property name.
+    #[serde(default)]
+    pub search_fields: BTreeMap<String, SearchFieldType>,
 }
 
 impl From<(&'_ Transaction<'_>, MapRef)> for WorkspaceMetadata {
@@ -23,6 +87,14 @@ impl From<(&'_ Transaction<'_>, MapRef)> for WorkspaceMetadata {
                 Some(value) => serde_json::from_str::<Vec<String>>(&value.to_string(trx)).unwrap(),
                 None => vec!["title".to_string(), "text".to_string()],
             },
+            search_analyzer: map
+                .get(trx, SEARCH_ANALYZER)
+                .and_then(|value| SearchAnalyzer::from_name(&value.to_string(trx)))
+                .unwrap_or_default(),
+            search_fields: map
+                .get(trx, SEARCH_FIELDS)
+                .and_then(|value| serde_json::from_str(&value.to_string(trx)).ok())
+                .unwrap_or_default(),
         }
     }
 }
@@ -37,6 +109,19 @@ impl From<WorkspaceMetadata> for Any {
         //     map.insert("avatar".to_owned(), avatar.into());
         // }
         map.insert(SEARCH_INDEX.to_owned(), val.search_index.into());
+        map.insert(
+            SEARCH_ANALYZER.to_owned(),
+            val.search_analyzer.as_str().to_owned().into(),
+        );
+        map.insert(
+            SEARCH_FIELDS.to_owned(),
+            Any::Map(Box::new(
+                val.search_fields
+                    .into_iter()
+                    .map(|(name, field_type)| (name, field_type.as_str().to_owned().into()))
+                    .collect(),
+            )),
+        );
         Any::Map(map.into())
     }
 }
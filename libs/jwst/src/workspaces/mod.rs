@@ -1,13 +1,21 @@
 mod metadata;
 mod plugins;
+mod template;
 mod transaction;
 mod workspace;
 
 use super::{constants, error, info, trace, warn, Space};
 use plugins::PluginMap;
 
-pub use metadata::WorkspaceMetadata;
+pub use metadata::{SearchAnalyzer, SearchFieldType, WorkspaceMetadata};
 #[cfg(feature = "workspace-search")]
-pub use plugins::{SearchResult, SearchResults};
+pub use plugins::{
+    remove_search_index, search_index_dir, SearchError, SearchOptions, SearchQuery, SearchRange,
+    SearchResult, SearchResults,
+};
+pub use plugins::{BlockChange, BlockObserver, PageLink, PluginImpl, PluginRegister};
+#[cfg(feature = "workspace-vector-search")]
+pub use plugins::{Embedder, HashEmbedder, VectorSearchResult};
+pub use template::Template;
 pub use transaction::WorkspaceTransaction;
 pub use workspace::{MapSubscription, Workspace};
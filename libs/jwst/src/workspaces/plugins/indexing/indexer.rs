@@ -1,16 +1,36 @@
-use super::{PluginImpl, Workspace};
+use super::{
+    BlockChange, BlockObserver, IndexVersion, PluginImpl, SearchAnalyzer, SearchError,
+    SearchFieldType, SearchQuery, Workspace,
+};
+use crate::{error, Block};
 use lib0::any::Any;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::{atomic::AtomicU32, Arc};
-use tantivy::{collector::TopDocs, query::QueryParser, schema::*, Index, ReloadPolicy};
+use tantivy::{
+    collector::TopDocs,
+    query::{BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery},
+    schema::*,
+    DateTime, Index, ReloadPolicy, SnippetGenerator, TantivyError, Term,
+};
 use utoipa::ToSchema;
+use yrs::ReadTxn;
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct SearchResult {
     pub block_id: String,
+    pub space_id: String,
+    pub flavor: String,
     pub score: f32,
+    /// The first search index field which matched the query.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Fragment of the matched field, matched terms are highlighted with `<b>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 /// Returned from [`Workspace::search`]
@@ -19,26 +39,178 @@ pub struct SearchResult {
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SearchResults(Vec<SearchResult>);
 
+impl SearchResults {
+    pub fn items(&self) -> &[SearchResult] {
+        &self.0
+    }
+}
+
+/// Pagination and filters of [`Workspace::search_with_options`]
+///
+/// [`Workspace::search_with_options`]: crate::Workspace::search_with_options
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SearchOptions {
+    /// Number of results to skip, at most 10000.
+    #[serde(default)]
+    pub offset: usize,
+    /// Max number of results, default to 10, at most 100.
+    pub limit: Option<usize>,
+    /// Only search blocks in the space.
+    pub space: Option<String>,
+    /// Only search blocks with the flavor.
+    pub flavor: Option<String>,
+    /// Only search blocks updated at or after the timestamp in milliseconds.
+    pub updated_after: Option<u64>,
+    /// Only search blocks updated before the timestamp in milliseconds.
+    pub updated_before: Option<u64>,
+    /// Only search blocks whose number or date fields are in the ranges, by field name.
+    /// Dates are timestamps in milliseconds.
+    #[serde(default)]
+    pub ranges: BTreeMap<String, SearchRange>,
+    /// Only search blocks with all the tags, by tag field name.
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+}
+
+/// Range of a number or date field in [SearchOptions].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SearchRange {
+    /// Inclusive lower bound.
+    pub from: Option<f64>,
+    /// Exclusive upper bound.
+    pub to: Option<f64>,
+}
+
+impl SearchRange {
+    fn bounds<T>(&self, convert: impl Fn(f64) -> T) -> (Bound<T>, Bound<T>) {
+        (
+            self.from
+                .map_or(Bound::Unbounded, |from| Bound::Included(convert(from))),
+            self.to
+                .map_or(Bound::Unbounded, |to| Bound::Excluded(convert(to))),
+        )
+    }
+}
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+impl SearchOptions {
+    /// The larger limits are clamped to it.
+    pub const MAX_LIMIT: usize = 100;
+    /// The larger offsets are rejected, the skipped results are still collected.
+    pub const MAX_OFFSET: usize = 10000;
+
+    /// Max number of results, `limit` or the default limit, at most [SearchOptions::MAX_LIMIT].
+    pub fn result_limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .min(Self::MAX_LIMIT)
+    }
+}
+
+/// The fields of indexed block documents besides the search index fields.
+pub(super) struct DocumentFields {
+    /// `{space_id}:{block_id}`, the unique key of the document
+    pub(super) doc_id: Field,
+    pub(super) block_id: Field,
+    pub(super) space_id: Field,
+    pub(super) flavor: Field,
+    pub(super) updated: Field,
+}
+
+// a value of the search index fields and typed fields
+enum FieldValue {
+    Text(String),
+    Number(f64),
+    // timestamp in milliseconds
+    Date(i64),
+}
+
+impl FieldValue {
+    // strings of a string, Y.Text or an array
+    fn texts(value: &Any) -> Vec<Self> {
+        match value {
+            Any::String(text) => vec![Self::Text(text.to_string())],
+            Any::Array(items) => items.iter().flat_map(Self::texts).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn number(value: &Any) -> Option<Self> {
+        match value {
+            Any::Number(number) => Some(Self::Number(*number)),
+            Any::BigInt(number) => Some(Self::Number(*number as f64)),
+            _ => None,
+        }
+    }
+
+    fn date(value: &Any) -> Option<Self> {
+        match value {
+            Any::Number(millis) => Some(Self::Date(*millis as i64)),
+            Any::BigInt(millis) => Some(Self::Date(*millis)),
+            Any::String(date) => chrono::DateTime::parse_from_rfc3339(date)
+                .map(|date| date.timestamp_millis())
+                .or_else(|_| {
+                    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map(|date| {
+                        date.and_hms_opt(0, 0, 0)
+                            .unwrap_or_default()
+                            .timestamp_millis()
+                    })
+                })
+                .ok()
+                .map(Self::Date),
+            _ => None,
+        }
+    }
+}
+
+// the content of a block to be indexed
+struct BlockDocument {
+    doc_id: String,
+    block_id: String,
+    space_id: String,
+    flavor: String,
+    updated: u64,
+    values: Vec<(Field, FieldValue)>,
+}
+
 pub struct IndexingPluginImpl {
-    // /// `true` if the text search has not yet populated the Tantivy index
-    // /// `false` if there should only be incremental changes necessary to the blocks.
-    // first_index: bool,
-    pub(super) queue_reindex: Arc<AtomicU32>,
+    // blocks changed since the last update, need to keep so it gets dropped with this plugin
+    pub(super) observer: BlockObserver,
     pub(super) schema: Schema,
+    pub(super) fields: DocumentFields,
     pub(super) index: Rc<Index>,
     pub(super) query_parser: QueryParser,
-    // need to keep so it gets dropped with this plugin
-    pub(super) _update_sub: Option<yrs::UpdateSubscription>,
     pub(super) search_index: Vec<String>,
+    pub(super) search_analyzer: SearchAnalyzer,
+    pub(super) search_fields: BTreeMap<String, SearchFieldType>,
+    // typed fields which are not search index fields
+    pub(super) typed_fields: Vec<(String, SearchFieldType, Field)>,
+    // directory of the persisted index, `None` if the index is stored in memory
+    pub(super) persisted_dir: Option<PathBuf>,
 }
 
 impl IndexingPluginImpl {
-    pub fn search<S: AsRef<str>>(
+    pub fn search(&self, query: impl Into<SearchQuery>) -> Result<SearchResults, SearchError> {
+        self.search_with_options(query, &SearchOptions::default())
+    }
+
+    pub fn search_with_options(
         &self,
-        query: S,
-    ) -> Result<SearchResults, Box<dyn std::error::Error>> {
+        query: impl Into<SearchQuery>,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, SearchError> {
+        if options.offset > SearchOptions::MAX_OFFSET {
+            return Err(SearchError::InvalidQuery(format!(
+                "offset {} exceeds the max offset {}",
+                options.offset,
+                SearchOptions::MAX_OFFSET
+            )));
+        }
+
         let mut items = Vec::new();
-        if self.search_index.is_empty() {
+        let limit = options.result_limit();
+        if self.search_index.is_empty() || limit == 0 {
             return Ok(SearchResults(items));
         }
 
@@ -48,117 +220,295 @@ impl IndexingPluginImpl {
             .reload_policy(ReloadPolicy::OnCommit)
             .try_into()?;
         let searcher = reader.searcher();
-        let query = self.query_parser.parse_query(query.as_ref())?;
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
-        // The actual documents still need to be retrieved from Tantivy’s store.
-        // Since the body field was not configured as stored, the document returned will only contain a title.
-
-        if !top_docs.is_empty() {
-            let block_id_field = self.schema.get_field("block_id").unwrap();
-
-            for (score, doc_address) in top_docs {
-                let retrieved_doc = searcher.doc(doc_address)?;
-                if let Some(Value::Str(id)) = retrieved_doc.get_first(block_id_field) {
-                    items.push(SearchResult {
-                        block_id: id.to_string(),
-                        score,
-                    });
-                } else {
-                    let to_json = self.schema.to_json(&retrieved_doc);
-                    eprintln!("Unexpected non-block doc in Tantivy result set: {to_json}");
-                }
+        let text_query = self.build_query(&query.into())?;
+
+        // snippets are generated from the same text query for every search index field
+        let snippet_generators = self
+            .search_index
+            .iter()
+            .zip(self.default_fields())
+            .map(|(name, field)| {
+                SnippetGenerator::create(&searcher, &*text_query, field)
+                    .map(|generator| (name, generator))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let query = self.filter_query(text_query, options)?;
+        let top_docs = searcher.search(
+            &query,
+            &TopDocs::with_limit(limit).and_offset(options.offset),
+        )?;
+
+        for (score, doc_address) in top_docs {
+            let retrieved_doc = searcher.doc(doc_address)?;
+            let get_text = |field| match retrieved_doc.get_first(field) {
+                Some(Value::Str(text)) => Some(text.to_string()),
+                _ => None,
+            };
+
+            if let (Some(block_id), Some(space_id)) = (
+                get_text(self.fields.block_id),
+                get_text(self.fields.space_id),
+            ) {
+                let (field, snippet) = snippet_generators
+                    .iter()
+                    .map(|(name, generator)| (name, generator.snippet_from_doc(&retrieved_doc)))
+                    .find(|(_, snippet)| !snippet.highlighted().is_empty())
+                    .map(|(name, snippet)| (Some(name.to_string()), Some(snippet.to_html())))
+                    .unwrap_or_default();
+
+                items.push(SearchResult {
+                    block_id,
+                    space_id,
+                    flavor: get_text(self.fields.flavor).unwrap_or_default(),
+                    score,
+                    field,
+                    snippet,
+                });
+            } else {
+                let to_json = self.schema.to_json(&retrieved_doc);
+                eprintln!("Unexpected non-block doc in Tantivy result set: {to_json}");
             }
         }
 
         Ok(SearchResults(items))
     }
+
+    pub(super) fn default_fields(&self) -> Vec<Field> {
+        self.search_index
+            .iter()
+            .map(|name| self.schema.get_field(name).unwrap())
+            .collect()
+    }
+
+    fn typed_field(&self, name: &str) -> Result<(SearchFieldType, Field), SearchError> {
+        self.typed_fields
+            .iter()
+            .find(|(field_name, ..)| field_name == name)
+            .map(|(_, field_type, field)| (*field_type, *field))
+            .ok_or_else(|| SearchError::InvalidQuery(format!("unknown search field: {name}")))
+    }
+
+    // combine the text query with the filters of options
+    fn filter_query(
+        &self,
+        query: Box<dyn Query>,
+        options: &SearchOptions,
+    ) -> Result<Box<dyn Query>, SearchError> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![];
+
+        let term_filters = [
+            (self.fields.space_id, &options.space),
+            (self.fields.flavor, &options.flavor),
+        ];
+        for (field, value) in term_filters {
+            if let Some(value) = value {
+                clauses.push((
+                    Occur::Must,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, value),
+                        IndexRecordOption::Basic,
+                    )),
+                ));
+            }
+        }
+
+        if options.updated_after.is_some() || options.updated_before.is_some() {
+            let start = options.updated_after.unwrap_or(u64::MIN);
+            let end = options.updated_before.unwrap_or(u64::MAX);
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new_u64(self.fields.updated, start..end)),
+            ));
+        }
+
+        for (name, range) in &options.ranges {
+            let range_query = match self.typed_field(name)? {
+                (SearchFieldType::Number, field) => {
+                    let (from, to) = range.bounds(|value| value);
+                    RangeQuery::new_f64_bounds(field, from, to)
+                }
+                (SearchFieldType::Date, field) => {
+                    let (from, to) =
+                        range.bounds(|value| DateTime::from_timestamp_millis(value as i64));
+                    RangeQuery::new_date_bounds(field, from, to)
+                }
+                (SearchFieldType::Tag, _) => {
+                    return Err(SearchError::InvalidQuery(format!(
+                        "range of tag field: {name}"
+                    )))
+                }
+            };
+            clauses.push((Occur::Must, Box::new(range_query)));
+        }
+
+        for (name, tags) in &options.tags {
+            let field = match self.typed_field(name)? {
+                (SearchFieldType::Tag, field) => field,
+                _ => {
+                    return Err(SearchError::InvalidQuery(format!(
+                        "tags of non tag field: {name}"
+                    )))
+                }
+            };
+            for tag in tags {
+                clauses.push((
+                    Occur::Must,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, tag),
+                        IndexRecordOption::Basic,
+                    )),
+                ));
+            }
+        }
+
+        Ok(if clauses.is_empty() {
+            query
+        } else {
+            clauses.push((Occur::Must, query));
+            Box::new(BooleanQuery::new(clauses))
+        })
+    }
 }
 
 impl PluginImpl for IndexingPluginImpl {
     fn on_update(&mut self, ws: &Workspace) -> Result<(), Box<dyn std::error::Error>> {
-        let curr = self.queue_reindex.load(std::sync::atomic::Ordering::SeqCst);
-        if curr > 0 {
-            // TODO: reindex
-
-            let re_index_list = ws.with_trx(|t| {
-                t.spaces(|spaces| {
-                    spaces
-                        .flat_map(|space| {
-                            space.blocks(&t.trx, |blocks| {
-                                blocks
-                                    .map(|block| {
-                                        (
-                                            format!("{}:{}", space.space_id(), block.block_id()),
-                                            self.search_index
-                                                .iter()
-                                                .map(|field| {
-                                                    block
-                                                        .content(&t.trx)
-                                                        .get(field)
-                                                        .map(ToOwned::to_owned)
-                                                        .and_then(|a| match a {
-                                                            Any::String(str) => {
-                                                                Some(str.to_string())
-                                                            }
-                                                            _ => None,
-                                                        })
-                                                })
-                                                .collect(),
-                                        )
-                                    })
-                                    .collect::<Vec<_>>()
-                            })
-                        })
-                        .collect::<HashMap<_, _>>()
-                })
+        if let Some(changes) = self.observer.take_changes(ws) {
+            let (version, (updated, removed)): (_, (Vec<_>, Vec<_>)) = ws.with_trx(|t| {
+                let version = IndexVersion::new(
+                    &t.trx,
+                    &self.search_index,
+                    self.search_analyzer,
+                    &self.search_fields,
+                );
+                let documents = changes
+                    .iter()
+                    .map(|change| {
+                        let (space_id, block_id) = (change.space_id(), change.block_id());
+                        let doc_id = format!("{space_id}:{block_id}");
+                        let document = match change {
+                            BlockChange::Updated { .. } => t
+                                .get_exists_space(space_id)
+                                .and_then(|space| space.get(&t.trx, block_id))
+                                .map(|block| self.block_document(&t.trx, space_id, &block)),
+                            BlockChange::Removed { .. } => None,
+                        };
+                        (doc_id, document)
+                    })
+                    .partition(|(_, document)| document.is_some());
+                (version, documents)
             });
 
-            // dbg!((txn, upd));
-            // println!("got update event: {items}");
-            // just re-index stupidly
-            self.re_index_content(re_index_list)
-                .map_err(|err| format!("Error during reindex: {err:?}"))?;
-        }
+            self.re_index_content(
+                updated.into_iter().filter_map(|(_, document)| document),
+                removed.into_iter().map(|(doc_id, _)| doc_id),
+            )
+            .map_err(|err| format!("Error during reindex: {err:?}"))?;
 
-        // reset back down now that the update was applied
-        self.queue_reindex
-            .fetch_sub(curr, std::sync::atomic::Ordering::SeqCst);
+            if let Some(dir) = &self.persisted_dir {
+                version
+                    .write(dir)
+                    .map_err(|err| format!("Error writing index version: {err:?}"))?;
+            }
+        }
 
         Ok(())
     }
 }
 
 impl IndexingPluginImpl {
-    fn re_index_content<BlockIdTitleAndTextIter>(
+    fn block_document<T: ReadTxn>(&self, trx: &T, space_id: &str, block: &Block) -> BlockDocument {
+        let content = block.content(trx);
+        BlockDocument {
+            doc_id: format!("{space_id}:{}", block.block_id()),
+            block_id: block.block_id(),
+            space_id: space_id.to_owned(),
+            flavor: block.flavor(trx),
+            updated: block.updated(trx),
+            values: self
+                .search_index
+                .iter()
+                .zip(self.default_fields())
+                .flat_map(|(name, field)| {
+                    content
+                        .get(name)
+                        .map(FieldValue::texts)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(move |value| (field, value))
+                })
+                .chain(
+                    self.typed_fields
+                        .iter()
+                        .flat_map(|(name, field_type, field)| {
+                            let values = match (field_type, content.get(name)) {
+                                (SearchFieldType::Tag, Some(value)) => FieldValue::texts(value),
+                                (SearchFieldType::Number, Some(value)) => {
+                                    FieldValue::number(value).into_iter().collect()
+                                }
+                                (SearchFieldType::Date, Some(value)) => {
+                                    FieldValue::date(value).into_iter().collect()
+                                }
+                                (_, None) => vec![],
+                            };
+                            values.into_iter().map(move |value| (*field, value))
+                        }),
+                )
+                .collect(),
+        }
+    }
+
+    // replace the documents of updated blocks and delete the documents of removed blocks
+    fn re_index_content<BlockDocumentIter, RemovedDocIdIter>(
         &mut self,
-        blocks: BlockIdTitleAndTextIter,
+        blocks: BlockDocumentIter,
+        removed: RemovedDocIdIter,
     ) -> Result<(), Box<dyn std::error::Error>>
     where
-        // TODO: use a structure with better names than tuples?
-        BlockIdTitleAndTextIter: IntoIterator<Item = (String, Vec<Option<String>>)>,
+        BlockDocumentIter: IntoIterator<Item = BlockDocument>,
+        RemovedDocIdIter: IntoIterator<Item = String>,
     {
-        let block_id_field = self.schema.get_field("block_id").unwrap();
+        let DocumentFields {
+            doc_id: doc_id_field,
+            block_id: block_id_field,
+            space_id: space_id_field,
+            flavor: flavor_field,
+            updated: updated_field,
+        } = self.fields;
 
-        let mut writer = self
-            .index
-            .writer(50_000_000)
-            .map_err(|err| format!("Error creating writer: {err:?}"))?;
+        let mut writer = self.index.writer(50_000_000).map_err(|err| {
+            if let TantivyError::LockFailure(..) = err {
+                // e.g. another instance of the workspace is writing the same persisted index
+                error!(
+                    "search index in {:?} is locked by another writer: {err}",
+                    self.persisted_dir
+                );
+            }
+            format!("Error creating writer: {err:?}")
+        })?;
 
-        let search_index = self
-            .search_index
-            .iter()
-            .map(|filed| self.schema.get_field(filed).unwrap())
-            .collect::<Vec<_>>();
+        for doc_id in removed {
+            writer.delete_term(Term::from_field_text(doc_id_field, &doc_id));
+        }
+
+        for block in blocks {
+            writer.delete_term(Term::from_field_text(doc_id_field, &block.doc_id));
 
-        for (block_id, fields) in blocks {
             let mut block_doc = Document::new();
-            block_doc.add_text(block_id_field, block_id);
-            fields.iter().enumerate().for_each(|(index, field)| {
-                if let Some(field_text) = field {
-                    let index_field = search_index.get(index).unwrap().to_owned();
-                    block_doc.add_text(index_field, field_text);
+            block_doc.add_text(doc_id_field, block.doc_id);
+            block_doc.add_text(block_id_field, block.block_id);
+            block_doc.add_text(space_id_field, block.space_id);
+            block_doc.add_text(flavor_field, block.flavor);
+            block_doc.add_u64(updated_field, block.updated);
+            for (field, value) in block.values {
+                match value {
+                    FieldValue::Text(text) => block_doc.add_text(field, text),
+                    FieldValue::Number(number) => block_doc.add_f64(field, number),
+                    FieldValue::Date(millis) => {
+                        block_doc.add_date(field, DateTime::from_timestamp_millis(millis))
+                    }
                 }
-            });
+            }
             writer.add_document(block_doc)?;
         }
 
@@ -183,7 +533,7 @@ mod test {
             let mut sorted_ids = $search_results
                 .0
                 .iter()
-                .map(|i| &i.block_id)
+                .map(|i| format!("{}:{}", i.space_id, i.block_id))
                 .collect::<Vec<_>>();
             sorted_ids.sort();
             assert_eq!(
@@ -361,4 +711,395 @@ mod test {
             })
             .is_some());
     }
+
+    #[test]
+    fn incremental_index_test() {
+        let workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-load");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let a = space.create(&mut t.trx, "a", "affine:text");
+            let b = space.create(&mut t.trx, "b", "affine:text");
+            a.set(&mut t.trx, "title", "apple");
+            b.set(&mut t.trx, "title", "banana");
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                expect_search_gives_ids!(search_plugin, "apple", &["space:a"]);
+                expect_search_gives_ids!(search_plugin, "banana", &["space:b"]);
+            })
+            .is_some());
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let a = space.get(&t.trx, "a").unwrap();
+            a.set(&mut t.trx, "title", "cherry");
+            space.remove(&mut t.trx, "b");
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                assert!(!search_plugin.observer.has_changes());
+
+                // only the latest content of the block is indexed
+                expect_search_gives_ids!(search_plugin, "cherry", &["space:a"]);
+                assert!(search_plugin.search("apple").unwrap().0.is_empty());
+                assert!(search_plugin.search("banana").unwrap().0.is_empty());
+            })
+            .is_some());
+    }
+
+    #[test]
+    fn search_options_test() {
+        let workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-load");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let space1 = t.get_space("space1");
+            let space2 = t.get_space("space2");
+
+            let a = space1.create(&mut t.trx, "a", "affine:text");
+            let b = space1.create(&mut t.trx, "b", "affine:page");
+            let c = space2.create(&mut t.trx, "c", "affine:text");
+            a.set(&mut t.trx, "title", "hello world");
+            b.set(&mut t.trx, "title", "hello there");
+            c.set(&mut t.trx, "text", "hello again");
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                let search = |options: SearchOptions| {
+                    search_plugin
+                        .search_with_options("hello", &options)
+                        .expect("no error searching")
+                };
+
+                let page = search(SearchOptions {
+                    limit: Some(2),
+                    ..Default::default()
+                });
+                assert_eq!(page.items().len(), 2);
+                let page = search(SearchOptions {
+                    offset: 2,
+                    limit: Some(2),
+                    ..Default::default()
+                });
+                assert_eq!(page.items().len(), 1);
+
+                // the limit is clamped and the offset is bounded
+                let options = SearchOptions {
+                    limit: Some(usize::MAX),
+                    ..Default::default()
+                };
+                assert_eq!(options.result_limit(), SearchOptions::MAX_LIMIT);
+                assert_eq!(search(options).items().len(), 3);
+                let page = search(SearchOptions {
+                    offset: SearchOptions::MAX_OFFSET,
+                    ..Default::default()
+                });
+                assert!(page.items().is_empty());
+                assert!(search_plugin
+                    .search_with_options(
+                        "hello",
+                        &SearchOptions {
+                            offset: usize::MAX,
+                            ..Default::default()
+                        }
+                    )
+                    .unwrap_err()
+                    .is_invalid_query());
+
+                let result = search(SearchOptions {
+                    space: Some("space2".into()),
+                    ..Default::default()
+                });
+                expect_result_ids!(result, &["space2:c"]);
+                assert_eq!(result.items()[0].field.as_deref(), Some("text"));
+                assert_eq!(result.items()[0].flavor, "affine:text");
+
+                let result = search(SearchOptions {
+                    flavor: Some("affine:page".into()),
+                    ..Default::default()
+                });
+                expect_result_ids!(result, &["space1:b"]);
+
+                let result = search(SearchOptions {
+                    updated_after: Some(u64::MAX - 1),
+                    ..Default::default()
+                });
+                assert!(result.items().is_empty());
+
+                let result = search_plugin.search("world").expect("no error searching");
+                expect_result_ids!(result, &["space1:a"]);
+                assert_eq!(result.items()[0].field.as_deref(), Some("title"));
+                assert!(result.items()[0].snippet.as_ref().unwrap().contains("<b>"));
+            })
+            .is_some());
+    }
+
+    #[test]
+    fn persisted_index_test() {
+        let dir = std::env::temp_dir().join(format!("jwst-index-{}", nanoid::nanoid!()));
+        let doc = yrs::Doc::new();
+        let with_persisted_index = |doc: &yrs::Doc| {
+            let workspace = Workspace::from_doc(doc.clone(), "wk-persist");
+            super::super::super::insert_plugin(
+                workspace,
+                IndexingPluginRegister::persisted_directory(dir.clone()),
+            )
+            .expect("failed to insert plugin")
+        };
+        let queued = |workspace: &Workspace| {
+            workspace
+                .with_plugin::<IndexingPluginImpl, u32>(|search_plugin| {
+                    search_plugin.observer.pending()
+                })
+                .unwrap()
+        };
+
+        let workspace = with_persisted_index(&doc);
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            let a = space.create(&mut t.trx, "a", "affine:text");
+            a.set(&mut t.trx, "title", "apple");
+        });
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        assert!(dir.join(VERSION_FILE).exists());
+        drop(workspace);
+
+        // the workspace is not changed, the index will be reused without re-indexing
+        let workspace = with_persisted_index(&doc);
+        assert_eq!(queued(&workspace), 0);
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                expect_search_gives_ids!(search_plugin, "apple", &["space:a"]);
+            })
+            .is_some());
+
+        // later changes are still indexed
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            let a = space.get(&t.trx, "a").unwrap();
+            a.set(&mut t.trx, "title", "cherry");
+        });
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                expect_search_gives_ids!(search_plugin, "cherry", &["space:a"]);
+            })
+            .is_some());
+        drop(workspace);
+
+        // the workspace is changed while the index is not loaded, so the index will be rebuilt
+        Workspace::from_doc(doc.clone(), "wk-persist").with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.remove(&mut t.trx, "a");
+            let b = space.create(&mut t.trx, "b", "affine:text");
+            b.set(&mut t.trx, "title", "durian");
+        });
+        let workspace = with_persisted_index(&doc);
+        assert_eq!(queued(&workspace), 1);
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        assert!(workspace
+            .with_plugin::<IndexingPluginImpl, ()>(|search_plugin| {
+                expect_search_gives_ids!(search_plugin, "durian", &["space:b"]);
+                assert!(search_plugin.search("cherry").unwrap().0.is_empty());
+            })
+            .is_some());
+        drop(workspace);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_analyzer_test() {
+        let search = |analyzer: SearchAnalyzer, text: &str, query: &str| {
+            let workspace = Workspace::new("wk-analyzer");
+            workspace.set_search_analyzer(analyzer);
+            assert_eq!(workspace.metadata().search_analyzer, analyzer);
+            workspace.with_trx(|mut t| {
+                let space = t.get_space("space");
+                let block = space.create(&mut t.trx, "a", "affine:text");
+                block.set(&mut t.trx, "title", text);
+            });
+            workspace.search(query).unwrap().0.len()
+        };
+
+        // stemming and stop words
+        assert_eq!(
+            search(SearchAnalyzer::English, "Running the tests", "run"),
+            1
+        );
+        assert_eq!(
+            search(SearchAnalyzer::English, "Running the tests", "the"),
+            0
+        );
+        assert_eq!(
+            search(SearchAnalyzer::English, "Running the tests", "unn"),
+            0
+        );
+        assert_eq!(search(SearchAnalyzer::Gram, "Running the tests", "unn"), 1);
+
+        // chinese segmentation
+        assert_eq!(
+            search(SearchAnalyzer::Chinese, "我们讨论搜索引擎", "搜索"),
+            1
+        );
+        assert_eq!(
+            search(SearchAnalyzer::Chinese, "我们讨论搜索引擎", "论搜"),
+            0
+        );
+        assert_eq!(
+            search(SearchAnalyzer::Chinese, "Searching 搜索引擎", "search"),
+            1
+        );
+        assert_eq!(
+            search(SearchAnalyzer::Chinese, "我们讨论搜索引擎。", "引擎"),
+            1
+        );
+
+        // cjk bigrams
+        assert_eq!(
+            search(SearchAnalyzer::CjkBigram, "我们讨论搜索引擎", "论搜"),
+            1
+        );
+        assert_eq!(
+            search(SearchAnalyzer::CjkBigram, "我们讨论搜索引擎", "索擎"),
+            0
+        );
+    }
+
+    #[test]
+    fn search_fields_test() {
+        let workspace = Workspace::new("wk-fields");
+        assert!(workspace.set_search_fields(BTreeMap::from([
+            ("tags".to_owned(), SearchFieldType::Tag),
+            ("priority".to_owned(), SearchFieldType::Number),
+            ("due".to_owned(), SearchFieldType::Date),
+        ])));
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            for (id, tags, priority, due) in [
+                ("a", vec!["work", "urgent"], 3.0, "2023-03-01"),
+                ("b", vec!["work"], 1.0, "2023-04-01T08:00:00+08:00"),
+                ("c", vec!["home"], 2.0, "2023-05-01"),
+            ] {
+                let block = space.create(&mut t.trx, id, "affine:todo");
+                block.text_insert(&mut t.trx, "text", 0, "task to do");
+                let tags = tags
+                    .into_iter()
+                    .map(|tag| Any::String(tag.into()))
+                    .collect();
+                block.set(&mut t.trx, "tags", Any::Array(tags));
+                block.set(&mut t.trx, "priority", priority);
+                block.set(&mut t.trx, "due", due.to_owned());
+            }
+        });
+
+        let search = |query: SearchQuery, options: SearchOptions| {
+            let mut ids = workspace
+                .search_with_options(query, &options)
+                .unwrap()
+                .items()
+                .iter()
+                .map(|item| item.block_id.clone())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let range = |from: Option<f64>, to: Option<f64>| SearchRange { from, to };
+        let millis = |date: &str| {
+            chrono::DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .timestamp_millis() as f64
+        };
+
+        // rich text is indexed
+        assert_eq!(
+            search("task".into(), Default::default()),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            search(
+                SearchQuery::All,
+                SearchOptions {
+                    tags: BTreeMap::from([("tags".to_owned(), vec!["work".to_owned()])]),
+                    ..Default::default()
+                }
+            ),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            search(
+                "task".into(),
+                SearchOptions {
+                    ranges: BTreeMap::from([("priority".to_owned(), range(Some(2.0), None))]),
+                    ..Default::default()
+                }
+            ),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            search(
+                SearchQuery::All,
+                SearchOptions {
+                    ranges: BTreeMap::from([(
+                        "due".to_owned(),
+                        range(
+                            Some(millis("2023-03-01T00:00:00Z")),
+                            Some(millis("2023-04-01T00:00:00Z"))
+                        )
+                    )]),
+                    tags: BTreeMap::from([("tags".to_owned(), vec!["work".to_owned()])]),
+                    ..Default::default()
+                }
+            ),
+            // the upper bound is exclusive in any time zone
+            vec!["a"]
+        );
+
+        let error = |options: SearchOptions| {
+            workspace
+                .search_with_options(SearchQuery::All, &options)
+                .unwrap_err()
+        };
+        assert!(error(SearchOptions {
+            ranges: BTreeMap::from([("unknown".to_owned(), range(None, None))]),
+            ..Default::default()
+        })
+        .is_invalid_query());
+        assert!(error(SearchOptions {
+            tags: BTreeMap::from([("priority".to_owned(), vec!["1".to_owned()])]),
+            ..Default::default()
+        })
+        .is_invalid_query());
+    }
 }
@@ -1,9 +1,19 @@
 mod indexer;
+mod query;
 mod register;
 mod tokenizer;
 
-use super::{PluginImpl, PluginRegister, Workspace};
-use tokenizer::{tokenizers_register, GRAM_TOKENIZER};
+use super::{
+    BlockChange, BlockObserver, PluginImpl, PluginRegister, SearchAnalyzer, SearchFieldType,
+    Workspace, WorkspaceMetadata,
+};
+use indexer::DocumentFields;
+use register::IndexVersion;
+#[cfg(test)]
+use register::VERSION_FILE;
+use tokenizer::tokenizers_register;
 
-pub use indexer::{IndexingPluginImpl, SearchResult, SearchResults};
+pub use indexer::{IndexingPluginImpl, SearchOptions, SearchRange, SearchResult, SearchResults};
+pub use query::{SearchError, SearchQuery};
 pub(super) use register::IndexingPluginRegister;
+pub use register::{remove_search_index, search_index_dir};
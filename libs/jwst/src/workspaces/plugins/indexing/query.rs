@@ -0,0 +1,325 @@
+use super::IndexingPluginImpl;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tantivy::{
+    query::{AllQuery, BooleanQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, TermQuery},
+    schema::IndexRecordOption,
+    TantivyError, Term,
+};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+// larger distances match too many terms and are not supported by tantivy
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+fn default_distance() -> u8 {
+    1
+}
+
+/// A structured query of [`Workspace::search`], words are tokenized by the
+/// [`SearchAnalyzer`] of the workspace and matched in any of the search index fields.
+///
+/// Plain strings can be converted into a [`SearchQuery::Text`].
+///
+/// [`Workspace::search`]: crate::Workspace::search
+/// [`SearchAnalyzer`]: crate::SearchAnalyzer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchQuery {
+    /// Query syntax of tantivy, e.g. `hello AND title:world`.
+    Text { text: String },
+    /// Every word must match a term within `distance` edits, 1 by default and at most 2.
+    /// The last word is matched as a prefix if `prefix` is true.
+    Fuzzy {
+        text: String,
+        #[serde(default = "default_distance")]
+        distance: u8,
+        #[serde(default)]
+        prefix: bool,
+    },
+    /// Every word must match, the last word is matched as a prefix, for search as you type.
+    Prefix { text: String },
+    /// The words must appear next to each other in order.
+    Phrase { text: String },
+    /// Matched blocks must match all `must` queries and none of the `must_not` queries,
+    /// `should` queries are required only if there is no `must` query.
+    Bool {
+        #[serde(default)]
+        must: Vec<SearchQuery>,
+        #[serde(default)]
+        should: Vec<SearchQuery>,
+        #[serde(default)]
+        must_not: Vec<SearchQuery>,
+    },
+    /// Matches every block, to search by the filters of [`SearchOptions`] only.
+    ///
+    /// [`SearchOptions`]: crate::SearchOptions
+    All,
+}
+
+impl From<&str> for SearchQuery {
+    fn from(text: &str) -> Self {
+        Self::Text {
+            text: text.to_owned(),
+        }
+    }
+}
+
+impl From<&String> for SearchQuery {
+    fn from(text: &String) -> Self {
+        text.as_str().into()
+    }
+}
+
+impl From<String> for SearchQuery {
+    fn from(text: String) -> Self {
+        Self::Text { text }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("fuzzy distance {0} exceeds the max distance {MAX_FUZZY_DISTANCE}")]
+    InvalidDistance(u8),
+    #[error("query has no words to search")]
+    EmptyQuery,
+    #[error("failed to update search index: {0}")]
+    Update(String),
+    #[error("search index error")]
+    Index(#[from] TantivyError),
+}
+
+impl SearchError {
+    /// Whether the error is caused by the query rather than the index.
+    pub fn is_invalid_query(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidQuery(_) | Self::InvalidDistance(_) | Self::EmptyQuery
+        )
+    }
+}
+
+impl IndexingPluginImpl {
+    pub(super) fn build_query(&self, query: &SearchQuery) -> Result<Box<dyn Query>, SearchError> {
+        match query {
+            SearchQuery::Text { text } => self
+                .query_parser
+                .parse_query(text)
+                .map_err(|err| SearchError::InvalidQuery(err.to_string())),
+            SearchQuery::Fuzzy {
+                text,
+                distance,
+                prefix,
+            } => {
+                if *distance > MAX_FUZZY_DISTANCE {
+                    return Err(SearchError::InvalidDistance(*distance));
+                }
+                self.words_query(text, |term, last| {
+                    Box::new(if *prefix && last {
+                        FuzzyTermQuery::new_prefix(term, *distance, true)
+                    } else {
+                        FuzzyTermQuery::new(term, *distance, true)
+                    })
+                })
+            }
+            SearchQuery::Prefix { text } => self.words_query(text, |term, last| {
+                if last {
+                    Box::new(FuzzyTermQuery::new_prefix(term, 0, true))
+                } else {
+                    Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs))
+                }
+            }),
+            SearchQuery::Phrase { text } => {
+                let words = self.tokenize(text)?;
+                let fields = self.default_fields();
+                let clauses = fields
+                    .into_iter()
+                    .map(|field| {
+                        let mut terms = words
+                            .iter()
+                            .map(|word| Term::from_field_text(field, word))
+                            .collect::<Vec<_>>();
+                        let query: Box<dyn Query> = if terms.len() > 1 {
+                            Box::new(PhraseQuery::new(terms))
+                        } else {
+                            Box::new(TermQuery::new(
+                                terms.remove(0),
+                                IndexRecordOption::WithFreqs,
+                            ))
+                        };
+                        (Occur::Should, query)
+                    })
+                    .collect();
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+            SearchQuery::Bool {
+                must,
+                should,
+                must_not,
+            } => {
+                if must.is_empty() && should.is_empty() {
+                    // a query with only `must_not` clauses matches nothing
+                    return Err(SearchError::EmptyQuery);
+                }
+                let clauses = [
+                    (Occur::Must, must),
+                    (Occur::Should, should),
+                    (Occur::MustNot, must_not),
+                ]
+                .into_iter()
+                .flat_map(|(occur, queries)| {
+                    queries
+                        .iter()
+                        .map(move |query| self.build_query(query).map(|query| (occur, query)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+            SearchQuery::All => Ok(Box::new(AllQuery)),
+        }
+    }
+
+    // every word must match in any of the search index fields,
+    // `word_query` receives the term of a word and whether it's the last word
+    fn words_query(
+        &self,
+        text: &str,
+        word_query: impl Fn(Term, bool) -> Box<dyn Query>,
+    ) -> Result<Box<dyn Query>, SearchError> {
+        let words = self.tokenize(text)?;
+        let fields = self.default_fields();
+        let clauses = words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                let last = index + 1 == words.len();
+                let alternatives = fields
+                    .iter()
+                    .map(|field| {
+                        let term = Term::from_field_text(*field, word);
+                        (Occur::Should, word_query(term, last))
+                    })
+                    .collect();
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::new(alternatives)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    // all search index fields share the analyzer of the workspace
+    fn tokenize(&self, text: &str) -> Result<Vec<String>, SearchError> {
+        let Some(field) = self.default_fields().first().copied() else {
+            return Err(SearchError::EmptyQuery);
+        };
+        let analyzer = self.index.tokenizer_for_field(field)?;
+
+        let mut words = vec![];
+        let mut stream = analyzer.token_stream(text);
+        while stream.advance() {
+            words.push(stream.token().text.clone());
+        }
+        if words.is_empty() {
+            return Err(SearchError::EmptyQuery);
+        }
+
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{SearchAnalyzer, Workspace};
+
+    #[test]
+    fn structured_query_test() {
+        let workspace = Workspace::new("wk-query");
+        workspace.set_search_analyzer(SearchAnalyzer::English);
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            for (id, title) in [
+                ("a", "hello world"),
+                ("b", "help wanted"),
+                ("c", "world peace"),
+            ] {
+                let block = space.create(&mut t.trx, id, "affine:text");
+                block.set(&mut t.trx, "title", title);
+            }
+        });
+
+        let search = |query: SearchQuery| {
+            let mut ids = workspace
+                .search(query)
+                .unwrap()
+                .items()
+                .iter()
+                .map(|item| item.block_id.clone())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let text = |text: &str| SearchQuery::Text {
+            text: text.to_owned(),
+        };
+        let prefix = |text: &str| SearchQuery::Prefix {
+            text: text.to_owned(),
+        };
+        let phrase = |text: &str| SearchQuery::Phrase {
+            text: text.to_owned(),
+        };
+
+        assert_eq!(
+            search(serde_json::from_str(r#"{"type": "fuzzy", "text": "wrld"}"#).unwrap()),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            search(SearchQuery::Fuzzy {
+                text: "helo wor".to_owned(),
+                distance: 1,
+                prefix: true,
+            }),
+            vec!["a"]
+        );
+        assert_eq!(search(prefix("hel")), vec!["a", "b"]);
+        assert_eq!(search(prefix("hello wor")), vec!["a"]);
+        assert_eq!(search(phrase("hello world")), vec!["a"]);
+        assert!(search(phrase("world hello")).is_empty());
+        assert_eq!(
+            search(SearchQuery::Bool {
+                must: vec![prefix("wor")],
+                should: vec![],
+                must_not: vec![text("peace")],
+            }),
+            vec!["a"]
+        );
+
+        let error = |query: SearchQuery| workspace.search(query).unwrap_err();
+        assert!(matches!(
+            error(SearchQuery::Fuzzy {
+                text: "wrld".to_owned(),
+                distance: 3,
+                prefix: false,
+            }),
+            SearchError::InvalidDistance(3)
+        ));
+        assert!(matches!(
+            error(text("title:(")),
+            SearchError::InvalidQuery(_)
+        ));
+        // stop words are removed
+        assert!(matches!(error(phrase("the")), SearchError::EmptyQuery));
+        assert!(matches!(
+            error(SearchQuery::Bool {
+                must: vec![],
+                should: vec![],
+                must_not: vec![text("peace")],
+            }),
+            SearchError::EmptyQuery
+        ));
+    }
+}
@@ -1,21 +1,89 @@
 use super::*;
+use crate::{info, Base64Engine, URL_SAFE_ENGINE};
+use serde::{Deserialize, Serialize};
 use std::{
-    path::PathBuf,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
     rc::Rc,
-    sync::{atomic::AtomicU32, Arc},
 };
 use tantivy::{
     query::QueryParser,
-    schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, STORED, STRING},
+    schema::{
+        IndexRecordOption, Schema, TextFieldIndexing, TextOptions, FAST, INDEXED, STORED, STRING,
+    },
     Index,
 };
+use yrs::{updates::encoder::Encode, ReadTxn};
+
+const INDEX_DIR: &str = "index";
+pub(super) const VERSION_FILE: &str = "version.json";
+
+/// The directory of the persisted search index of the workspace in `root`.
+pub fn search_index_dir(root: &Path, workspace_id: &str) -> PathBuf {
+    // workspace id may contain characters which are not allowed in path, so it will be encoded
+    root.join(URL_SAFE_ENGINE.encode(workspace_id))
+}
+
+/// Remove the persisted search index of the workspace in `root`,
+/// should be called after the workspace deleted.
+pub fn remove_search_index(root: &Path, workspace_id: &str) -> std::io::Result<()> {
+    let dir = search_index_dir(root, workspace_id);
+    if dir.exists() {
+        info!("remove search index of workspace: {}", workspace_id);
+        fs::remove_dir_all(dir)?;
+    }
+
+    Ok(())
+}
+
+/// The workspace state which a persisted index was built from.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct IndexVersion {
+    /// encoded state vector of the workspace
+    state_vector: String,
+    search_index: Vec<String>,
+    #[serde(default)]
+    analyzer: SearchAnalyzer,
+    #[serde(default)]
+    search_fields: BTreeMap<String, SearchFieldType>,
+}
+
+impl IndexVersion {
+    pub(super) fn new<T: ReadTxn>(
+        trx: &T,
+        search_index: &[String],
+        analyzer: SearchAnalyzer,
+        search_fields: &BTreeMap<String, SearchFieldType>,
+    ) -> Self {
+        Self {
+            state_vector: URL_SAFE_ENGINE.encode(trx.state_vector().encode_v1()),
+            search_index: search_index.to_vec(),
+            analyzer,
+            search_fields: search_fields.clone(),
+        }
+    }
+
+    fn read(dir: &Path) -> Option<Self> {
+        fs::read(dir.join(VERSION_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+    }
+
+    pub(super) fn write(&self, dir: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_vec(self)?;
+        // write to a temporary file first to avoid a partially written version
+        let temp = dir.join(format!("{VERSION_FILE}.tmp"));
+        fs::write(&temp, content)?;
+        fs::rename(temp, dir.join(VERSION_FILE))
+    }
+}
 
 #[derive(Debug)]
 enum IndexingStorageKind {
     /// Store index in memory (default)
     Ram,
     /// Store index in a specific directory
-    #[allow(dead_code)]
     PersistedDirectory(PathBuf),
 }
 
@@ -31,85 +99,136 @@ pub struct IndexingPluginRegister {
 }
 
 impl IndexingPluginRegister {
-    #[allow(dead_code)]
     pub fn ram() -> Self {
         Self {
             storage_kind: IndexingStorageKind::Ram,
         }
     }
 
-    #[allow(dead_code)]
+    /// Store the index and its version in `path`, the index will be reused if the
+    /// workspace hasn't changed since the index was built.
     pub fn persisted_directory(path: PathBuf) -> Self {
         Self {
             storage_kind: IndexingStorageKind::PersistedDirectory(path),
         }
     }
+
+    /// Use the search index directory of the workspace, or fallback to memory.
+    /// See [Workspace::from_doc_with_search_index].
+    pub fn from_workspace(ws: &Workspace) -> Self {
+        match ws.search_index_root() {
+            Some(root) => Self::persisted_directory(search_index_dir(root, &ws.id())),
+            None => Self::ram(),
+        }
+    }
 }
 
 impl PluginRegister for IndexingPluginRegister {
     type Plugin = IndexingPluginImpl;
     fn setup(self, ws: &mut Workspace) -> Result<IndexingPluginImpl, Box<dyn std::error::Error>> {
-        let search_index = ws.metadata().search_index;
-        let options = TextOptions::default().set_indexing_options(
-            TextFieldIndexing::default()
-                .set_tokenizer(GRAM_TOKENIZER)
-                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
-        );
+        let WorkspaceMetadata {
+            search_index,
+            search_analyzer,
+            search_fields,
+            ..
+        } = ws.metadata();
+        let options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(search_analyzer.as_str())
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            // stored for generating snippets
+            .set_stored();
 
+        // prefixed to avoid conflict with the search index fields
         let mut schema_builder = Schema::builder();
-        schema_builder.add_text_field("block_id", STRING | STORED);
+        let fields = DocumentFields {
+            doc_id: schema_builder.add_text_field("sys:doc_id", STRING),
+            block_id: schema_builder.add_text_field("sys:block_id", STRING | STORED),
+            space_id: schema_builder.add_text_field("sys:space_id", STRING | STORED),
+            flavor: schema_builder.add_text_field("sys:flavor", STRING | STORED),
+            updated: schema_builder.add_u64_field("sys:updated", INDEXED | STORED | FAST),
+        };
         search_index.iter().for_each(|field_name| {
             schema_builder.add_text_field(field_name.as_str(), options.clone());
         });
+        // text fields take precedence over typed fields with the same name
+        let typed_fields = search_fields
+            .iter()
+            .filter(|(name, _)| !search_index.contains(name))
+            .map(|(name, field_type)| {
+                let field = match field_type {
+                    SearchFieldType::Tag => schema_builder.add_text_field(name, STRING),
+                    SearchFieldType::Number => {
+                        schema_builder.add_f64_field(name, INDEXED | STORED | FAST)
+                    }
+                    SearchFieldType::Date => {
+                        schema_builder.add_date_field(name, INDEXED | STORED | FAST)
+                    }
+                };
+                (name.clone(), *field_type, field)
+            })
+            .collect();
         let schema = schema_builder.build();
 
-        let index_dir: Box<dyn tantivy::Directory> = match &self.storage_kind {
-            IndexingStorageKind::Ram => Box::new(tantivy::directory::RamDirectory::create()),
-            IndexingStorageKind::PersistedDirectory(dir) => {
-                Box::new(tantivy::directory::MmapDirectory::open(dir)?)
-            }
-        };
+        let (index_dir, persisted_dir, up_to_date): (Box<dyn tantivy::Directory>, _, _) =
+            match self.storage_kind {
+                IndexingStorageKind::Ram => (
+                    Box::new(tantivy::directory::RamDirectory::create()),
+                    None,
+                    false,
+                ),
+                IndexingStorageKind::PersistedDirectory(dir) => {
+                    let version = ws.with_trx(|t| {
+                        IndexVersion::new(&t.trx, &search_index, search_analyzer, &search_fields)
+                    });
+                    let up_to_date = IndexVersion::read(&dir).as_ref() == Some(&version);
+                    if !up_to_date && dir.exists() {
+                        // the index is outdated or built with other fields, rebuild it from scratch
+                        info!("rebuild search index of workspace: {}", ws.id());
+                        fs::remove_dir_all(&dir)?;
+                    }
+                    let index_dir = dir.join(INDEX_DIR);
+                    fs::create_dir_all(&index_dir)?;
+
+                    (
+                        Box::new(tantivy::directory::MmapDirectory::open(index_dir)?),
+                        Some(dir),
+                        up_to_date,
+                    )
+                }
+            };
 
         let index = Rc::new({
             let index = Index::open_or_create(index_dir, schema.clone())?;
-            tokenizers_register(index.tokenizers());
+            tokenizers_register(index.tokenizers(), search_analyzer);
             index
         });
 
-        let mut fields = vec![];
+        let mut default_fields = vec![];
         search_index.iter().for_each(|field_name| {
             let body = schema.get_field(field_name.as_str()).unwrap();
-            fields.push(body);
+            default_fields.push(body);
         });
 
-        let queue_reindex = Arc::new(AtomicU32::new(
-            // require an initial re-index by setting the default above 0
-            1,
-        ));
-
-        let queue_reindex_clone = queue_reindex.clone();
-        let sub = ws.observe(move |_txn, _e| {
-            // upd.update
-            // let u = yrs::Update::decode_v1(&e.update).unwrap();
-            // let _items = u
-            //     .as_items()
-            //     .into_iter()
-            //     .map(|i| format!("\n  {i:?}"))
-            //     .collect::<String>();
-            // for item in u.as_items() {
-            //     item.id;
-            // }
-            queue_reindex_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        });
+        // require an initial re-index of all blocks,
+        // unless the persisted index is built from the current workspace
+        let observer = BlockObserver::new(ws, !up_to_date);
 
-        Ok(IndexingPluginImpl {
+        let plugin = IndexingPluginImpl {
             schema,
-            query_parser: QueryParser::for_index(&index, fields),
+            query_parser: QueryParser::for_index(&index, default_fields),
+            fields,
             index,
-            queue_reindex,
-            // needs to drop sub with everything else
-            _update_sub: sub,
+            observer,
             search_index,
-        })
+            search_analyzer,
+            search_fields,
+            typed_fields,
+            persisted_dir,
+        };
+
+        Ok(plugin)
     }
 }
@@ -1,7 +1,242 @@
-use tantivy::tokenizer::{NgramTokenizer, TokenizerManager};
+use super::SearchAnalyzer;
+use cang_jie::{CangJieTokenizer, TokenizerOption};
+use jieba_rs::Jieba;
+use std::sync::{Arc, OnceLock};
+use tantivy::tokenizer::{
+    BoxTokenStream, Language, LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer,
+    Stemmer, StopWordFilter, TextAnalyzer, Token, TokenFilter, TokenStream, Tokenizer,
+    TokenizerManager,
+};
 
-pub const GRAM_TOKENIZER: &str = "gram";
+// tokens longer than this are usually not words, e.g. urls or base64 strings
+const MAX_TOKEN_LENGTH: usize = 40;
 
-pub fn tokenizers_register(tokenizers: &TokenizerManager) {
-    tokenizers.register(GRAM_TOKENIZER, NgramTokenizer::new(1, 10, false));
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+const CHINESE_STOP_WORDS: &[&str] = &[
+    "的", "了", "和", "是", "在", "就", "都", "而", "及", "与", "着", "或", "一个", "没有", "我们",
+    "你们", "他们", "它们", "这", "那", "之", "也",
+];
+
+// loading the dictionary is expensive, so it's shared by all workspaces
+fn jieba() -> Arc<Jieba> {
+    static JIEBA: OnceLock<Arc<Jieba>> = OnceLock::new();
+    JIEBA.get_or_init(|| Arc::new(Jieba::new())).clone()
+}
+
+fn stop_words(words: &[&[&str]]) -> StopWordFilter {
+    StopWordFilter::remove(
+        words
+            .iter()
+            .flat_map(|words| words.iter().map(|word| word.to_string()))
+            .collect(),
+    )
+}
+
+/// Register the tokenizer of the analyzer, named by [SearchAnalyzer::as_str].
+pub fn tokenizers_register(tokenizers: &TokenizerManager, analyzer: SearchAnalyzer) {
+    let name = analyzer.as_str();
+    match analyzer {
+        SearchAnalyzer::Gram => tokenizers.register(name, NgramTokenizer::new(1, 10, false)),
+        SearchAnalyzer::English => tokenizers.register(
+            name,
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+                .filter(LowerCaser)
+                .filter(stop_words(&[ENGLISH_STOP_WORDS]))
+                .filter(Stemmer::new(Language::English)),
+        ),
+        SearchAnalyzer::Chinese => tokenizers.register(
+            name,
+            TextAnalyzer::from(CangJieTokenizer {
+                worker: jieba(),
+                option: TokenizerOption::ForSearch { hmm: false },
+            })
+            // jieba also emits whitespaces and punctuations as tokens
+            .filter(WordOnlyFilter)
+            .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+            .filter(LowerCaser)
+            .filter(stop_words(&[ENGLISH_STOP_WORDS, CHINESE_STOP_WORDS]))
+            .filter(Stemmer::new(Language::English)),
+        ),
+        SearchAnalyzer::CjkBigram => tokenizers.register(
+            name,
+            TextAnalyzer::from(CjkBigramTokenizer)
+                .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+                .filter(LowerCaser),
+        ),
+    }
+}
+
+/// Removes the tokens without any alphanumeric characters, e.g. whitespaces and punctuations.
+///
+/// Unlike [tantivy::tokenizer::AlphaNumOnlyFilter], the tokens of non-ascii words are kept.
+#[derive(Clone)]
+pub struct WordOnlyFilter;
+
+impl TokenFilter for WordOnlyFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        WordOnlyFilterStream { tail: token_stream }.into()
+    }
+}
+
+struct WordOnlyFilterStream<'a> {
+    tail: BoxTokenStream<'a>,
+}
+
+impl TokenStream for WordOnlyFilterStream<'_> {
+    fn advance(&mut self) -> bool {
+        while self.tail.advance() {
+            if self.tail.token().text.chars().any(char::is_alphanumeric) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // hiragana and katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK unified ideographs extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{AC00}'..='\u{D7AF}' // hangul syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{20000}'..='\u{2A6DF}' // CJK unified ideographs extension B
+    )
+}
+
+/// Splits CJK characters into overlapping bigrams and other text into alphanumeric words,
+/// a single CJK character between other characters is kept as a token.
+#[derive(Clone)]
+pub struct CjkBigramTokenizer;
+
+impl Tokenizer for CjkBigramTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let mut tokens = vec![];
+        let mut push = |text: &str, offset_from: usize, offset_to: usize| {
+            tokens.push(Token {
+                offset_from,
+                offset_to,
+                position: tokens.len(),
+                text: text[offset_from..offset_to].to_owned(),
+                position_length: 1,
+            });
+        };
+
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if is_cjk(c) {
+                let mut prev = start;
+                while let Some(&(next, c)) = chars.peek() {
+                    if !is_cjk(c) {
+                        break;
+                    }
+                    push(text, prev, next + c.len_utf8());
+                    prev = next;
+                    chars.next();
+                }
+                if prev == start {
+                    push(text, start, start + c.len_utf8());
+                }
+            } else if c.is_alphanumeric() {
+                let mut end = start + c.len_utf8();
+                while let Some(&(next, c)) = chars.peek() {
+                    if !c.is_alphanumeric() || is_cjk(c) {
+                        break;
+                    }
+                    end = next + c.len_utf8();
+                    chars.next();
+                }
+                push(text, start, end);
+            }
+        }
+
+        CjkBigramTokenStream {
+            tokens,
+            index: None,
+        }
+        .into()
+    }
+}
+
+struct CjkBigramTokenStream {
+    tokens: Vec<Token>,
+    index: Option<usize>,
+}
+
+impl TokenStream for CjkBigramTokenStream {
+    fn advance(&mut self) -> bool {
+        let index = self.index.map_or(0, |index| index + 1);
+        self.index = Some(index);
+        index < self.tokens.len()
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index.unwrap_or_default()]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index.unwrap_or_default()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cjk_bigram_tokenizer() {
+        let mut stream = CjkBigramTokenizer.token_stream("搜索引擎 OctoBase, 中 test");
+        let mut tokens = vec![];
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push((token.text.clone(), token.position));
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                ("搜索".to_owned(), 0),
+                ("索引".to_owned(), 1),
+                ("引擎".to_owned(), 2),
+                ("OctoBase".to_owned(), 3),
+                ("中".to_owned(), 4),
+                ("test".to_owned(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn word_only_filter() {
+        let mut stream = WordOnlyFilter.transform(
+            CangJieTokenizer {
+                worker: jieba(),
+                option: TokenizerOption::ForSearch { hmm: false },
+            }
+            .token_stream("搜索引擎, OctoBase！"),
+        );
+        let mut tokens = vec![];
+        while stream.advance() {
+            tokens.push(stream.token().text.clone());
+        }
+
+        assert!(tokens.contains(&"搜索".to_owned()));
+        assert!(tokens.contains(&"OctoBase".to_owned()));
+        assert!(tokens
+            .iter()
+            .all(|token| token.chars().any(char::is_alphanumeric)));
+    }
 }
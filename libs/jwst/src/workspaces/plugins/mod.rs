@@ -1,19 +1,34 @@
 #[cfg(feature = "workspace-search")]
 mod indexing;
+mod observer;
 mod plugin;
+mod references;
+#[cfg(feature = "workspace-vector-search")]
+mod vector;
 
 use super::*;
 
 #[cfg(feature = "workspace-search")]
 pub(super) use indexing::IndexingPluginImpl;
-pub(super) use plugin::{PluginImpl, PluginMap, PluginRegister};
+pub use observer::{BlockChange, BlockObserver};
+pub(super) use plugin::PluginMap;
+pub use plugin::{PluginImpl, PluginRegister};
+pub use references::PageLink;
+pub(super) use references::ReferencesPlugin;
+#[cfg(feature = "workspace-vector-search")]
+pub(super) use vector::VectorPlugin;
+#[cfg(feature = "workspace-vector-search")]
+pub use vector::{Embedder, HashEmbedder, VectorSearchResult};
 
 #[cfg(feature = "workspace-search")]
-pub use indexing::{SearchResult, SearchResults};
+pub use indexing::{
+    remove_search_index, search_index_dir, SearchError, SearchOptions, SearchQuery, SearchRange,
+    SearchResult, SearchResults,
+};
 
-/// Setup a [WorkspacePlugin] and insert it into the [Workspace].
-/// See [plugins].
-fn insert_plugin(
+/// Setup a [PluginImpl] and insert it into the [Workspace].
+/// See [plugins](self).
+pub(super) fn insert_plugin(
     mut workspace: Workspace,
     config: impl PluginRegister,
 ) -> Result<Workspace, Box<dyn std::error::Error>> {
@@ -23,13 +38,52 @@ fn insert_plugin(
     Ok(workspace)
 }
 
-/// Setup plugin: [indexing]
+/// Setup default plugins: [references], [indexing], [vector]
 pub(super) fn setup_plugin(workspace: Workspace) -> Workspace {
-    // default plugins
+    let workspace = insert_plugin(workspace, references::ReferencesPluginRegister)
+        .expect("Failed to setup references plugin");
+    setup_search_plugin(workspace)
+}
+
+/// Setup plugin: [vector] with the embedder of the previous one, or a [HashEmbedder] by default
+#[cfg(feature = "workspace-vector-search")]
+pub(super) fn setup_vector_plugin(
+    workspace: Workspace,
+    embedder: Option<std::sync::Arc<dyn Embedder>>,
+) -> Workspace {
+    let embedder = embedder
+        .or_else(|| workspace.with_plugin::<VectorPlugin, _>(|plugin| plugin.embedder.clone()))
+        .unwrap_or_else(|| std::sync::Arc::new(HashEmbedder::default()));
+    insert_plugin(workspace, vector::VectorPluginRegister::new(embedder))
+        .expect("Failed to setup vector search plugin")
+}
+
+/// Setup plugins: [indexing] and [vector], they will be rebuilt if the search metadata is changed
+pub(super) fn setup_search_plugin(workspace: Workspace) -> Workspace {
+    #[cfg(feature = "workspace-vector-search")]
+    let workspace = setup_vector_plugin(workspace, None);
+
     if cfg!(feature = "workspace-search") {
         // Set up indexing plugin
-        insert_plugin(workspace, indexing::IndexingPluginRegister::default())
-            .expect("Failed to setup search plugin")
+        let mut workspace = workspace;
+        let plugin = indexing::IndexingPluginRegister::from_workspace(&workspace)
+            .setup(&mut workspace)
+            .or_else(|e| {
+                // e.g. the index directory isn't writable, the index is rebuilt in memory
+                // so the workspace is still searchable, but it won't be persisted
+                error!(
+                    "failed to setup search index of {} in {:?}, it won't be persisted: {e}",
+                    workspace.id(),
+                    workspace.search_index_root()
+                );
+                indexing::IndexingPluginRegister::ram().setup(&mut workspace)
+            })
+            .expect("Failed to setup search plugin");
+        workspace
+            .plugins
+            .insert_plugin(plugin)
+            .expect("Failed to setup search plugin");
+        workspace
     } else {
         workspace
     }
@@ -0,0 +1,274 @@
+use super::*;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, RwLock,
+    },
+};
+use yrs::{
+    types::{Event, Events, PathSegment},
+    DeepObservable, MapRef, Subscription, TransactionMut, UpdateSubscription,
+};
+
+type SpaceSubscription = Subscription<Arc<dyn Fn(&TransactionMut, &Events)>>;
+
+/// A change of a block, reported by [BlockObserver::take_changes].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BlockChange {
+    /// The block is created or its content is changed.
+    Updated { space_id: String, block_id: String },
+    /// The block is removed.
+    Removed { space_id: String, block_id: String },
+}
+
+impl BlockChange {
+    pub fn space_id(&self) -> &str {
+        match self {
+            Self::Updated { space_id, .. } | Self::Removed { space_id, .. } => space_id,
+        }
+    }
+
+    pub fn block_id(&self) -> &str {
+        match self {
+            Self::Updated { block_id, .. } | Self::Removed { block_id, .. } => block_id,
+        }
+    }
+}
+
+/// Tracks the blocks changed in all spaces of a [Workspace], so a plugin can
+/// update the data it derives from blocks in [PluginImpl::on_update].
+///
+/// The observer should be created in [PluginRegister::setup] and owned by the plugin,
+/// the subscriptions are dropped with it.
+pub struct BlockObserver {
+    // number of workspace updates since the changes were taken
+    pending: Arc<AtomicU32>,
+    changed: Arc<RwLock<HashSet<(String, String)>>>,
+    space_subs: HashMap<String, SpaceSubscription>,
+    _update_sub: Option<UpdateSubscription>,
+}
+
+impl BlockObserver {
+    /// If `initial` is true, all existing blocks will be reported as updated by the first
+    /// [BlockObserver::take_changes], otherwise only the later changes are reported.
+    pub fn new(ws: &mut Workspace, initial: bool) -> Self {
+        let pending = Arc::new(AtomicU32::new(if initial { 1 } else { 0 }));
+
+        let pending_clone = pending.clone();
+        let sub = ws.observe(move |_trx, _e| {
+            pending_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut observer = Self {
+            pending,
+            changed: Arc::default(),
+            // needs to drop sub with everything else
+            space_subs: HashMap::new(),
+            _update_sub: sub,
+        };
+
+        if !initial {
+            for (space_id, blocks) in observer.unobserved_spaces(ws) {
+                observer.observe_space(space_id, blocks);
+            }
+        }
+
+        observer
+    }
+
+    /// Number of workspace updates whose changes are not taken yet.
+    pub fn pending(&self) -> u32 {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Whether there are changes not taken yet.
+    pub fn has_changes(&self) -> bool {
+        self.pending() > 0 || !self.changed.read().unwrap().is_empty()
+    }
+
+    /// Take the blocks changed since the last call, `None` if the workspace is not updated.
+    /// A block created in a new space is reported once the space is observed.
+    pub fn take_changes(&mut self, ws: &Workspace) -> Option<Vec<BlockChange>> {
+        let curr = self.pending();
+        if curr == 0 {
+            return None;
+        }
+
+        // spaces created since the last update are not observed yet,
+        // so all of their blocks are reported as updated
+        for (space_id, blocks) in self.unobserved_spaces(ws) {
+            self.observe_space(space_id.clone(), blocks);
+
+            let blocks = ws.with_trx(|t| {
+                t.get_exists_space(&space_id)
+                    .map(|space| {
+                        space.blocks(&t.trx, |blocks| {
+                            blocks.map(|block| block.block_id()).collect::<Vec<_>>()
+                        })
+                    })
+                    .unwrap_or_default()
+            });
+            self.changed.write().unwrap().extend(
+                blocks
+                    .into_iter()
+                    .map(|block_id| (space_id.clone(), block_id)),
+            );
+        }
+
+        let changed = std::mem::take(&mut *self.changed.write().unwrap());
+        let changes = ws.with_trx(|t| {
+            changed
+                .into_iter()
+                .map(|(space_id, block_id)| {
+                    let exists = t
+                        .get_exists_space(&space_id)
+                        .and_then(|space| space.get(&t.trx, &block_id))
+                        .is_some();
+                    if exists {
+                        BlockChange::Updated { space_id, block_id }
+                    } else {
+                        BlockChange::Removed { space_id, block_id }
+                    }
+                })
+                .collect()
+        });
+
+        // reset back down now that the changes were taken
+        self.pending.fetch_sub(curr, Ordering::SeqCst);
+
+        Some(changes)
+    }
+
+    fn unobserved_spaces(&self, ws: &Workspace) -> Vec<(String, MapRef)> {
+        ws.with_trx(|t| {
+            t.spaces(|spaces| {
+                spaces
+                    .filter(|space| !self.space_subs.contains_key(&space.space_id()))
+                    .map(|space| (space.space_id(), space.blocks.clone()))
+                    .collect()
+            })
+        })
+    }
+
+    // track the blocks changed in the space
+    fn observe_space(&mut self, space_id: String, mut blocks: MapRef) {
+        let changed = self.changed.clone();
+        let observed_space = space_id.clone();
+        let sub = blocks.observe_deep(move |trx, events| {
+            let mut changed = changed.write().unwrap();
+            for event in events.iter() {
+                let mut path = event.path();
+                match (path.pop_front(), event) {
+                    // block content changed
+                    (Some(PathSegment::Key(block_id)), _) => {
+                        changed.insert((observed_space.clone(), block_id.to_string()));
+                    }
+                    // block created or removed
+                    (None, Event::Map(event)) => {
+                        changed.extend(
+                            event
+                                .keys(trx)
+                                .keys()
+                                .map(|block_id| (observed_space.clone(), block_id.to_string())),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        });
+        self.space_subs.insert(space_id, sub);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct ChangesPlugin {
+        observer: Option<BlockObserver>,
+        changes: Vec<BlockChange>,
+    }
+
+    impl PluginImpl for ChangesPlugin {
+        fn on_update(&mut self, ws: &Workspace) -> Result<(), Box<dyn std::error::Error>> {
+            if let Some(changes) = self.observer.as_mut().and_then(|o| o.take_changes(ws)) {
+                self.changes = changes;
+                self.changes.sort_by(|a, b| a.block_id().cmp(b.block_id()));
+            }
+            Ok(())
+        }
+    }
+
+    struct ChangesPluginRegister;
+
+    impl PluginRegister for ChangesPluginRegister {
+        type Plugin = ChangesPlugin;
+
+        fn setup(self, ws: &mut Workspace) -> Result<Self::Plugin, Box<dyn std::error::Error>> {
+            Ok(ChangesPlugin {
+                observer: Some(BlockObserver::new(ws, true)),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn block_changes_test() {
+        let workspace = Workspace::new("wk-changes");
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.create(&mut t.trx, "a", "affine:text");
+            space.create(&mut t.trx, "b", "affine:text");
+        });
+        let workspace = workspace
+            .register_plugin(ChangesPluginRegister)
+            .expect("failed to register plugin");
+
+        let changes = |workspace: &Workspace| {
+            workspace
+                .update_plugin::<ChangesPlugin>()
+                .expect("failed to update plugin");
+            workspace
+                .with_plugin::<ChangesPlugin, _>(|plugin| plugin.changes.clone())
+                .unwrap()
+        };
+        let updated = |block_id: &str| BlockChange::Updated {
+            space_id: "space".into(),
+            block_id: block_id.into(),
+        };
+
+        // existing blocks are reported in the first update
+        assert_eq!(changes(&workspace), vec![updated("a"), updated("b")]);
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            let a = space.get(&t.trx, "a").unwrap();
+            a.set(&mut t.trx, "title", "apple");
+            space.remove(&mut t.trx, "b");
+            t.get_space("other").create(&mut t.trx, "c", "affine:text");
+        });
+        assert_eq!(
+            changes(&workspace),
+            vec![
+                updated("a"),
+                BlockChange::Removed {
+                    space_id: "space".into(),
+                    block_id: "b".into(),
+                },
+                BlockChange::Updated {
+                    space_id: "other".into(),
+                    block_id: "c".into(),
+                },
+            ]
+        );
+        assert!(!workspace
+            .with_plugin::<ChangesPlugin, _>(|plugin| plugin
+                .observer
+                .as_ref()
+                .unwrap()
+                .has_changes())
+            .unwrap());
+    }
+}
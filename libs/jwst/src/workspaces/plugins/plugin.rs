@@ -1,11 +1,15 @@
-//! Plugins are an internal experimental interface for extending the [Workspace].
+//! Plugins extend the [Workspace] with data derived from blocks, e.g. the search index.
+//!
+//! A plugin is created by its [PluginRegister] with [Workspace::register_plugin],
+//! tracks changes with a [BlockObserver](super::BlockObserver), and is queried by
+//! [Workspace::with_plugin] after [Workspace::update_plugin].
 
 use super::*;
 use std::sync::{Arc, RwLock};
 use type_map::TypeMap;
 
-/// A configuration from which a [WorkspacePlugin] can be created from.
-pub(crate) trait PluginRegister {
+/// A configuration from which a [PluginImpl] can be created from.
+pub trait PluginRegister {
     type Plugin: PluginImpl;
     // Do we need self?
     fn setup(self, ws: &mut Workspace) -> Result<Self::Plugin, Box<dyn std::error::Error>>;
@@ -13,10 +17,11 @@ pub(crate) trait PluginRegister {
     // -> Box<dyn FnMut(&mut Workspace)>;
 }
 
-/// A workspace plugin which comes from a corresponding [WorkspacePluginConfig::setup].
+/// A workspace plugin which comes from a corresponding [PluginRegister::setup].
 /// In that setup call, the plugin will have initial access to the whole [Workspace],
 /// and will be able to add listeners to changes to blocks in the [Workspace].
-pub(crate) trait PluginImpl: 'static {
+/// Only one plugin of each type can be registered in a [Workspace].
+pub trait PluginImpl: 'static {
     /// IDEA 1/10:
     /// This update is called sometime between when we know changes have been made to the workspace
     /// and the time when we will get the plugin to query its data (e.g. search())
@@ -0,0 +1,270 @@
+//! The reference graph between pages, a page is a [Space] of the [Workspace].
+//!
+//! A block links to a page or a block of the page by:
+//! - a `pageId` property and an optional `blockId` property, e.g. a linked page block
+//! - an inline `reference` attribute of rich text, a map with `pageId` and optional `blockId`
+
+use super::*;
+use crate::Block;
+use lib0::any::Any;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use yrs::ReadTxn;
+
+const PAGE_ID: &str = "pageId";
+const BLOCK_ID: &str = "blockId";
+const REFERENCE_ATTRIBUTE: &str = "reference";
+
+/// A link from a block to a page or a block of the page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub struct PageLink {
+    /// The space of the block containing the link.
+    pub space_id: String,
+    pub block_id: String,
+    /// The linked page, i.e. a space id.
+    pub target_page_id: String,
+    /// The linked block of the page, `None` if the whole page is linked.
+    pub target_block_id: Option<String>,
+}
+
+fn string_value(value: Option<&Any>) -> Option<String> {
+    match value {
+        Some(Any::String(value)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// links of the `pageId` property and the inline references of all rich text properties
+fn block_links<T: ReadTxn>(trx: &T, space_id: &str, block: &Block) -> Vec<PageLink> {
+    let mut targets = vec![];
+
+    if let Some(page_id) = string_value(block.get(trx, PAGE_ID).as_ref()) {
+        targets.push((page_id, string_value(block.get(trx, BLOCK_ID).as_ref())));
+    }
+
+    for key in block.content(trx).keys() {
+        for delta in block.text_delta(trx, key).unwrap_or_default() {
+            let reference = delta
+                .attributes
+                .as_ref()
+                .and_then(|attributes| attributes.get(REFERENCE_ATTRIBUTE));
+            if let Some(Any::Map(reference)) = reference {
+                if let Some(page_id) = string_value(reference.get(PAGE_ID)) {
+                    targets.push((page_id, string_value(reference.get(BLOCK_ID))));
+                }
+            }
+        }
+    }
+
+    targets.sort();
+    targets.dedup();
+    targets
+        .into_iter()
+        .map(|(target_page_id, target_block_id)| PageLink {
+            space_id: space_id.to_owned(),
+            block_id: block.block_id(),
+            target_page_id,
+            target_block_id,
+        })
+        .collect()
+}
+
+pub(crate) struct ReferencesPlugin {
+    observer: BlockObserver,
+    // links by the `(space_id, block_id)` of the source block
+    links: HashMap<(String, String), Vec<PageLink>>,
+    // source blocks by the linked page
+    backlinks: HashMap<String, HashSet<(String, String)>>,
+}
+
+impl ReferencesPlugin {
+    /// Links from the blocks of the page.
+    pub fn references(&self, page_id: &str) -> Vec<PageLink> {
+        let mut links = self
+            .links
+            .iter()
+            .filter(|((space_id, _), _)| space_id == page_id)
+            .flat_map(|(_, links)| links.iter().cloned())
+            .collect::<Vec<_>>();
+        links.sort();
+        links
+    }
+
+    /// Links to the page or the blocks of the page.
+    pub fn backlinks(&self, page_id: &str) -> Vec<PageLink> {
+        let mut links = self
+            .backlinks
+            .get(page_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|source| self.links.get(source))
+            .flatten()
+            .filter(|link| link.target_page_id == page_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        links.sort();
+        links
+    }
+
+    /// All links of the workspace.
+    pub fn links(&self) -> Vec<PageLink> {
+        let mut links = self.links.values().flatten().cloned().collect::<Vec<_>>();
+        links.sort();
+        links
+    }
+
+    fn remove_links(&mut self, source: &(String, String)) {
+        for link in self.links.remove(source).unwrap_or_default() {
+            if let Some(sources) = self.backlinks.get_mut(&link.target_page_id) {
+                sources.remove(source);
+                if sources.is_empty() {
+                    self.backlinks.remove(&link.target_page_id);
+                }
+            }
+        }
+    }
+
+    fn insert_links(&mut self, source: (String, String), links: Vec<PageLink>) {
+        if links.is_empty() {
+            return;
+        }
+        for link in &links {
+            self.backlinks
+                .entry(link.target_page_id.clone())
+                .or_default()
+                .insert(source.clone());
+        }
+        self.links.insert(source, links);
+    }
+}
+
+impl PluginImpl for ReferencesPlugin {
+    fn on_update(&mut self, ws: &Workspace) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(changes) = self.observer.take_changes(ws) {
+            let updated = ws.with_trx(|t| {
+                changes
+                    .iter()
+                    .map(|change| {
+                        let source = (change.space_id().to_owned(), change.block_id().to_owned());
+                        let links = match change {
+                            BlockChange::Updated { space_id, block_id } => t
+                                .get_exists_space(space_id)
+                                .and_then(|space| space.get(&t.trx, block_id))
+                                .map(|block| block_links(&t.trx, space_id, &block))
+                                .unwrap_or_default(),
+                            BlockChange::Removed { .. } => vec![],
+                        };
+                        (source, links)
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            for (source, links) in updated {
+                self.remove_links(&source);
+                self.insert_links(source, links);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct ReferencesPluginRegister;
+
+impl PluginRegister for ReferencesPluginRegister {
+    type Plugin = ReferencesPlugin;
+
+    fn setup(self, ws: &mut Workspace) -> Result<Self::Plugin, Box<dyn std::error::Error>> {
+        Ok(ReferencesPlugin {
+            // all blocks are scanned in the first update
+            observer: BlockObserver::new(ws, true),
+            links: HashMap::new(),
+            backlinks: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn link(space_id: &str, block_id: &str, page_id: &str, target: Option<&str>) -> PageLink {
+        PageLink {
+            space_id: space_id.to_owned(),
+            block_id: block_id.to_owned(),
+            target_page_id: page_id.to_owned(),
+            target_block_id: target.map(|target| target.to_owned()),
+        }
+    }
+
+    #[test]
+    fn page_references_test() {
+        let workspace = Workspace::new("wk-references");
+        workspace.with_trx(|mut t| {
+            let page1 = t.get_space("page1");
+            let page2 = t.get_space("page2");
+
+            page2.create(&mut t.trx, "target", "affine:paragraph");
+            let linked = page1.create(&mut t.trx, "linked", "affine:linked-page");
+            linked.set(&mut t.trx, PAGE_ID, "page2".to_owned());
+
+            let text = page1.create(&mut t.trx, "text", "affine:paragraph");
+            text.text_insert(&mut t.trx, "text", 0, "see ");
+            text.text_insert_with_format(
+                &mut t.trx,
+                "text",
+                4,
+                "target",
+                HashMap::from([(
+                    REFERENCE_ATTRIBUTE.to_owned(),
+                    Any::Map(Box::new(HashMap::from([
+                        (PAGE_ID.to_owned(), Any::String("page2".into())),
+                        (BLOCK_ID.to_owned(), Any::String("target".into())),
+                    ]))),
+                )]),
+            );
+        });
+
+        assert_eq!(
+            workspace.page_references("page1"),
+            vec![
+                link("page1", "linked", "page2", None),
+                link("page1", "text", "page2", Some("target")),
+            ]
+        );
+        assert_eq!(
+            workspace.page_backlinks("page2"),
+            workspace.page_references("page1")
+        );
+        assert!(workspace.page_backlinks("page1").is_empty());
+        assert!(workspace.broken_links().is_empty());
+
+        // links are updated incrementally
+        workspace.with_trx(|mut t| {
+            let page1 = t.get_space("page1");
+            let page2 = t.get_space("page2");
+            let linked = page1.get(&t.trx, "linked").unwrap();
+            linked.set(&mut t.trx, PAGE_ID, "page3".to_owned());
+            page2.remove(&mut t.trx, "target");
+        });
+
+        assert_eq!(
+            workspace.page_backlinks("page2"),
+            vec![link("page1", "text", "page2", Some("target"))]
+        );
+        assert_eq!(
+            workspace.broken_links(),
+            vec![
+                link("page1", "linked", "page3", None),
+                link("page1", "text", "page2", Some("target")),
+            ]
+        );
+
+        workspace.with_trx(|mut t| {
+            let page1 = t.get_space("page1");
+            page1.remove(&mut t.trx, "text");
+        });
+        assert!(workspace.page_backlinks("page2").is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+//! Semantic search over the text of blocks by the embeddings of a pluggable [Embedder],
+//! the nearest neighbors are found by cosine similarity without any external service.
+
+use super::*;
+use lib0::any::Any;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use utoipa::ToSchema;
+
+/// Converts text into a vector, similar texts should have similar vectors.
+pub trait Embedder: Send + Sync {
+    /// The length of the vectors.
+    fn dimensions(&self) -> usize;
+    /// The vector of the text, its length must be [Embedder::dimensions].
+    fn embed(&self, text: &str) -> Vec<f32>;
+}
+
+/// A deterministic local embedder by hashing words and their character trigrams,
+/// it captures lexical similarity only but needs no model.
+#[derive(Debug, Clone)]
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl HashEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    // FNV-1a, stable across platforms and rust versions unlike the std hasher
+    fn hash(token: &str) -> u64 {
+        token.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn add(&self, vector: &mut [f32], token: &str, weight: f32) {
+        let hash = Self::hash(token);
+        let index = (hash % self.dimensions as u64) as usize;
+        // the sign reduces the bias of hash collisions
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[index] += sign * weight;
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Embedder for HashEmbedder {
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let word = word.to_lowercase();
+            self.add(&mut vector, &word, 1.0);
+
+            let chars = format!("^{word}$").chars().collect::<Vec<_>>();
+            for trigram in chars.windows(3) {
+                self.add(&mut vector, &trigram.iter().collect::<String>(), 0.5);
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+// cosine similarity of normalized vectors
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Returned from [`Workspace::vector_search`] and [`Workspace::related_blocks`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VectorSearchResult {
+    pub space_id: String,
+    pub block_id: String,
+    /// Cosine similarity between -1 and 1, higher is more similar.
+    pub score: f32,
+}
+
+pub(crate) struct VectorPlugin {
+    observer: BlockObserver,
+    pub(super) embedder: Arc<dyn Embedder>,
+    // fields of the block text, same as the search index
+    fields: Vec<String>,
+    // normalized vectors by `(space_id, block_id)`, blocks without text are skipped
+    vectors: HashMap<(String, String), Vec<f32>>,
+}
+
+impl VectorPlugin {
+    /// Blocks most similar to the text.
+    pub fn search(&self, text: &str, limit: usize) -> Vec<VectorSearchResult> {
+        let mut vector = self.embedder.embed(text);
+        normalize(&mut vector);
+        self.nearest(&vector, limit, |_| true)
+    }
+
+    /// Blocks most similar to the block, excluding itself.
+    pub fn related(&self, space_id: &str, block_id: &str, limit: usize) -> Vec<VectorSearchResult> {
+        let source = (space_id.to_owned(), block_id.to_owned());
+        match self.vectors.get(&source) {
+            Some(vector) => self.nearest(vector, limit, |key| key != &source),
+            None => vec![],
+        }
+    }
+
+    fn nearest(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: impl Fn(&(String, String)) -> bool,
+    ) -> Vec<VectorSearchResult> {
+        let mut results = self
+            .vectors
+            .iter()
+            .filter(|(key, _)| filter(key))
+            .map(|((space_id, block_id), block_vector)| VectorSearchResult {
+                space_id: space_id.clone(),
+                block_id: block_id.clone(),
+                score: similarity(vector, block_vector),
+            })
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| (&a.space_id, &a.block_id).cmp(&(&b.space_id, &b.block_id)))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+impl PluginImpl for VectorPlugin {
+    fn on_update(&mut self, ws: &Workspace) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(changes) = self.observer.take_changes(ws) {
+            let texts = ws.with_trx(|t| {
+                changes
+                    .iter()
+                    .map(|change| {
+                        let key = (change.space_id().to_owned(), change.block_id().to_owned());
+                        let text = match change {
+                            BlockChange::Updated { space_id, block_id } => t
+                                .get_exists_space(space_id)
+                                .and_then(|space| space.get(&t.trx, block_id))
+                                .map(|block| {
+                                    let content = block.content(&t.trx);
+                                    self.fields
+                                        .iter()
+                                        .filter_map(|field| match content.get(field) {
+                                            Some(Any::String(text)) => Some(text.to_string()),
+                                            _ => None,
+                                        })
+                                        .collect::<Vec<_>>()
+                                        .join("\n")
+                                }),
+                            BlockChange::Removed { .. } => None,
+                        };
+                        (key, text)
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            for (key, text) in texts {
+                match text.filter(|text| !text.trim().is_empty()) {
+                    Some(text) => {
+                        let mut vector = self.embedder.embed(&text);
+                        // the changes are taken already, so only the invalid block is skipped
+                        if vector.len() != self.embedder.dimensions() {
+                            warn!(
+                                "skip embedding of block {}: {} dimensions, expected {}",
+                                key.1,
+                                vector.len(),
+                                self.embedder.dimensions()
+                            );
+                            self.vectors.remove(&key);
+                            continue;
+                        }
+                        normalize(&mut vector);
+                        self.vectors.insert(key, vector);
+                    }
+                    None => {
+                        self.vectors.remove(&key);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct VectorPluginRegister {
+    embedder: Arc<dyn Embedder>,
+}
+
+impl VectorPluginRegister {
+    pub(crate) fn new(embedder: Arc<dyn Embedder>) -> Self {
+        Self { embedder }
+    }
+}
+
+impl PluginRegister for VectorPluginRegister {
+    type Plugin = VectorPlugin;
+
+    fn setup(self, ws: &mut Workspace) -> Result<Self::Plugin, Box<dyn std::error::Error>> {
+        Ok(VectorPlugin {
+            // all blocks are embedded in the first update
+            observer: BlockObserver::new(ws, true),
+            embedder: self.embedder,
+            fields: ws.metadata().search_index,
+            vectors: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hash_embedder_test() {
+        let embedder = HashEmbedder::default();
+        let vector = embedder.embed("Hello World");
+        assert_eq!(vector.len(), embedder.dimensions());
+        assert_eq!(vector, embedder.embed("hello, world!"));
+        assert!((similarity(&vector, &vector) - 1.0).abs() < 1e-5);
+        assert!(embedder.embed("").iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn vector_search_test() {
+        let workspace = Workspace::new("wk-vector");
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            for (id, title) in [
+                ("a", "rust programming language"),
+                ("b", "cooking pasta recipe"),
+                ("c", "programming the rust compiler"),
+                ("d", ""),
+            ] {
+                let block = space.create(&mut t.trx, id, "affine:text");
+                block.set(&mut t.trx, "title", title.to_owned());
+            }
+        });
+
+        let ids = |results: Vec<VectorSearchResult>| {
+            results
+                .into_iter()
+                .map(|result| result.block_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(workspace.vector_search("rust programming", 2)),
+            vec!["a", "c"]
+        );
+        assert_eq!(ids(workspace.vector_search("pasta", 1)), vec!["b"]);
+        assert_eq!(ids(workspace.related_blocks("space", "a", 1)), vec!["c"]);
+        // blocks without text are not embedded
+        assert!(workspace.related_blocks("space", "d", 1).is_empty());
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            space.remove(&mut t.trx, "c");
+        });
+        assert_eq!(ids(workspace.related_blocks("space", "a", 3)), vec!["b"]);
+    }
+
+    // returns a vector of the wrong length for the text containing "invalid"
+    struct InvalidEmbedder(HashEmbedder);
+
+    impl Embedder for InvalidEmbedder {
+        fn dimensions(&self) -> usize {
+            self.0.dimensions()
+        }
+
+        fn embed(&self, text: &str) -> Vec<f32> {
+            let mut vector = self.0.embed(text);
+            if text.contains("invalid") {
+                vector.pop();
+            }
+            vector
+        }
+    }
+
+    #[test]
+    fn vector_invalid_embedding_test() {
+        let workspace = Workspace::new("wk-vector-invalid");
+        workspace.set_embedder(Arc::new(InvalidEmbedder(HashEmbedder::default())));
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+            for (id, title) in [
+                ("a", "rust programming"),
+                ("b", "invalid rust"),
+                ("c", "rust compiler"),
+            ] {
+                let block = space.create(&mut t.trx, id, "affine:text");
+                block.set(&mut t.trx, "title", title.to_owned());
+            }
+        });
+
+        // the other blocks of the same update are still embedded
+        let ids = workspace
+            .vector_search("rust", 3)
+            .into_iter()
+            .map(|result| result.block_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&"b".to_owned()));
+    }
+}
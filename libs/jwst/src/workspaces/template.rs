@@ -0,0 +1,224 @@
+use super::*;
+use crate::Block;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use yrs::TransactionMut;
+
+// space id of `space:templates`
+pub(super) const TEMPLATE_SPACE: &str = "templates";
+
+/// A named block subtree which can be instantiated into any space of the workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Template {
+    pub name: String,
+    /// root block id of the template in the template space
+    pub block_id: String,
+    pub flavor: String,
+    pub created: u64,
+}
+
+impl Template {
+    fn new(trx: &TransactionMut, block: &Block) -> Option<Self> {
+        block.template_name(trx).map(|name| Self {
+            name,
+            block_id: block.block_id(),
+            flavor: block.flavor(trx),
+            created: block.created(trx),
+        })
+    }
+}
+
+impl WorkspaceTransaction<'_> {
+    fn get_template_block(&self, name: &str) -> Option<Block> {
+        self.get_exists_space(TEMPLATE_SPACE).and_then(|space| {
+            space.blocks(&self.trx, |mut blocks| {
+                blocks.find(|block| block.template_name(&self.trx).as_deref() == Some(name))
+            })
+        })
+    }
+
+    /// List all templates of the workspace, sorted by name.
+    pub fn templates(&self) -> Vec<Template> {
+        let mut templates = self
+            .get_exists_space(TEMPLATE_SPACE)
+            .map(|space| {
+                space.blocks(&self.trx, |blocks| {
+                    blocks
+                        .filter_map(|block| Template::new(&self.trx, &block))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .unwrap_or_default();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        templates
+    }
+
+    /// Get a template by name.
+    pub fn get_template(&self, name: &str) -> Option<Template> {
+        self.get_template_block(name)
+            .and_then(|block| Template::new(&self.trx, &block))
+    }
+
+    /// Save the block and its descendants as a template, the template with the same name
+    /// will be replaced. Return `None` if the block belongs to another workspace.
+    pub fn save_template(&mut self, name: &str, block: &Block) -> Option<Template> {
+        // the previous template is kept if the block can't be saved
+        let previous = self.get_template_block(name);
+
+        let space = self.get_space(TEMPLATE_SPACE);
+        let template = block.save_as_template(&mut self.trx, &space, name)?;
+        if let Some(previous) = previous {
+            self.remove_template_block(&space, &previous);
+        }
+        info!(
+            "save template: {}, root block: {}",
+            name,
+            template.block_id()
+        );
+
+        Template::new(&self.trx, &template)
+    }
+
+    /// Remove the template and all of its blocks, return false if the template not exists.
+    pub fn remove_template(&mut self, name: &str) -> bool {
+        let Some(block) = self.get_template_block(name) else {
+            return false;
+        };
+        let Some(space) = self.get_exists_space(TEMPLATE_SPACE) else {
+            return false;
+        };
+
+        info!("remove template: {}", name);
+        self.remove_template_block(&space, &block);
+
+        true
+    }
+
+    fn remove_template_block(&mut self, space: &Space, block: &Block) {
+        for block_id in block.subtree(&self.trx, space) {
+            space.remove(&mut self.trx, block_id);
+        }
+    }
+
+    /// Create a copy of the template into `space` with new block ids, `{{name}}` placeholders
+    /// in string properties and rich texts will be replaced by `variables`.
+    /// The copy will be inserted into `parent` if given, at `pos` or appended.
+    ///
+    /// Return `None` if the template not exists, or the space or parent belongs to another workspace.
+    pub fn instantiate_template(
+        &mut self,
+        name: &str,
+        space: &Space,
+        parent: Option<&Block>,
+        pos: Option<u32>,
+        variables: &HashMap<String, String>,
+    ) -> Option<Block> {
+        let template = self.get_template_block(name)?;
+        template.instantiate(&mut self.trx, space, parent, pos, variables)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lib0::any::Any;
+
+    #[test]
+    fn template() {
+        let workspace = Workspace::new("test");
+
+        workspace.with_trx(|mut t| {
+            let space = t.get_space("space");
+
+            let page = space.create(&mut t.trx, "page", "affine:page");
+            let text = space.create(&mut t.trx, "text", "affine:paragraph");
+            page.push_children(&mut t.trx, &text);
+            page.set(&mut t.trx, "title", "Daily {{date}}");
+            text.text_insert(&mut t.trx, "text", 0, "Todo of {{date}} by {{author}}");
+            let authors = |author: &str| {
+                Any::Map(Box::new(HashMap::from([(
+                    "authors".to_owned(),
+                    Any::Array(Box::new([Any::String(author.into())])),
+                )])))
+            };
+            page.set(&mut t.trx, "meta", authors("{{author}}"));
+
+            let template = t.save_template("daily", &page).unwrap();
+            assert_eq!(template.name, "daily");
+            assert_eq!(template.flavor, "affine:page");
+            assert_eq!(t.templates(), vec![template.clone()]);
+            assert_eq!(t.get_template("daily"), Some(template));
+
+            // templates are not listed as normal spaces
+            assert_eq!(t.spaces(|spaces| spaces.count()), 1);
+            assert!(WorkspaceTransaction::is_reserved_space(TEMPLATE_SPACE));
+            assert!(!WorkspaceTransaction::is_reserved_space("space"));
+
+            let variables = HashMap::from([
+                ("date".to_owned(), "2023-03-01".to_owned()),
+                ("author".to_owned(), "alice".to_owned()),
+            ]);
+            let root = space.create(&mut t.trx, "root", "affine:page");
+            let page = t
+                .instantiate_template("daily", &space, Some(&root), None, &variables)
+                .unwrap();
+
+            assert_eq!(root.children(&t.trx), vec![page.block_id()]);
+            assert_eq!(page.template_name(&t.trx), None);
+            assert_eq!(
+                page.get(&t.trx, "title").unwrap().to_string(),
+                "Daily 2023-03-01"
+            );
+            // strings nested in properties are substituted too
+            assert_eq!(page.get(&t.trx, "meta"), Some(authors("alice")));
+
+            let text = space.get(&t.trx, &page.children(&t.trx)[0]).unwrap();
+            assert_eq!(
+                text.get(&t.trx, "text").unwrap().to_string(),
+                "Todo of 2023-03-01 by alice"
+            );
+
+            // the values are substituted once, without expanding the placeholders in them
+            let variables = HashMap::from([
+                ("date".to_owned(), "{{author}}".to_owned()),
+                ("author".to_owned(), "bob".to_owned()),
+            ]);
+            let page = t
+                .instantiate_template("daily", &space, None, None, &variables)
+                .unwrap();
+            assert_eq!(
+                page.get(&t.trx, "title").unwrap().to_string(),
+                "Daily {{author}}"
+            );
+            let text = space.get(&t.trx, &page.children(&t.trx)[0]).unwrap();
+            assert_eq!(
+                text.get(&t.trx, "text").unwrap().to_string(),
+                "Todo of {{author}} by bob"
+            );
+
+            // the template is replaced by the one with the same name
+            let template = t.save_template("daily", &text).unwrap();
+            assert_eq!(template.flavor, "affine:paragraph");
+            assert_eq!(t.templates(), vec![template.clone()]);
+
+            // and kept if the block can't be saved
+            let other = Workspace::new("other");
+            let block = other.with_trx(|mut t| {
+                let space = t.get_space("space");
+                space.create(&mut t.trx, "block", "affine:page")
+            });
+            assert_eq!(t.save_template("daily", &block), None);
+            assert_eq!(t.templates(), vec![template]);
+
+            assert!(t.remove_template("daily"));
+            assert!(!t.remove_template("daily"));
+            assert!(t.templates().is_empty());
+            assert_eq!(
+                t.instantiate_template("daily", &space, None, None, &variables),
+                None
+            );
+        });
+    }
+}
@@ -1,8 +1,9 @@
 use crate::utils::JS_INT_RANGE;
 
-use super::*;
+use super::{template::TEMPLATE_SPACE, *};
+use crate::IntegrityReport;
 use lib0::any::Any;
-use yrs::{Map, ReadTxn, TransactionMut};
+use yrs::{Doc, Map, ReadTxn, TransactionMut, Value};
 
 pub struct WorkspaceTransaction<'a> {
     pub ws: &'a Workspace,
@@ -11,13 +12,24 @@ pub struct WorkspaceTransaction<'a> {
 
 unsafe impl Send for WorkspaceTransaction<'_> {}
 
-const RESERVE_SPACE: [&str; 2] = [constants::space::META, constants::space::UPDATED];
+const RESERVE_SPACE: [&str; 4] = [
+    constants::space::META,
+    constants::space::UPDATED,
+    constants::space::TEMPLATES,
+    constants::space::SUBDOCS,
+];
 
 impl WorkspaceTransaction<'_> {
     pub fn get_space<S: AsRef<str>>(&mut self, space_id: S) -> Space {
         Space::new(&mut self.trx, self.ws.doc(), self.ws.id(), space_id)
     }
 
+    /// Whether the space id is used by the workspace itself, e.g. `templates`,
+    /// such a space shouldn't be created or written as a normal space.
+    pub fn is_reserved_space<S: AsRef<str>>(space_id: S) -> bool {
+        RESERVE_SPACE.contains(&format!("space:{}", space_id.as_ref()).as_str())
+    }
+
     pub fn get_exists_space<S: AsRef<str>>(&self, space_id: S) -> Option<Space> {
         Space::from_exists(&self.trx, self.ws.doc(), self.ws.id(), space_id)
     }
@@ -41,6 +53,65 @@ impl WorkspaceTransaction<'_> {
         cb(Box::new(iterator))
     }
 
+    /// Create the subdocument of the space if not exists, the space in the subdocument
+    /// is synced and stored by the guid of the subdocument, so it can be loaded on demand
+    /// instead of with the workspace.
+    pub fn create_subdoc<S: AsRef<str>>(&mut self, space_id: S) -> Doc {
+        if let Some(doc) = self.get_subdoc(&space_id) {
+            return doc;
+        }
+
+        let store = self.trx.store_mut();
+        let subdocs = self
+            .ws
+            .doc()
+            .get_or_insert_map_with_trx(store, constants::space::SUBDOCS);
+        subdocs.insert(&mut self.trx, space_id.as_ref(), Doc::new())
+    }
+
+    pub fn get_subdoc<S: AsRef<str>>(&self, space_id: S) -> Option<Doc> {
+        match self
+            .trx
+            .get_map(constants::space::SUBDOCS)?
+            .get(&self.trx, space_id.as_ref())
+        {
+            Some(Value::YDoc(doc)) => Some(doc),
+            _ => None,
+        }
+    }
+
+    /// The ids of spaces with subdocuments and the guids of their subdocuments.
+    pub fn subdocs(&self) -> Vec<(String, String)> {
+        let Some(subdocs) = self.trx.get_map(constants::space::SUBDOCS) else {
+            return vec![];
+        };
+        subdocs
+            .iter(&self.trx)
+            .filter_map(|(space_id, value)| match value {
+                Value::YDoc(doc) => Some((space_id.to_owned(), doc.guid().to_string())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Check the block tree of all spaces including templates, see [Space::check_integrity].
+    /// The issues will be repaired if `repair` is true, see [Space::repair_integrity].
+    pub fn check_integrity(&mut self, repair: bool) -> Vec<IntegrityReport> {
+        let mut spaces = self.spaces(|spaces| spaces.collect::<Vec<_>>());
+        spaces.extend(self.get_exists_space(TEMPLATE_SPACE));
+
+        spaces
+            .iter()
+            .map(|space| {
+                if repair {
+                    space.repair_integrity(&mut self.trx)
+                } else {
+                    space.check_integrity(&self.trx)
+                }
+            })
+            .collect()
+    }
+
     pub fn set_metadata(&mut self, key: &str, value: impl Into<Any>) {
         info!("set metadata: {}", key);
         let key = key.to_string();
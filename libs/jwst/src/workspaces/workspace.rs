@@ -1,7 +1,14 @@
-use super::{metadata::SEARCH_INDEX, plugins::setup_plugin, *};
+use super::{
+    metadata::{SEARCH_ANALYZER, SEARCH_FIELDS, SEARCH_INDEX},
+    plugins::{insert_plugin, setup_plugin, setup_search_plugin, ReferencesPlugin},
+    template::TEMPLATE_SPACE,
+    *,
+};
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use std::{
+    collections::BTreeMap,
     panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
     sync::Arc,
     thread::sleep,
     time::Duration,
@@ -24,7 +31,7 @@ use yrs::{
 static PROTOCOL: DefaultProtocol = DefaultProtocol;
 
 use super::PluginMap;
-use plugins::PluginImpl;
+use plugins::{PluginImpl, PluginRegister};
 
 pub type MapSubscription = Subscription<Arc<dyn Fn(&TransactionMut, &MapEvent)>>;
 
@@ -38,9 +45,10 @@ pub struct Workspace {
     /// This enables us to properly manage lifetimes of observers which will subscribe
     /// into events that the [Workspace] experiences, like block updates.
     ///
-    /// Public just for the crate as we experiment with the plugins interface.
-    /// See [plugins].
+    /// See [PluginRegister].
     pub(super) plugins: PluginMap,
+    /// The root directory of the persisted search indexes, the index is stored in memory if not set.
+    search_index_root: Option<PathBuf>,
 }
 
 unsafe impl Send for Workspace {}
@@ -53,6 +61,16 @@ impl Workspace {
     }
 
     pub fn from_doc<S: AsRef<str>>(doc: Doc, id: S) -> Workspace {
+        Self::from_doc_with_search_index(doc, id, None)
+    }
+
+    /// Like [Workspace::from_doc], but persist the search index in a sub directory of
+    /// `index_root`, so it can be reused after restart.
+    pub fn from_doc_with_search_index<S: AsRef<str>>(
+        doc: Doc,
+        id: S,
+        index_root: Option<&Path>,
+    ) -> Workspace {
         let updated = doc.get_or_insert_map("space:updated");
         let metadata = doc.get_or_insert_map("space:meta");
 
@@ -63,6 +81,7 @@ impl Workspace {
             updated,
             metadata,
             plugins: Default::default(),
+            search_index_root: index_root.map(Path::to_path_buf),
         })
     }
 
@@ -73,6 +92,7 @@ impl Workspace {
         updated: MapRef,
         metadata: MapRef,
         plugins: PluginMap,
+        search_index_root: Option<PathBuf>,
     ) -> Workspace {
         Self {
             id: id.as_ref().to_string(),
@@ -81,38 +101,139 @@ impl Workspace {
             updated,
             metadata,
             plugins,
+            search_index_root,
         }
     }
 
+    /// The root directory of the persisted search indexes, `None` if the index is stored in memory.
+    pub fn search_index_root(&self) -> Option<&Path> {
+        self.search_index_root.as_deref()
+    }
+
+    /// Setup the plugin of the register and insert it into the workspace,
+    /// a registered plugin of the same type will be replaced.
+    /// The plugin is shared by the clones of the workspace.
+    pub fn register_plugin(
+        self,
+        register: impl PluginRegister,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        insert_plugin(self, register)
+    }
+
     /// Allow the plugin to run any necessary updates it could have flagged via observers.
-    /// See [plugins].
-    pub(super) fn update_plugin<P: PluginImpl>(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// See [PluginImpl::on_update].
+    pub fn update_plugin<P: PluginImpl>(&self) -> Result<(), Box<dyn std::error::Error>> {
         self.plugins.update_plugin::<P>(self)
     }
 
-    /// See [plugins].
-    pub(super) fn with_plugin<P: PluginImpl, T>(&self, cb: impl Fn(&P) -> T) -> Option<T> {
+    /// Query the state of the plugin, `None` if the plugin is not registered.
+    /// Call [Workspace::update_plugin] first to apply the latest changes.
+    pub fn with_plugin<P: PluginImpl, T>(&self, cb: impl Fn(&P) -> T) -> Option<T> {
         self.plugins.with_plugin::<P, T>(cb)
     }
 
+    /// Search blocks by a query string or a structured [SearchQuery].
     #[cfg(feature = "workspace-search")]
-    pub fn search<S: AsRef<str>>(
+    pub fn search(&self, query: impl Into<SearchQuery>) -> Result<SearchResults, SearchError> {
+        self.search_with_options(query, &SearchOptions::default())
+    }
+
+    /// Search blocks with pagination and filters, see [SearchOptions].
+    #[cfg(feature = "workspace-search")]
+    pub fn search_with_options(
         &self,
-        query: S,
-    ) -> Result<SearchResults, Box<dyn std::error::Error>> {
+        query: impl Into<SearchQuery>,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, SearchError> {
         use plugins::IndexingPluginImpl;
 
         // refresh index if doc has update
-        self.update_plugin::<IndexingPluginImpl>()?;
+        self.update_plugin::<IndexingPluginImpl>()
+            .map_err(|err| SearchError::Update(err.to_string()))?;
 
-        let query = query.as_ref();
+        let query = query.into();
 
-        self.with_plugin::<IndexingPluginImpl, Result<SearchResults, Box<dyn std::error::Error>>>(
-            |search_plugin| search_plugin.search(query),
+        self.with_plugin::<IndexingPluginImpl, Result<SearchResults, SearchError>>(
+            |search_plugin| search_plugin.search_with_options(query.clone(), options),
         )
         .expect("text search was set up by default")
     }
 
+    /// Blocks whose text is the most similar to the text, by the embeddings of [Embedder].
+    #[cfg(feature = "workspace-vector-search")]
+    pub fn vector_search(&self, text: &str, limit: usize) -> Vec<VectorSearchResult> {
+        self.with_vectors(|plugin| plugin.search(text, limit))
+    }
+
+    /// Blocks whose text is the most similar to the block, i.e. "find related notes".
+    #[cfg(feature = "workspace-vector-search")]
+    pub fn related_blocks(
+        &self,
+        space_id: &str,
+        block_id: &str,
+        limit: usize,
+    ) -> Vec<VectorSearchResult> {
+        self.with_vectors(|plugin| plugin.related(space_id, block_id, limit))
+    }
+
+    /// Change the embedder of vector search, the blocks will be embedded again.
+    #[cfg(feature = "workspace-vector-search")]
+    pub fn set_embedder(&self, embedder: std::sync::Arc<dyn Embedder>) {
+        plugins::setup_vector_plugin(self.clone(), Some(embedder));
+    }
+
+    #[cfg(feature = "workspace-vector-search")]
+    fn with_vectors(
+        &self,
+        cb: impl Fn(&plugins::VectorPlugin) -> Vec<VectorSearchResult>,
+    ) -> Vec<VectorSearchResult> {
+        use plugins::VectorPlugin;
+
+        // refresh vectors if doc has update
+        if let Err(err) = self.update_plugin::<VectorPlugin>() {
+            error!("failed to update vector search: {}", err);
+        }
+        self.with_plugin::<VectorPlugin, _>(cb)
+            .expect("vector search was set up by default")
+    }
+
+    /// Links from the blocks of the page to other pages or blocks, see [PageLink].
+    pub fn page_references(&self, page_id: &str) -> Vec<PageLink> {
+        self.with_references(|plugin| plugin.references(page_id))
+    }
+
+    /// Links from any block to the page or the blocks of the page, i.e. "what links here".
+    pub fn page_backlinks(&self, page_id: &str) -> Vec<PageLink> {
+        self.with_references(|plugin| plugin.backlinks(page_id))
+    }
+
+    /// Links whose target page or block does not exist.
+    pub fn broken_links(&self) -> Vec<PageLink> {
+        let links = self.with_references(|plugin| plugin.links());
+        self.with_trx(|t| {
+            links
+                .into_iter()
+                .filter(|link| {
+                    let target = t.get_exists_space(&link.target_page_id);
+                    match (target, &link.target_block_id) {
+                        (Some(space), Some(block_id)) => !space.exists(&t.trx, block_id),
+                        (Some(_), None) => false,
+                        (None, _) => true,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn with_references(&self, cb: impl Fn(&ReferencesPlugin) -> Vec<PageLink>) -> Vec<PageLink> {
+        // refresh references if doc has update
+        if let Err(err) = self.update_plugin::<ReferencesPlugin>() {
+            error!("failed to update references: {}", err);
+        }
+        self.with_plugin::<ReferencesPlugin, _>(cb)
+            .expect("references was set up by default")
+    }
+
     pub fn search_result(&self, query: String) -> String {
         match self.search(query) {
             Ok(list) => serde_json::to_string(&list).unwrap(),
@@ -129,12 +250,32 @@ impl Workspace {
             None => {
                 let value = serde_json::to_string(&fields).unwrap();
                 self.with_trx(|mut trx| trx.set_metadata(SEARCH_INDEX, value));
-                setup_plugin(self.clone());
+                setup_search_plugin(self.clone());
                 true
             }
         }
     }
 
+    /// Index the typed block properties besides the text fields of search index,
+    /// so they can be filtered by [SearchOptions], the blocks will be re-indexed.
+    pub fn set_search_fields(&self, fields: BTreeMap<String, SearchFieldType>) -> bool {
+        if fields.keys().any(|name| name.is_empty()) {
+            error!("field name cannot be empty");
+            return false;
+        }
+
+        let value = serde_json::to_string(&fields).unwrap();
+        self.with_trx(|mut trx| trx.set_metadata(SEARCH_FIELDS, value));
+        setup_search_plugin(self.clone());
+        true
+    }
+
+    /// Change the analyzer of search, the blocks will be re-indexed.
+    pub fn set_search_analyzer(&self, analyzer: SearchAnalyzer) {
+        self.with_trx(|mut trx| trx.set_metadata(SEARCH_ANALYZER, analyzer.as_str().to_owned()));
+        setup_search_plugin(self.clone());
+    }
+
     pub fn with_trx<T>(&self, f: impl FnOnce(WorkspaceTransaction) -> T) -> T {
         let doc = self.doc();
         let trx = WorkspaceTransaction {
@@ -364,6 +505,9 @@ impl Serialize for Workspace {
         for space in self.with_trx(|t| t.spaces(|spaces| spaces.collect::<Vec<_>>())) {
             map.serialize_entry(&format!("space:{}", space.space_id()), &space)?;
         }
+        if let Some(templates) = self.with_trx(|t| t.get_exists_space(TEMPLATE_SPACE)) {
+            map.serialize_entry(constants::space::TEMPLATES, &templates)?;
+        }
 
         let trx = self.doc.transact();
         map.serialize_entry("space:meta", &self.metadata.to_json(&trx))?;
@@ -382,6 +526,7 @@ impl Clone for Workspace {
             self.updated.clone(),
             self.metadata.clone(),
             self.plugins.clone(),
+            self.search_index_root.clone(),
         )
     }
 }
@@ -524,6 +669,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn subdoc_test() {
+        let workspace = Workspace::new("test");
+
+        let (guid, subdoc) = workspace.with_trx(|mut t| {
+            t.get_space("space").create(&mut t.trx, "block", "text");
+            let subdoc = t.create_subdoc("page");
+            let guid = subdoc.guid().to_string();
+            assert_eq!(t.create_subdoc("page").guid().to_string(), guid);
+            assert_eq!(t.subdocs(), vec![("page".to_owned(), guid.clone())]);
+            (guid, subdoc)
+        });
+
+        // the space in the subdocument is not a part of the workspace
+        let page = Workspace::from_doc(subdoc, &guid);
+        page.with_trx(|mut t| {
+            t.get_space("page").create(&mut t.trx, "block", "text");
+        });
+        workspace.with_trx(|t| {
+            let spaces = t.spaces(|spaces| spaces.map(|s| s.space_id()).collect::<Vec<_>>());
+            assert_eq!(spaces, vec!["space"]);
+            assert!(t.get_exists_space("page").is_none());
+        });
+
+        // the subdocument is synced by reference
+        let update = workspace.sync_migration(10).unwrap();
+        let doc = Doc::new();
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        let workspace = Workspace::from_doc(doc, "test");
+        workspace.with_trx(|t| {
+            assert_eq!(t.subdocs(), vec![("page".to_owned(), guid.clone())]);
+            let subdoc = t.get_subdoc("page").unwrap();
+            assert!(subdoc.transact().get_map("space:page").is_none());
+        });
+    }
+
     #[test]
     fn scan_doc() {
         let doc = Doc::new();